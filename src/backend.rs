@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+use itertools::EitherOrBoth as ZipEntry;
+use itertools::Itertools;
+#[cfg(feature = "xlib")]
+use x11::xrandr;
+
+use crate::crtc::normalize_positions;
+#[cfg(feature = "xlib")]
+use crate::output::property::PropertyOwner;
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+use crate::{
+    Crtc, Output, Property, ScreenResources, ScreenSize, ScreenSizeRange, XId, XrandrError,
+};
+
+/// The primitive operations everything else is built on. `XHandle` performs
+/// them on an x server, `FakeBackend` on an in-memory screen, so layout code
+/// can be tested without a display.
+pub trait RandrBackend {
+    /// The current crtcs, outputs and modes
+    ///
+    /// # Errors
+    /// * `XrandrError::GetResources` - Getting the resources failed
+    ///
+    fn screen_resources(&mut self) -> Result<ScreenResources, XrandrError>;
+
+    /// The current state of the crtc with xid `xid`
+    ///
+    /// # Errors
+    /// * `XrandrError::GetCrtc(xid)` - There is no such crtc
+    ///
+    fn crtc_info(&mut self, xid: XId) -> Result<Crtc, XrandrError>;
+
+    /// The current state of the output with xid `xid`
+    ///
+    /// # Errors
+    /// * `XrandrError::GetOutputInfo(xid)` - There is no such output
+    ///
+    fn output_info(&mut self, xid: XId) -> Result<Output, XrandrError>;
+
+    /// The properties of the output with xid `xid`, by name
+    ///
+    /// # Errors
+    /// * `XrandrError::GetOutputProp(xid)` - Getting a property failed
+    ///
+    fn output_properties(&mut self, xid: XId) -> Result<IndexMap<String, Property>, XrandrError>;
+
+    /// Configures a crtc as described by `crtc`. Its position, mode,
+    /// rotation and outputs are set in one go.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - The configuration was rejected
+    ///
+    fn set_crtc_config(&mut self, crtc: &Crtc) -> Result<(), XrandrError>;
+
+    /// The current size of the screen
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - The query failed
+    ///
+    fn screen_size(&mut self) -> Result<ScreenSize, XrandrError>;
+
+    /// The smallest and largest screen size that is supported
+    ///
+    /// # Errors
+    /// * `XrandrError::GetScreenSizeRange` - The query failed
+    ///
+    fn screen_size_range(&mut self) -> Result<ScreenSizeRange, XrandrError>;
+
+    /// Resizes the screen. Every enabled crtc has to fit in the new size.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - The size was rejected
+    ///
+    fn set_screen_size(&mut self, size: &ScreenSize) -> Result<(), XrandrError>;
+
+    /// Makes the output with xid `output` the primary one
+    ///
+    /// # Errors
+    /// * `XrandrError::GetOutputInfo(xid)` - There is no such output
+    ///
+    fn set_primary(&mut self, output: XId) -> Result<(), XrandrError>;
+
+    /// Gets every crtc that has a mode
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - Getting the resources or a crtc failed
+    ///
+    fn enabled_crtcs(&mut self) -> Result<Vec<Crtc>, XrandrError> {
+        let res = self.screen_resources()?;
+        let mut crtcs = Vec::new();
+        for xid in &res.crtcs {
+            let crtc = self.crtc_info(*xid)?;
+            if crtc.mode != 0 {
+                crtcs.push(crtc);
+            }
+        }
+        Ok(crtcs)
+    }
+
+    /// Finds an unused crtc that `output` can be shown on
+    ///
+    /// # Errors
+    /// * `XrandrError::NoCrtcAvailable` - Every possible crtc is in use
+    ///
+    fn available_crtc(&mut self, output: &Output) -> Result<Crtc, XrandrError> {
+        let res = self.screen_resources()?;
+        for xid in &res.crtcs {
+            let crtc = self.crtc_info(*xid)?;
+            if crtc.possible.contains(&output.xid) && crtc.outputs.is_empty() {
+                return Ok(crtc);
+            }
+        }
+
+        Err(XrandrError::NoCrtcAvailable)
+    }
+
+    /// Applies some set of altered crtcs
+    /// Due to xrandr's structure, changing one or more crtcs properly can be
+    /// quite complicated. One should therefore call this function on any crtcs
+    /// that you want to change.
+    /// # Arguments
+    /// * `changes`
+    ///   Altered crtcs
+    ///
+    /// # Errors
+    /// * `XrandrError::ScreenTooLarge` - The crtcs do not fit on the largest
+    ///   supported screen
+    /// * `XrandrError::CrtcChanged(xid)` - A crtc changed since it was read
+    /// * `XrandrError::InvalidFilter(name)` - A transform's filter contains
+    ///   a nul byte
+    /// * `XrandrError::_` - various calls to the backend may fail
+    ///
+    fn apply_crtcs(&mut self, changed: &mut [Crtc]) -> Result<(), XrandrError> {
+        // Filter names are passed as C strings, check them before anything
+        // is changed
+        if let Some(crtc) = changed.iter().find(|c| c.transform.filter.contains('\0')) {
+            return Err(XrandrError::InvalidFilter(crtc.transform.filter.clone()));
+        }
+        let old_crtcs = self.enabled_crtcs()?;
+
+        // Construct new crtcs out of the old ones and the new where provided
+        let mut changed_map: HashMap<XId, Crtc> = HashMap::new();
+        changed.iter().cloned().for_each(|c| {
+            changed_map.insert(c.xid, c);
+        });
+
+        let mut new_crtcs: Vec<Crtc> = Vec::new();
+        for crtc in &old_crtcs {
+            match changed_map.remove(&crtc.xid) {
+                None => new_crtcs.push(crtc.clone()),
+                Some(c) => new_crtcs.push(c.clone()),
+            }
+        }
+        new_crtcs.extend(changed_map.drain().map(|(_, v)| v));
+
+        // In case the top-left corner is no longer at (0,0), renormalize
+        normalize_positions(&mut new_crtcs)?;
+        let current_size = self.screen_size()?;
+        let range = self.screen_size_range()?;
+        let mut new_size = ScreenSize::fitting_crtcs(&current_size, &range, &new_crtcs)?;
+
+        // Check the size before changing anything, as the x backend fails
+        // with a (fatal) BadValue if it cannot handle the size
+        new_size.fit_to_range(&range)?;
+
+        // Disable crtcs that do not fit before setting the new size
+        // Note that this should only be crtcs that were changed, but `changed`
+        // contains the already altered crtc, so we have to use `old_crtcs`
+        let mut old_crtcs = old_crtcs;
+        for crtc in &mut old_crtcs {
+            if !new_size.fits_crtc(crtc) {
+                crtc.set_disable();
+                self.set_crtc_config(crtc)?;
+            }
+        }
+        self.set_screen_size(&new_size)?;
+
+        // Find the crtcs that were changed. Done this late to also account
+        // for crtcs that were altered by normalize_positions()
+        let mut to_apply: Vec<&Crtc> = Vec::new();
+        for pair in old_crtcs.iter().zip_longest(new_crtcs.iter()) {
+            match pair {
+                ZipEntry::Both(old, new) => {
+                    assert!(old.xid == new.xid, "invalid new_crtcs");
+                    if new.timestamp < old.timestamp {
+                        return Err(XrandrError::CrtcChanged(new.xid));
+                    }
+                    if new != old {
+                        to_apply.push(new);
+                    }
+                }
+                ZipEntry::Right(new) => to_apply.push(new),
+                ZipEntry::Left(_) => unreachable!("invalid new_crtcs"),
+            }
+        }
+
+        // Move and re-enable the crtcs
+        to_apply
+            .into_iter()
+            .try_for_each(|c| self.set_crtc_config(c))
+    }
+}
+
+#[cfg(feature = "xlib")]
+impl RandrBackend for XHandle {
+    fn screen_resources(&mut self) -> Result<ScreenResources, XrandrError> {
+        ScreenResources::new(self)
+    }
+
+    fn crtc_info(&mut self, xid: XId) -> Result<Crtc, XrandrError> {
+        Crtc::from_xid(self, xid)
+    }
+
+    fn output_info(&mut self, xid: XId) -> Result<Output, XrandrError> {
+        Output::from_xid(self, xid)
+    }
+
+    fn output_properties(&mut self, xid: XId) -> Result<IndexMap<String, Property>, XrandrError> {
+        Property::all(self, PropertyOwner::Output(xid))
+    }
+
+    fn set_crtc_config(&mut self, crtc: &Crtc) -> Result<(), XrandrError> {
+        crtc.apply(self)
+    }
+
+    fn screen_size(&mut self) -> Result<ScreenSize, XrandrError> {
+        Ok(ScreenSize::current(self))
+    }
+
+    fn screen_size_range(&mut self) -> Result<ScreenSizeRange, XrandrError> {
+        ScreenSizeRange::new(self)
+    }
+
+    fn set_screen_size(&mut self, size: &ScreenSize) -> Result<(), XrandrError> {
+        unsafe {
+            xrandr::XRRSetScreenSize(
+                self.sys.as_ptr(),
+                self.root(),
+                size.width,
+                size.height,
+                size.width_mm,
+                size.height_mm,
+            );
+        }
+        Ok(())
+    }
+
+    fn set_primary(&mut self, output: XId) -> Result<(), XrandrError> {
+        unsafe {
+            xrandr::XRRSetOutputPrimary(self.sys.as_ptr(), self.root(), output);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,254 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "xlib")]
+use crate::output::property::{Property, PropertyOwner};
+use crate::output::property::{Range, Ranges, Value, Values};
+use crate::{Output, XrandrError};
+#[cfg(feature = "xlib")]
+use crate::{XHandle, XId};
+
+// Where the kernel lists backlight devices, used if the x driver does not
+// expose the backlight as an output property (e.g. modesetting)
+pub(crate) const SYSFS_ROOT: &str = "/sys/class/backlight";
+
+// Names of the output property drivers use, `BACKLIGHT` is the legacy one
+const PROPERTIES: [&str; 2] = ["Backlight", "BACKLIGHT"];
+
+// Kernel backlight types, from most to least preferred. Firmware interfaces
+// know the panel best, raw ones write to the hardware directly.
+const SYSFS_TYPES: [&str; 3] = ["firmware", "platform", "raw"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BacklightSource {
+    /// The output property with this name
+    Property(String),
+    /// The sysfs directory of the backlight device
+    Sysfs(PathBuf),
+}
+
+// The brightness of a (laptop) panel, as a value in the range the driver
+// supports, and where it was read from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    pub value: i64,
+    pub min: i64,
+    pub max: i64,
+    pub source: BacklightSource,
+}
+
+impl Backlight {
+    /// The brightness in percent of the supported range
+    #[must_use]
+    pub fn percent(&self) -> f64 {
+        if self.max <= self.min {
+            return 100.0;
+        }
+        (self.value - self.min) as f64 * 100.0 / (self.max - self.min) as f64
+    }
+
+    /// The value for a brightness of `percent`, clamped to the range
+    #[cfg(feature = "xlib")]
+    pub(crate) fn value_at(&self, percent: f64) -> i64 {
+        let percent = percent.clamp(0.0, 100.0);
+        let value = self.min + ((self.max - self.min) as f64 * percent / 100.0).round() as i64;
+        value.clamp(self.min, self.max)
+    }
+
+    // From the `Backlight` or `BACKLIGHT` property, if the output has one
+    fn from_property(output: &Output) -> Option<Self> {
+        PROPERTIES.iter().find_map(|name| {
+            let property = output.properties.get(*name)?;
+
+            let value = match &property.value {
+                Value::Integer32(values) => i64::from(*values.first()?),
+                _ => return None,
+            };
+            let (min, max) = match &property.values {
+                Some(Values::Range(Ranges::Integer32(ranges))) => {
+                    let Range { lower, upper } = ranges.first()?;
+                    (i64::from(*lower), i64::from(*upper))
+                }
+                _ => return None,
+            };
+
+            Some(Self {
+                value,
+                min,
+                max,
+                source: BacklightSource::Property((*name).to_string()),
+            })
+        })
+    }
+
+    // From the preferred backlight device in `root`, if there is any
+    fn from_sysfs(root: &Path) -> Result<Option<Self>, XrandrError> {
+        let entries = match fs::read_dir(root) {
+            Ok(entries) => entries,
+            Err(_) => return Ok(None),
+        };
+
+        let mut devices: Vec<(usize, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .map(|entry| {
+                let dir = entry.path();
+                let kind = fs::read_to_string(dir.join("type")).unwrap_or_default();
+                let rank = SYSFS_TYPES
+                    .iter()
+                    .position(|t| *t == kind.trim())
+                    .unwrap_or(SYSFS_TYPES.len());
+                (rank, dir)
+            })
+            .collect();
+        devices.sort();
+
+        let Some((_, dir)) = devices.into_iter().next() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            value: read_number(&dir.join("brightness"))?,
+            min: 0,
+            max: read_number(&dir.join("max_brightness"))?,
+            source: BacklightSource::Sysfs(dir),
+        }))
+    }
+}
+
+/// The backlight of `output`, from its output property or else from the
+/// backlight devices in `sysfs_root`. The latter only applies to internal
+/// panels, as the kernel does not tell which output a device belongs to.
+///
+/// # Errors
+/// * `XrandrError::Io` - A sysfs backlight device could not be read
+///
+pub(crate) fn find(output: &Output, sysfs_root: &Path) -> Result<Option<Backlight>, XrandrError> {
+    if let Some(backlight) = Backlight::from_property(output) {
+        return Ok(Some(backlight));
+    }
+
+    let is_internal = ["eDP", "LVDS", "DSI"]
+        .iter()
+        .any(|prefix| output.name.starts_with(prefix));
+    if !is_internal {
+        return Ok(None);
+    }
+
+    Backlight::from_sysfs(sysfs_root)
+}
+
+/// Sets the brightness of `output` to `percent`, through the same interface
+/// `backlight` was read from
+///
+/// # Errors
+/// * `XrandrError::Io` - Writing to the sysfs device failed
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn set(
+    handle: &mut XHandle,
+    output: XId,
+    backlight: &Backlight,
+    percent: f64,
+) -> Result<(), XrandrError> {
+    let value = backlight.value_at(percent);
+
+    match &backlight.source {
+        BacklightSource::Property(name) => {
+            let value = Value::Integer32(vec![i32::try_from(value).unwrap_or(i32::MAX)]);
+            Property::set(handle, PropertyOwner::Output(output), name, &value)
+        }
+        BacklightSource::Sysfs(dir) => write_sysfs(dir, value),
+    }
+}
+
+#[cfg(feature = "xlib")]
+fn write_sysfs(dir: &Path, value: i64) -> Result<(), XrandrError> {
+    fs::write(dir.join("brightness"), value.to_string()).map_err(XrandrError::Io)
+}
+
+fn read_number(path: &Path) -> Result<i64, XrandrError> {
+    let content = fs::read_to_string(path).map_err(XrandrError::Io)?;
+    content.trim().parse().map_err(|_| {
+        XrandrError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} does not hold a number", path.display()),
+        ))
+    })
+}
+
+#[cfg(all(test, feature = "xlib"))]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    // A fake /sys/class/backlight with the given (name, type, brightness)
+    fn sysfs(name: &str, devices: &[(&str, &str, i64)]) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("xrandr-backlight-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+
+        for (device, kind, brightness) in devices {
+            let dir = root.join(device);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("type"), format!("{}\n", kind)).unwrap();
+            fs::write(dir.join("brightness"), format!("{}\n", brightness)).unwrap();
+            fs::write(dir.join("max_brightness"), "1000\n").unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn reads_backlight_property() {
+        let mut output = fixtures::output(1, "eDP-1");
+        output.properties.insert(
+            "BACKLIGHT".to_string(),
+            Property {
+                name: "BACKLIGHT".to_string(),
+                value: Value::Integer32(vec![30]),
+                values: Some(Values::Range(Ranges::Integer32(vec![Range {
+                    lower: 10,
+                    upper: 110,
+                }]))),
+                is_immutable: false,
+                is_pending: false,
+            },
+        );
+
+        let backlight = find(&output, Path::new("/nonexistent")).unwrap().unwrap();
+        assert_eq!(
+            backlight.source,
+            BacklightSource::Property("BACKLIGHT".to_string())
+        );
+        assert_eq!(backlight.percent(), 20.0);
+        assert_eq!(backlight.value_at(50.0), 60);
+        assert_eq!(backlight.value_at(150.0), 110);
+        assert_eq!(backlight.value_at(-5.0), 10);
+    }
+
+    #[test]
+    fn falls_back_to_sysfs() {
+        let root = sysfs(
+            "fallback",
+            &[
+                ("acpi_video0", "firmware", 250),
+                ("intel_backlight", "raw", 500),
+            ],
+        );
+
+        let backlight = find(&fixtures::output(1, "eDP-1"), &root).unwrap().unwrap();
+        assert_eq!(
+            backlight.source,
+            BacklightSource::Sysfs(root.join("acpi_video0"))
+        );
+        assert_eq!(backlight.percent(), 25.0);
+
+        // External monitors have no sysfs backlight
+        assert!(find(&fixtures::output(2, "DP-1"), &root).unwrap().is_none());
+
+        let dir = root.join("acpi_video0");
+        write_sysfs(&dir, backlight.value_at(75.0)).unwrap();
+        assert_eq!(read_number(&dir.join("brightness")).unwrap(), 750);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,406 @@
+use std::process::ExitCode;
+
+use xrandr::{
+    ColorAdjustment, Mode, Output, Relation, Rotation, ScreenResources, Transform, XHandle, XId,
+    XrandrError,
+};
+
+const USAGE: &str = "usage: xrandr [options]
+  --verbose
+  --output <output>
+      --auto
+      --mode <mode>
+      --rate <rate>
+      --pos <x>x<y>
+      --rotate normal,inverted,left,right
+      --left-of <output>
+      --right-of <output>
+      --above <output>
+      --below <output>
+      --same-as <output>
+      --scale <x>[x<y>]
+      --brightness <value>
+      --primary
+      --off";
+
+// Everything to change about one `--output`, in the order it is applied
+#[derive(Debug, Default, PartialEq)]
+struct OutputArgs {
+    name: String,
+    off: bool,
+    auto: bool,
+    mode: Option<String>,
+    rate: Option<f64>,
+    rotate: Option<Rotation>,
+    scale: Option<(f64, f64)>,
+    pos: Option<(i32, i32)>,
+    relation: Option<(Relation, String)>,
+    primary: bool,
+    brightness: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Args {
+    verbose: bool,
+    help: bool,
+    outputs: Vec<OutputArgs>,
+}
+
+// Parses `<a><sep><b>`, e.g. `1920x0`
+fn pair<T: std::str::FromStr>(value: &str, sep: char) -> Option<(T, T)> {
+    let (a, b) = value.split_once(sep)?;
+    Some((a.parse().ok()?, b.parse().ok()?))
+}
+
+fn rotation(value: &str) -> Option<Rotation> {
+    match value {
+        "normal" => Some(Rotation::Normal),
+        "left" => Some(Rotation::Left),
+        "inverted" => Some(Rotation::Inverted),
+        "right" => Some(Rotation::Right),
+        _ => None,
+    }
+}
+
+fn relation(option: &str) -> Option<Relation> {
+    match option {
+        "--left-of" => Some(Relation::LeftOf),
+        "--right-of" => Some(Relation::RightOf),
+        "--above" => Some(Relation::Above),
+        "--below" => Some(Relation::Below),
+        "--same-as" => Some(Relation::SameAs),
+        _ => None,
+    }
+}
+
+fn takes_value(option: &str) -> bool {
+    let options = [
+        "--mode",
+        "--rate",
+        "--refresh",
+        "--pos",
+        "--rotate",
+        "--orientation",
+        "--scale",
+        "--brightness",
+    ];
+    options.contains(&option) || relation(option).is_some()
+}
+
+/// Parses the command line, without the program name
+///
+/// # Errors
+/// A message for the user, if an option is unknown, misplaced or has an
+/// invalid value
+///
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.into_iter();
+
+    while let Some(option) = args.next() {
+        match option.as_str() {
+            "--verbose" => parsed.verbose = true,
+            "-q" | "--query" | "--current" => {}
+            "-h" | "--help" => parsed.help = true,
+            "--output" => {
+                let name = args.next().ok_or("--output requires an argument")?;
+                parsed.outputs.push(OutputArgs {
+                    name,
+                    ..OutputArgs::default()
+                });
+            }
+            _ => {
+                let misplaced = if option.starts_with("--") {
+                    format!("{} must be preceded by --output", option)
+                } else {
+                    format!("unrecognized option '{}'", option)
+                };
+                let output = parsed.outputs.last_mut().ok_or(misplaced)?;
+                parse_output_option(output, &option, &mut args)?;
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_output_option(
+    output: &mut OutputArgs,
+    option: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<(), String> {
+    match option {
+        "--off" => output.off = true,
+        "--auto" => output.auto = true,
+        "--primary" => output.primary = true,
+        _ if !takes_value(option) => return Err(format!("unrecognized option '{}'", option)),
+        _ => {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} requires an argument", option))?;
+            let invalid = || format!("invalid value '{}' for {}", value, option);
+
+            match option {
+                "--mode" => output.mode = Some(value.clone()),
+                "--rate" | "--refresh" => output.rate = Some(value.parse().map_err(|_| invalid())?),
+                "--pos" => output.pos = Some(pair(&value, 'x').ok_or_else(invalid)?),
+                "--rotate" | "--orientation" => {
+                    output.rotate = Some(rotation(&value).ok_or_else(invalid)?);
+                }
+                "--scale" => {
+                    let scale = match value.parse() {
+                        Ok(both) => (both, both),
+                        Err(_) => pair(&value, 'x').ok_or_else(invalid)?,
+                    };
+                    output.scale = Some(scale);
+                }
+                "--brightness" => {
+                    output.brightness = Some(value.parse().map_err(|_| invalid())?);
+                }
+                _ => output.relation = relation(option).map(|r| (r, value.clone())),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Picks the mode called `name` (or with xid `name`, e.g. `0x4a`) among
+/// `supported`, which are in the order of the output's modes. Like xrandr,
+/// the one with the rate closest to `rate` wins, or else the first one.
+fn pick_mode<'a>(
+    modes: &'a [Mode],
+    supported: &[XId],
+    name: &str,
+    rate: Option<f64>,
+) -> Option<&'a Mode> {
+    let mut candidates = supported
+        .iter()
+        .filter_map(|xid| modes.iter().find(|m| m.xid == *xid))
+        .filter(|m| m.name == name || format!("0x{:x}", m.xid) == name);
+
+    match rate {
+        None => candidates.next(),
+        Some(rate) => {
+            candidates.min_by(|a, b| (a.rate - rate).abs().total_cmp(&(b.rate - rate).abs()))
+        }
+    }
+}
+
+fn find_output(handle: &mut XHandle, name: &str) -> Result<Output, XrandrError> {
+    handle
+        .all_outputs()?
+        .into_iter()
+        .find(|o| o.name == name)
+        .ok_or_else(|| XrandrError::UnknownOutput(name.to_string()))
+}
+
+// Applies the options of one `--output`, reading the output again after
+// every step, since each one can change its crtc
+fn apply(handle: &mut XHandle, args: &OutputArgs, verbose: bool) -> Result<(), XrandrError> {
+    let log = |step: &str| {
+        if verbose {
+            println!("{}: {}", args.name, step);
+        }
+    };
+    let mut output = find_output(handle, &args.name)?;
+
+    if args.off || (args.auto && !output.connected) {
+        log("disabling");
+        return handle.disable(&output);
+    }
+
+    if args.auto || args.mode.is_some() || args.rate.is_some() {
+        if output.crtc.is_none() {
+            log("enabling");
+            handle.enable(&output, &Rotation::Normal)?;
+            output = find_output(handle, &args.name)?;
+        }
+
+        let modes = ScreenResources::new(handle)?.modes;
+        let name = match (&args.mode, output.current_mode) {
+            (Some(name), _) => name.clone(),
+            (None, Some(current)) if !args.auto => modes
+                .iter()
+                .find(|m| m.xid == current)
+                .map(|m| m.name.clone())
+                .unwrap_or_default(),
+            _ => {
+                let preferred = output.preferred_modes.first();
+                let preferred = preferred.ok_or(XrandrError::NoPreferredModes(output.xid))?;
+                format!("0x{:x}", preferred)
+            }
+        };
+        let mode = pick_mode(&modes, &output.modes, &name, args.rate)
+            .ok_or_else(|| XrandrError::UnknownMode(output.name.clone(), name.clone()))?;
+
+        log(&format!("setting mode {} ({:.2} Hz)", mode.name, mode.rate));
+        handle.set_mode(&output, mode)?;
+        output = find_output(handle, &args.name)?;
+    }
+
+    if let Some(rotation) = &args.rotate {
+        log(&format!("rotating {:?}", rotation));
+        handle.set_rotation(&output, rotation)?;
+        output = find_output(handle, &args.name)?;
+    }
+
+    if let Some((x, y)) = args.scale {
+        log(&format!("scaling {}x{}", x, y));
+        handle.set_transform(&output, &Transform::scale(x, y))?;
+        output = find_output(handle, &args.name)?;
+    }
+
+    if let Some((x, y)) = args.pos {
+        log(&format!("moving to {}x{}", x, y));
+        handle.set_position_absolute(&output, x, y)?;
+        output = find_output(handle, &args.name)?;
+    }
+
+    if let Some((relation, target)) = &args.relation {
+        log(&format!("placing {:?} {}", relation, target));
+        let target = find_output(handle, target)?;
+        handle.set_position(&output, relation, &target)?;
+        output = find_output(handle, &args.name)?;
+    }
+
+    if args.primary {
+        log("making primary");
+        handle.set_primary(&output)?;
+    }
+
+    if let Some(brightness) = args.brightness {
+        log(&format!("setting brightness {}", brightness));
+        let adjustment = ColorAdjustment::new().with_brightness(brightness);
+        handle.adjust_colors(&output, &adjustment)?;
+    }
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), XrandrError> {
+    let mut handle = XHandle::open()?;
+    if args.outputs.is_empty() {
+        let snapshot = handle.snapshot()?;
+        if args.verbose {
+            print!("{}", snapshot.verbose());
+        } else {
+            print!("{}", snapshot.query());
+        }
+        return Ok(());
+    }
+
+    args.outputs
+        .iter()
+        .try_for_each(|output| apply(&mut handle, output, args.verbose))
+}
+
+fn main() -> ExitCode {
+    let args = match parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("xrandr: {}\n{}", message, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("xrandr: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Result<Args, String> {
+        parse(line.split_whitespace().map(String::from))
+    }
+
+    fn mode(xid: XId, name: &str, rate: f64) -> Mode {
+        Mode {
+            xid,
+            width: 1920,
+            height: 1080,
+            dot_clock: 0,
+            hsync_tart: 0,
+            hsync_end: 0,
+            htotal: 0,
+            hskew: 0,
+            vsync_start: 0,
+            vsync_end: 0,
+            vtotal: 0,
+            name: name.to_string(),
+            flags: 0,
+            rate,
+        }
+    }
+
+    #[test]
+    fn parses_options_per_output() {
+        let parsed = args(
+            "--verbose --output DP-1 --mode 1920x1080 --rate 144 --left-of eDP-1 --primary \
+             --output eDP-1 --scale 1.5 --rotate left --pos 0x1080 --brightness 0.8",
+        )
+        .unwrap();
+
+        assert!(parsed.verbose);
+        assert_eq!(
+            parsed.outputs[0],
+            OutputArgs {
+                name: "DP-1".to_string(),
+                mode: Some("1920x1080".to_string()),
+                rate: Some(144.0),
+                relation: Some((Relation::LeftOf, "eDP-1".to_string())),
+                primary: true,
+                ..OutputArgs::default()
+            }
+        );
+        assert_eq!(parsed.outputs[1].scale, Some((1.5, 1.5)));
+        assert_eq!(parsed.outputs[1].rotate, Some(Rotation::Left));
+        assert_eq!(parsed.outputs[1].pos, Some((0, 1080)));
+        assert_eq!(parsed.outputs[1].brightness, Some(0.8));
+    }
+
+    #[test]
+    fn rejects_invalid_options() {
+        assert_eq!(
+            args("--mode 1920x1080").unwrap_err(),
+            "--mode must be preceded by --output"
+        );
+        assert_eq!(
+            args("--output DP-1 --rotate sideways").unwrap_err(),
+            "invalid value 'sideways' for --rotate"
+        );
+        assert_eq!(
+            args("--output DP-1 --pos").unwrap_err(),
+            "--pos requires an argument"
+        );
+        assert_eq!(
+            args("--output DP-1 --frobnicate").unwrap_err(),
+            "unrecognized option '--frobnicate'"
+        );
+    }
+
+    #[test]
+    fn picks_closest_rate() {
+        let modes = [
+            mode(1, "1920x1080", 60.0),
+            mode(2, "1920x1080", 143.9),
+            mode(3, "1920x1080", 120.0),
+            mode(4, "1280x720", 60.0),
+        ];
+        let supported = [3, 1, 2];
+
+        let pick = |name, rate| pick_mode(&modes, &supported, name, rate).map(|m| m.xid);
+        assert_eq!(pick("1920x1080", None), Some(3));
+        assert_eq!(pick("1920x1080", Some(144.0)), Some(2));
+        assert_eq!(pick("0x1", None), Some(1));
+        assert_eq!(pick("1280x720", None), None);
+    }
+}
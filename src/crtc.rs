@@ -1,17 +1,29 @@
-use crate::screen_resources::ScreenResourcesHandle;
+use crate::panning::Panning;
+#[cfg(feature = "xlib")]
+use crate::screen_resources::{ScreenResources, ScreenResourcesHandle};
+use crate::transform::Transform;
+use crate::Mode;
+#[cfg(feature = "xlib")]
 use crate::XHandle;
 use crate::XId;
 use crate::XTime;
 use crate::XrandrError;
+#[cfg(feature = "xlib")]
 use crate::CURRENT_TIME;
+#[cfg(feature = "xlib")]
 use std::ptr;
+#[cfg(feature = "xlib")]
 use std::slice;
 
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
+#[cfg(feature = "xlib")]
 use x11::xrandr;
 
 // A Crtc can display a mode in one of 4 rotations
 #[derive(PartialEq, Eq, Copy, Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Rotation {
     Normal = 1,
     Left = 2,
@@ -19,11 +31,16 @@ pub enum Rotation {
     Right = 8,
 }
 
+// The x backend stores rotation and reflection in the same bitmask
+const ROTATION_MASK: u16 = 0x0f;
+const REFLECTION_MASK: u16 = 0x30;
+
 impl TryFrom<u16> for Rotation {
     type Error = XrandrError;
 
+    /// Decodes the rotation bits of `r`, ignoring any reflection bits
     fn try_from(r: u16) -> Result<Self, Self::Error> {
-        match r {
+        match r & ROTATION_MASK {
             1 => Ok(Rotation::Normal),
             2 => Ok(Rotation::Left),
             4 => Ok(Rotation::Inverted),
@@ -33,8 +50,59 @@ impl TryFrom<u16> for Rotation {
     }
 }
 
+// On top of its rotation, a Crtc can mirror its image along the x axis
+// (left and right are swapped), the y axis, or both
+#[derive(PartialEq, Eq, Copy, Debug, Clone, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Reflection {
+    #[default]
+    Normal = 0,
+    X = 16,
+    Y = 32,
+    XY = 48,
+}
+
+impl From<u16> for Reflection {
+    /// Decodes the reflection bits of `r`, ignoring any rotation bits
+    fn from(r: u16) -> Self {
+        match r & REFLECTION_MASK {
+            16 => Reflection::X,
+            32 => Reflection::Y,
+            48 => Reflection::XY,
+            _ => Reflection::Normal,
+        }
+    }
+}
+
+// The rotations in a bitmask of supported rotations and reflections
+pub(crate) fn supported_rotations(bits: u16) -> Vec<Rotation> {
+    [
+        Rotation::Normal,
+        Rotation::Left,
+        Rotation::Inverted,
+        Rotation::Right,
+    ]
+    .into_iter()
+    .filter(|r| bits & *r as u16 != 0)
+    .collect()
+}
+
+// The reflections in a bitmask of supported rotations and reflections. Not
+// reflecting is always supported, reflecting along both axes needs both bits.
+pub(crate) fn supported_reflections(bits: u16) -> Vec<Reflection> {
+    [
+        Reflection::Normal,
+        Reflection::X,
+        Reflection::Y,
+        Reflection::XY,
+    ]
+    .into_iter()
+    .filter(|r| bits & *r as u16 == *r as u16)
+    .collect()
+}
+
 // A Crtc can be positioned relative to another one in one of five directions
-#[derive(Copy, Debug, Clone)]
+#[derive(Copy, Debug, Clone, PartialEq, Eq)]
 pub enum Relation {
     LeftOf,
     RightOf,
@@ -43,9 +111,25 @@ pub enum Relation {
     SameAs,
 }
 
+// When placing a Crtc next to another one, the edges perpendicular to the
+// relation can be aligned in one of three ways. For `LeftOf`/`RightOf`,
+// `Start` aligns the top edges and `End` the bottom edges. For
+// `Above`/`Below`, `Start` aligns the left edges and `End` the right edges.
+// Alignment is ignored for `SameAs`.
+#[derive(Copy, Debug, Clone, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Start,
+    Center,
+    End,
+}
+
 // Crtcs define a region of pixels you can see. The Crtc controls the size
 // and timing of the signal. To this end, the Crtc struct in xrandr maintains
 // a list of attributes that usually correspond to a physical display.
+// `width` and `height` are those of the mode after rotation. The area the
+// crtc covers on the screen also depends on `transform`, see `screen_size()`,
+// and on `panning`, see `max_coordinates()`.
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct Crtc {
     pub xid: XId,
@@ -56,35 +140,45 @@ pub struct Crtc {
     pub height: u32,
     pub mode: XId,
     pub rotation: Rotation,
+    pub reflection: Reflection,
     pub outputs: Vec<XId>,
-    pub rotations: u16,
+    pub rotations: Vec<Rotation>,
+    pub reflections: Vec<Reflection>,
     pub possible: Vec<XId>,
+    pub transform: Transform,
+    pub panning: Option<Panning>,
 }
 
 /// Normalizes a set of Crtcs by making sure the top left pixel of the screen
 /// is at (0,0). This is needed after changing positions/rotations.
-pub(crate) fn normalize_positions(crtcs: &mut [Crtc]) {
+///
+/// # Errors
+/// * `XrandrError::PositionOutOfRange(xid)` - A crtc cannot be moved that far
+pub(crate) fn normalize_positions(crtcs: &mut [Crtc]) -> Result<(), XrandrError> {
     if crtcs.is_empty() {
-        return;
+        return Ok(());
     };
 
-    let left = crtcs.iter().map(|p| p.x).min().unwrap();
-    let top = crtcs.iter().map(|p| p.y).min().unwrap();
+    let left = crtcs.iter().map(|p| p.min_coordinates().0).min().unwrap();
+    let top = crtcs.iter().map(|p| p.min_coordinates().1).min().unwrap();
     if (top, left) == (0, 0) {
-        return;
+        return Ok(());
     };
 
     for c in crtcs.iter_mut() {
-        c.offset((-left, -top));
+        c.offset((-left, -top))?;
     }
+    Ok(())
 }
 
 // A wrapper that drops the pointer if it goes out of scope.
 // Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
 struct CrtcHandle {
     ptr: ptr::NonNull<xrandr::XRRCrtcInfo>,
 }
 
+#[cfg(feature = "xlib")]
 impl CrtcHandle {
     fn new(handle: &mut XHandle, xid: XId) -> Result<Self, XrandrError> {
         let res = ScreenResourcesHandle::new(handle)?;
@@ -97,6 +191,7 @@ impl CrtcHandle {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl Drop for CrtcHandle {
     fn drop(&mut self) {
         unsafe { xrandr::XRRFreeCrtcInfo(self.ptr.as_ptr()) };
@@ -120,6 +215,7 @@ impl Crtc {
     /// let mon1 = xhandle.monitors()?[0];
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn from_xid(handle: &mut XHandle, xid: XId) -> Result<Self, XrandrError> {
         let crtc_info = CrtcHandle::new(handle, xid)?;
 
@@ -138,8 +234,22 @@ impl Crtc {
             possible,
         } = unsafe { crtc_info.ptr.as_ref() };
 
+        let reflection = Reflection::from(*rotation);
         let rotation = Rotation::try_from(*rotation)?;
 
+        // The reported size includes the transformation, but we keep track
+        // of the size of the (rotated) mode
+        let transform = Transform::get(handle, xid);
+        let (width, height) = if transform.is_identity() || *mode == 0 {
+            (*width, *height)
+        } else {
+            let mode = ScreenResources::new(handle)?.mode(*mode)?;
+            match rotation {
+                Rotation::Normal | Rotation::Inverted => (mode.width, mode.height),
+                Rotation::Left | Rotation::Right => (mode.height, mode.width),
+            }
+        };
+
         let outputs = unsafe { slice::from_raw_parts(*outputs, *noutput as usize) };
 
         let possible = unsafe { slice::from_raw_parts(*possible, *npossible as usize) };
@@ -149,18 +259,22 @@ impl Crtc {
             timestamp: *timestamp,
             x: *x,
             y: *y,
-            width: *width,
-            height: *height,
+            width,
+            height,
             mode: *mode,
             rotation,
+            reflection,
             outputs: outputs.to_vec(),
-            rotations: *rotations,
+            rotations: supported_rotations(*rotations),
+            reflections: supported_reflections(*rotations),
             possible: possible.to_vec(),
+            transform,
+            panning: Panning::get(handle, xid)?,
         })
     }
 
-    /// Apply the current fields of this crtc. `&mut self` needed to create a
-    /// mut pointer to outputs, which lib-xrandr seems to require.
+    /// Apply the current fields of this crtc. lib-xrandr takes the outputs
+    /// as a mut pointer, but only reads them, so `&self` is cast to one.
     /// # Examples
     /// ```
     /// // Sets new mode on the crtc of some output
@@ -169,12 +283,24 @@ impl Crtc {
     /// crtc.apply(xhandle)
     /// ```
     ///
-    pub(crate) fn apply(&mut self, handle: &mut XHandle) -> Result<(), XrandrError> {
+    #[cfg(feature = "xlib")]
+    pub(crate) fn apply(&self, handle: &mut XHandle) -> Result<(), XrandrError> {
+        // The x backend does not write to the outputs
         let outputs = match self.outputs.len() {
             0 => std::ptr::null_mut(),
-            _ => self.outputs.as_mut_ptr(),
+            _ => self.outputs.as_ptr().cast_mut(),
         };
 
+        // The transform is only pending until the crtc is configured
+        if self.mode != 0 {
+            self.transform.set(handle, self.xid)?;
+        }
+
+        // An empty panning area turns panning off
+        if self.panning.is_none() && self.mode != 0 && Panning::get(handle, self.xid)?.is_some() {
+            Panning::default().set(handle, self.xid)?;
+        }
+
         let res = ScreenResourcesHandle::new(handle)?;
 
         unsafe {
@@ -186,12 +312,20 @@ impl Crtc {
                 self.x,
                 self.y,
                 self.mode,
-                self.rotation as u16,
+                self.rotation as u16 | self.reflection as u16,
                 outputs,
                 i32::try_from(self.outputs.len()).unwrap(),
             );
         }
 
+        // Like the transform, panning needs the crtc to be configured, but
+        // here it has to be configured first
+        if let Some(panning) = &self.panning {
+            if self.mode != 0 {
+                panning.set(handle, self.xid)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -200,8 +334,13 @@ impl Crtc {
     pub(crate) fn set_disable(&mut self) {
         self.x = 0;
         self.y = 0;
+        self.width = 0;
+        self.height = 0;
         self.mode = 0;
         self.rotation = Rotation::Normal;
+        self.reflection = Reflection::Normal;
+        self.transform = Transform::identity();
+        self.panning = None;
         self.outputs.clear();
     }
 
@@ -221,28 +360,176 @@ impl Crtc {
         }
     }
 
-    /// The most down an dright coordinates that this crtc uses
-    pub(crate) fn max_coordinates(&self) -> (i32, i32) {
-        assert!(
-            self.x >= 0 && self.y >= 0,
-            "max_coordinates should be called on normalized crtc"
-        );
+    /// The size of the area this crtc covers on the screen, i.e. after
+    /// rotation and transformation
+    #[must_use]
+    pub fn screen_size(&self) -> (u32, u32) {
+        self.transform.transformed_size(self.width, self.height)
+    }
+
+    /// The position this crtc gets when placed relative to `other`. Both
+    /// sizes are taken as they appear on screen, see `screen_size()`.
+    ///
+    /// # Arguments
+    /// * `gap` - Pixels between the two crtcs. Negative values make them
+    ///   overlap. Ignored for `Relation::SameAs`.
+    pub(crate) fn relative_position(
+        &self,
+        relation: Relation,
+        alignment: Alignment,
+        gap: i32,
+        other: &Crtc,
+    ) -> (i32, i32) {
+        let (w, h) = self.screen_size();
+        let (rel_w, rel_h) = other.screen_size();
+        let (w, h, rel_w, rel_h) = (w as i32, h as i32, rel_w as i32, rel_h as i32);
+        let (rel_x, rel_y) = (other.x, other.y);
+
+        // Offset along the edge that is shared with `other`
+        let align = |size: i32, rel_size: i32| match alignment {
+            Alignment::Start => 0,
+            Alignment::Center => (rel_size - size) / 2,
+            Alignment::End => rel_size - size,
+        };
 
-        // let (w, h) = self.rot_size();
-        // I think crtcs have this incorporated in their width/height fields
-        (self.x + self.width as i32, self.y + self.height as i32)
+        match relation {
+            Relation::LeftOf => (rel_x - w - gap, rel_y + align(h, rel_h)),
+            Relation::RightOf => (rel_x + rel_w + gap, rel_y + align(h, rel_h)),
+            Relation::Above => (rel_x + align(w, rel_w), rel_y - h - gap),
+            Relation::Below => (rel_x + align(w, rel_w), rel_y + rel_h + gap),
+            Relation::SameAs => (rel_x, rel_y),
+        }
     }
 
-    /// Creates a new Crtc that is offset (.x and .y) fields, by offset param
-    pub(crate) fn offset(&mut self, offset: (i32, i32)) {
-        let x = i32::checked_add(self.x, offset.0)
-            .expect("Display should not be positioned outside canvas range");
-        let y = i32::checked_add(self.y, offset.1)
-            .expect("Display should not be positioned outside canvas range");
+    /// Sets a new mode, and the width and height it has with the current
+    /// rotation
+    pub fn set_mode(&mut self, mode: &Mode) {
+        self.mode = mode.xid;
+        (self.width, self.height) = match self.rotation {
+            Rotation::Normal | Rotation::Inverted => (mode.width, mode.height),
+            Rotation::Left | Rotation::Right => (mode.height, mode.width),
+        };
+    }
+
+    /// The most up and left coordinates that this crtc uses, including its
+    /// panning area
+    pub(crate) fn min_coordinates(&self) -> (i32, i32) {
+        match &self.panning {
+            Some(p) => (self.x.min(p.left), self.y.min(p.top)),
+            None => (self.x, self.y),
+        }
+    }
+
+    /// The most down and right coordinates that this crtc uses, including
+    /// its panning area
+    pub(crate) fn max_coordinates(&self) -> (i32, i32) {
+        let (width, height) = self.screen_size();
+        let width = i32::try_from(width).unwrap_or(i32::MAX);
+        let height = i32::try_from(height).unwrap_or(i32::MAX);
+        let (x, y) = (self.x.saturating_add(width), self.y.saturating_add(height));
+
+        match &self.panning {
+            Some(p) => {
+                let (pan_x, pan_y) = p.max_coordinates();
+                (x.max(pan_x), y.max(pan_y))
+            }
+            None => (x, y),
+        }
+    }
 
-        assert!(x >= 0 && y >= 0, "Invalid coordinates after offset");
+    /// Moves this crtc by `offset`
+    ///
+    /// # Errors
+    /// * `XrandrError::PositionOutOfRange(xid)` - The new position does not
+    ///   fit in an `i32`
+    pub(crate) fn offset(&mut self, offset: (i32, i32)) -> Result<(), XrandrError> {
+        let x = i32::checked_add(self.x, offset.0);
+        let y = i32::checked_add(self.y, offset.1);
+
+        let panning = match &self.panning {
+            Some(p) => Some(p.offset(offset)),
+            None => Some(None),
+        };
+
+        let (Some(x), Some(y), Some(panning)) = (x, y, panning) else {
+            return Err(XrandrError::PositionOutOfRange(self.xid));
+        };
 
         self.x = x;
         self.y = y;
+        self.panning = panning;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn crtc(x: i32, y: i32, width: u32, height: u32) -> Crtc {
+        fixtures::crtc(1, x, y, width, height)
+    }
+
+    #[test]
+    fn aligns_relative_positions() {
+        let small = crtc(0, 0, 1920, 1080);
+        let large = crtc(100, 100, 2560, 1440);
+
+        let pos =
+            |relation, alignment, gap| small.relative_position(relation, alignment, gap, &large);
+
+        assert_eq!(pos(Relation::LeftOf, Alignment::Start, 0), (-1820, 100));
+        assert_eq!(pos(Relation::LeftOf, Alignment::Center, 0), (-1820, 280));
+        assert_eq!(pos(Relation::RightOf, Alignment::End, 0), (2660, 460));
+        assert_eq!(pos(Relation::RightOf, Alignment::End, 20), (2680, 460));
+        assert_eq!(pos(Relation::Above, Alignment::Center, -10), (420, -970));
+        assert_eq!(pos(Relation::Below, Alignment::End, 0), (740, 1540));
+        assert_eq!(pos(Relation::SameAs, Alignment::End, 50), (100, 100));
+    }
+
+    #[test]
+    fn set_mode_accounts_for_rotation() {
+        let mut rotated = crtc(0, 0, 1080, 1920);
+        rotated.rotation = Rotation::Left;
+
+        let mode = fixtures::mode(2, 2560, 1440, 60.0);
+        rotated.set_mode(&mode);
+
+        assert_eq!(
+            (rotated.mode, rotated.width, rotated.height),
+            (2, 1440, 2560)
+        );
+    }
+
+    #[test]
+    fn panning_extends_coordinates() {
+        let mut crtcs = [crtc(1280, 0, 1280, 800), crtc(2560, 0, 1920, 1080)];
+        crtcs[0].panning = Some(Panning::new(640, 0, 1920, 1200));
+
+        normalize_positions(&mut crtcs).unwrap();
+        assert_eq!(crtcs[0].min_coordinates(), (0, 0));
+        assert_eq!(crtcs[0].max_coordinates(), (1920, 1200));
+        assert_eq!((crtcs[1].x, crtcs[1].y), (1920, 0));
+    }
+
+    #[test]
+    fn decodes_rotation_and_reflection() {
+        // Rotated left and reflected along the y axis
+        let bits = Rotation::Left as u16 | Reflection::Y as u16;
+        assert_eq!(Rotation::try_from(bits).unwrap(), Rotation::Left);
+        assert_eq!(Reflection::from(bits), Reflection::Y);
+        assert!(Rotation::try_from(Reflection::X as u16).is_err());
+
+        let supported = Rotation::Normal as u16 | Rotation::Inverted as u16 | 0x30;
+        assert_eq!(
+            supported_rotations(supported),
+            vec![Rotation::Normal, Rotation::Inverted]
+        );
+        assert_eq!(supported_reflections(supported).len(), 4);
+        assert_eq!(
+            supported_reflections(0x1f),
+            vec![Reflection::Normal, Reflection::X]
+        );
     }
 }
@@ -0,0 +1,447 @@
+use indexmap::IndexMap;
+
+use crate::crtc::{Crtc, Reflection, Rotation};
+use crate::transform::Transform;
+use crate::{
+    Mode, Output, Property, RandrBackend, ScreenResources, ScreenSize, ScreenSizeRange, Snapshot,
+    XId, XTime, XrandrError,
+};
+
+// The dpi of a new screen, used to derive physical sizes
+const DPI: f64 = 96.0;
+
+// An in-memory screen that behaves like a (well-behaved) x server, so code
+// built on this crate can be tested without a display. Modes, crtcs and
+// outputs are added up front; configuring them checks what the x server
+// would check, and advances the timestamps.
+#[derive(Debug)]
+pub struct FakeBackend {
+    timestamp: XTime,
+    config_timestamp: XTime,
+    modes: Vec<Mode>,
+    crtcs: Vec<Crtc>,
+    outputs: Vec<Output>,
+    size: ScreenSize,
+    range: ScreenSizeRange,
+    next_xid: XId,
+}
+
+impl FakeBackend {
+    /// An empty screen of the minimum size of `range`
+    #[must_use]
+    pub fn new(range: ScreenSizeRange) -> Self {
+        Self {
+            timestamp: 1,
+            config_timestamp: 1,
+            modes: Vec::new(),
+            crtcs: Vec::new(),
+            outputs: Vec::new(),
+            size: ScreenSize {
+                width: range.min_width,
+                width_mm: millimeters(range.min_width),
+                height: range.min_height,
+                height_mm: millimeters(range.min_height),
+            },
+            range,
+            next_xid: 1,
+        }
+    }
+
+    /// Adds a mode of `width`x`height` at `rate` Hz, and returns its xid
+    pub fn add_mode(&mut self, width: u32, height: u32, rate: f64) -> XId {
+        let xid = self.xid();
+        self.modes.push(Mode {
+            xid,
+            width,
+            height,
+            dot_clock: (f64::from(width) * f64::from(height) * rate).round() as u64,
+            hsync_tart: width,
+            hsync_end: width,
+            htotal: width,
+            hskew: 0,
+            vsync_start: height,
+            vsync_end: height,
+            vtotal: height,
+            name: format!("{}x{}", width, height),
+            flags: 0,
+            rate,
+        });
+        xid
+    }
+
+    /// Adds a disabled crtc, and returns its xid
+    pub fn add_crtc(&mut self) -> XId {
+        let xid = self.xid();
+        self.crtcs.push(Crtc {
+            xid,
+            timestamp: self.timestamp,
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            mode: 0,
+            rotation: Rotation::Normal,
+            reflection: Reflection::Normal,
+            outputs: Vec::new(),
+            rotations: vec![
+                Rotation::Normal,
+                Rotation::Left,
+                Rotation::Inverted,
+                Rotation::Right,
+            ],
+            reflections: vec![Reflection::Normal],
+            possible: Vec::new(),
+            transform: Transform::identity(),
+            panning: None,
+        });
+        xid
+    }
+
+    /// Adds a connected, disabled output that supports `modes`, the first
+    /// of which is preferred. It can use every crtc added so far.
+    pub fn add_output(&mut self, name: &str, modes: &[XId]) -> XId {
+        let xid = self.xid();
+        for crtc in &mut self.crtcs {
+            crtc.possible.push(xid);
+        }
+
+        self.outputs.push(Output {
+            xid,
+            properties: IndexMap::new(),
+            timestamp: self.timestamp,
+            is_primary: false,
+            crtc: None,
+            name: name.to_string(),
+            mm_width: 0,
+            mm_height: 0,
+            connected: true,
+            subpixel_order: 0,
+            crtcs: self.crtcs.iter().map(|c| c.xid).collect(),
+            clones: Vec::new(),
+            modes: modes.to_vec(),
+            preferred_modes: modes.iter().take(1).copied().collect(),
+            current_mode: None,
+        });
+        xid
+    }
+
+    /// Sets a property of an output, e.g. `non-desktop`
+    ///
+    /// # Errors
+    /// * `XrandrError::GetOutputInfo(xid)` - There is no such output
+    ///
+    pub fn set_output_property(
+        &mut self,
+        output: XId,
+        property: Property,
+    ) -> Result<(), XrandrError> {
+        let output = self.output_mut(output)?;
+        output.properties.insert(property.name.clone(), property);
+        Ok(())
+    }
+
+    fn xid(&mut self) -> XId {
+        self.next_xid += 1;
+        self.next_xid - 1
+    }
+
+    fn output_mut(&mut self, xid: XId) -> Result<&mut Output, XrandrError> {
+        self.outputs
+            .iter_mut()
+            .find(|o| o.xid == xid)
+            .ok_or(XrandrError::GetOutputInfo(xid))
+    }
+
+    // Checks the configuration of `crtc` like the x server does
+    fn check_crtc(&self, crtc: &Crtc) -> Result<(), XrandrError> {
+        let current = self
+            .crtcs
+            .iter()
+            .find(|c| c.xid == crtc.xid)
+            .ok_or(XrandrError::GetCrtc(crtc.xid))?;
+        if crtc.mode == 0 {
+            return Ok(());
+        }
+
+        self.modes
+            .iter()
+            .find(|m| m.xid == crtc.mode)
+            .ok_or(XrandrError::GetModeInfo(crtc.mode))?;
+
+        let outputs_fit = crtc.outputs.iter().all(|xid| {
+            self.outputs
+                .iter()
+                .find(|o| o.xid == *xid)
+                .is_some_and(|o| o.modes.contains(&crtc.mode) && o.crtcs.contains(&crtc.xid))
+        });
+        let (max_x, max_y) = crtc.max_coordinates();
+
+        if crtc.outputs.is_empty()
+            || !outputs_fit
+            || !current.rotations.contains(&crtc.rotation)
+            || crtc.x < 0
+            || crtc.y < 0
+            || max_x > self.size.width
+            || max_y > self.size.height
+        {
+            return Err(XrandrError::InvalidCrtcConfig(crtc.xid));
+        }
+        Ok(())
+    }
+}
+
+// The physical size of `pixels` at `DPI`
+fn millimeters(pixels: i32) -> i32 {
+    (f64::from(pixels) * 25.4 / DPI).round() as i32
+}
+
+// A screen as it was captured, e.g. parsed from a bug report, so layouts
+// can be tried on it. Dumps have no physical size of the screen, which is
+// then derived from `DPI`.
+impl From<Snapshot> for FakeBackend {
+    fn from(snapshot: Snapshot) -> Self {
+        let mut size = snapshot.size;
+        if size.width_mm == 0 || size.height_mm == 0 {
+            size.width_mm = millimeters(size.width);
+            size.height_mm = millimeters(size.height);
+        }
+        let next_xid = (snapshot.modes.iter().map(|m| m.xid))
+            .chain(snapshot.crtcs.iter().map(|c| c.xid))
+            .chain(snapshot.outputs.iter().map(|o| o.xid))
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        Self {
+            timestamp: 1,
+            config_timestamp: 1,
+            modes: snapshot.modes,
+            crtcs: snapshot.crtcs,
+            outputs: snapshot.outputs,
+            size,
+            range: snapshot.range,
+            next_xid,
+        }
+    }
+}
+
+impl RandrBackend for FakeBackend {
+    fn screen_resources(&mut self) -> Result<ScreenResources, XrandrError> {
+        Ok(ScreenResources {
+            timestamp: self.timestamp,
+            config_timestamp: self.config_timestamp,
+            ncrtc: i32::try_from(self.crtcs.len()).unwrap_or(i32::MAX),
+            crtcs: self.crtcs.iter().map(|c| c.xid).collect(),
+            outputs: self.outputs.iter().map(|o| o.xid).collect(),
+            nmode: i32::try_from(self.modes.len()).unwrap_or(i32::MAX),
+            modes: self.modes.clone(),
+        })
+    }
+
+    fn crtc_info(&mut self, xid: XId) -> Result<Crtc, XrandrError> {
+        self.crtcs
+            .iter()
+            .find(|c| c.xid == xid)
+            .cloned()
+            .ok_or(XrandrError::GetCrtc(xid))
+    }
+
+    fn output_info(&mut self, xid: XId) -> Result<Output, XrandrError> {
+        self.output_mut(xid).map(|o| o.clone())
+    }
+
+    fn output_properties(&mut self, xid: XId) -> Result<IndexMap<String, Property>, XrandrError> {
+        self.output_mut(xid).map(|o| o.properties.clone())
+    }
+
+    fn set_crtc_config(&mut self, crtc: &Crtc) -> Result<(), XrandrError> {
+        self.check_crtc(crtc)?;
+        self.timestamp += 1;
+
+        let mut new = crtc.clone();
+        new.timestamp = self.timestamp;
+        if new.mode == 0 {
+            new.outputs.clear();
+        }
+
+        for output in &mut self.outputs {
+            if new.outputs.contains(&output.xid) {
+                output.crtc = Some(new.xid);
+                output.current_mode = Some(new.mode);
+            } else if output.crtc == Some(new.xid) {
+                output.crtc = None;
+                output.current_mode = None;
+            }
+        }
+
+        let old = self
+            .crtcs
+            .iter_mut()
+            .find(|c| c.xid == new.xid)
+            .expect("crtc was checked");
+        *old = new;
+        Ok(())
+    }
+
+    fn screen_size(&mut self) -> Result<ScreenSize, XrandrError> {
+        Ok(self.size)
+    }
+
+    fn screen_size_range(&mut self) -> Result<ScreenSizeRange, XrandrError> {
+        Ok(self.range)
+    }
+
+    fn set_screen_size(&mut self, size: &ScreenSize) -> Result<(), XrandrError> {
+        let range = self.range;
+        if size.width < range.min_width
+            || size.height < range.min_height
+            || size.width > range.max_width
+            || size.height > range.max_height
+        {
+            return Err(XrandrError::ScreenTooLarge {
+                width: size.width,
+                height: size.height,
+                max_width: range.max_width,
+                max_height: range.max_height,
+            });
+        }
+
+        if let Some(crtc) = self
+            .crtcs
+            .iter()
+            .find(|c| c.mode != 0 && !size.fits_crtc(c))
+        {
+            return Err(XrandrError::InvalidCrtcConfig(crtc.xid));
+        }
+
+        self.size = *size;
+        self.timestamp += 1;
+        Ok(())
+    }
+
+    fn set_primary(&mut self, output: XId) -> Result<(), XrandrError> {
+        self.output_mut(output)?;
+        for o in &mut self.outputs {
+            o.is_primary = o.xid == output;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: ScreenSizeRange = ScreenSizeRange {
+        min_width: 320,
+        min_height: 200,
+        max_width: 8192,
+        max_height: 8192,
+    };
+
+    // Two crtcs, and two outputs that support both modes
+    fn backend() -> (FakeBackend, [XId; 2], [XId; 2]) {
+        let mut backend = FakeBackend::new(RANGE);
+        let hd = backend.add_mode(1920, 1080, 60.0);
+        let qhd = backend.add_mode(2560, 1440, 60.0);
+        backend.add_crtc();
+        backend.add_crtc();
+        let dp = backend.add_output("DP-1", &[qhd, hd]);
+        let hdmi = backend.add_output("HDMI-1", &[hd, qhd]);
+        (backend, [hd, qhd], [dp, hdmi])
+    }
+
+    // Enables `output` in its preferred mode at `x`
+    fn enable(backend: &mut FakeBackend, output: XId, x: i32) -> XId {
+        let output = backend.output_info(output).unwrap();
+        let mode = backend
+            .screen_resources()
+            .unwrap()
+            .mode(output.preferred_modes[0])
+            .unwrap();
+
+        let mut crtc = backend.available_crtc(&output).unwrap();
+        crtc.outputs = vec![output.xid];
+        crtc.x = x;
+        crtc.set_mode(&mode);
+        backend.apply_crtcs(&mut [crtc.clone()]).unwrap();
+        crtc.xid
+    }
+
+    #[test]
+    fn applies_crtcs() {
+        let (mut backend, [_, qhd], [dp, hdmi]) = backend();
+
+        let dp_crtc = enable(&mut backend, dp, 0);
+        let size = backend.screen_size().unwrap();
+        assert_eq!((size.width, size.height), (2560, 1440));
+        assert_eq!(backend.output_info(dp).unwrap().current_mode, Some(qhd));
+
+        let hdmi_crtc = enable(&mut backend, hdmi, 2560);
+        assert_ne!(dp_crtc, hdmi_crtc);
+        let size = backend.screen_size().unwrap();
+        assert_eq!((size.width, size.height), (4480, 1440));
+        assert!(backend.crtc_info(hdmi_crtc).unwrap().timestamp > 1);
+
+        // No crtc left for a third output
+        let third = backend.add_output("DP-2", &[qhd]);
+        let third = backend.output_info(third).unwrap();
+        assert!(matches!(
+            backend.available_crtc(&third),
+            Err(XrandrError::NoCrtcAvailable)
+        ));
+
+        // Disabling the right output shrinks the screen again
+        let mut crtc = backend.crtc_info(hdmi_crtc).unwrap();
+        crtc.set_disable();
+        backend.apply_crtcs(&mut [crtc]).unwrap();
+        assert_eq!(backend.screen_size().unwrap().width, 2560);
+        assert_eq!(backend.output_info(hdmi).unwrap().crtc, None);
+        assert_eq!(backend.output_info(dp).unwrap().crtc, Some(dp_crtc));
+    }
+
+    #[test]
+    fn rejects_invalid_configurations() {
+        // Nothing to apply on an empty screen
+        let mut empty = FakeBackend::new(RANGE);
+        empty.apply_crtcs(&mut []).unwrap();
+        assert_eq!(empty.screen_size().unwrap().width, RANGE.min_width);
+
+        let (mut backend, [hd, _], [dp, hdmi]) = backend();
+        let mode = backend.screen_resources().unwrap().mode(hd).unwrap();
+
+        // Not within the screen, which has not been resized
+        let output = backend.output_info(dp).unwrap();
+        let mut crtc = backend.available_crtc(&output).unwrap();
+        crtc.outputs = vec![dp];
+        crtc.set_mode(&mode);
+        assert!(matches!(
+            backend.set_crtc_config(&crtc),
+            Err(XrandrError::InvalidCrtcConfig(_))
+        ));
+
+        // Too large for any screen
+        backend.apply_crtcs(&mut [crtc]).unwrap();
+        let output = backend.output_info(hdmi).unwrap();
+        let mut crtc = backend.available_crtc(&output).unwrap();
+        crtc.outputs = vec![hdmi];
+        crtc.x = 8000;
+        crtc.set_mode(&mode);
+        assert!(matches!(
+            backend.apply_crtcs(&mut [crtc]),
+            Err(XrandrError::ScreenTooLarge { .. })
+        ));
+
+        // Filters are passed on as C strings
+        let mut crtc = backend.enabled_crtcs().unwrap().remove(0);
+        crtc.transform.filter = "bi\0linear".to_string();
+        assert!(matches!(
+            backend.apply_crtcs(&mut [crtc]),
+            Err(XrandrError::InvalidFilter(_))
+        ));
+
+        assert!(backend.set_primary(dp).is_ok());
+        assert!(backend.output_info(dp).unwrap().is_primary);
+        assert!(backend.set_primary(99).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+// Builders for the crate's types, so tests can set up a configuration
+// without talking to an x backend
+use indexmap::IndexMap;
+
+use crate::crtc::{Crtc, Reflection, Rotation};
+use crate::transform::Transform;
+use crate::{Mode, Output, XId};
+
+pub(crate) fn crtc(xid: XId, x: i32, y: i32, width: u32, height: u32) -> Crtc {
+    Crtc {
+        xid,
+        timestamp: 0,
+        x,
+        y,
+        width,
+        height,
+        mode: 1,
+        rotation: Rotation::Normal,
+        reflection: Reflection::Normal,
+        outputs: vec![xid],
+        rotations: vec![
+            Rotation::Normal,
+            Rotation::Left,
+            Rotation::Inverted,
+            Rotation::Right,
+        ],
+        reflections: vec![Reflection::Normal],
+        possible: vec![xid],
+        transform: Transform::identity(),
+        panning: None,
+    }
+}
+
+pub(crate) fn output(xid: XId, name: &str) -> Output {
+    Output {
+        xid,
+        properties: IndexMap::new(),
+        timestamp: 0,
+        is_primary: false,
+        crtc: None,
+        name: name.to_string(),
+        mm_width: 0,
+        mm_height: 0,
+        connected: true,
+        subpixel_order: 0,
+        crtcs: Vec::new(),
+        clones: Vec::new(),
+        modes: Vec::new(),
+        preferred_modes: Vec::new(),
+        current_mode: None,
+    }
+}
+
+pub(crate) fn mode(xid: XId, width: u32, height: u32, rate: f64) -> Mode {
+    Mode {
+        xid,
+        width,
+        height,
+        dot_clock: 0,
+        hsync_tart: 0,
+        hsync_end: 0,
+        htotal: 0,
+        hskew: 0,
+        vsync_start: 0,
+        vsync_end: 0,
+        vtotal: 0,
+        name: format!("{}x{}", width, height),
+        flags: 0,
+        rate,
+    }
+}
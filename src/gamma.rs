@@ -0,0 +1,410 @@
+#[cfg(feature = "xlib")]
+use std::convert::TryFrom;
+#[cfg(feature = "xlib")]
+use std::{ptr, slice};
+
+#[cfg(feature = "xlib")]
+use x11::xrandr;
+
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+#[cfg(feature = "xlib")]
+use crate::XId;
+use crate::XrandrError;
+
+// A gamma ramp maps the intensity of each color channel to what is sent to
+// the display. All channels have the same number of entries, which the x
+// backend dictates per crtc (see `XHandle::gamma_size`).
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct GammaRamp {
+    pub red: Vec<u16>,
+    pub green: Vec<u16>,
+    pub blue: Vec<u16>,
+}
+
+// How to derive a gamma ramp from the linear one, like `xrandr --brightness`
+// and redshift's `--gamma r:g:b` and color temperature. Ramps are always
+// computed from the linear ramp and never from the current one, so applying
+// an adjustment again (e.g. after a crash) gives the same result, and
+// applying the default one reverts any adjustment.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct ColorAdjustment {
+    /// Factor for all intensities, 1.0 leaves them unchanged
+    pub brightness: f64,
+    /// Gamma of the red, green and blue channels, 1.0 leaves them unchanged
+    pub gamma: [f64; 3],
+    /// Color temperature in Kelvin, 6500 is neutral
+    pub temperature: u32,
+}
+
+// White points of a black body at various temperatures, relative to 6500K.
+// Every fifth entry of redshift's table (colorramp.c, computed by Ingo Thies),
+// so temperatures tint the display the same as in redshift.
+const BLACKBODY: [(u32, [f64; 3]); 19] = [
+    (1000, [1.0000, 0.1817, 0.0000]),
+    (1500, [1.0000, 0.4232, 0.0000]),
+    (2000, [1.0000, 0.5436, 0.0868]),
+    (2500, [1.0000, 0.6437, 0.2882]),
+    (3000, [1.0000, 0.7198, 0.4286]),
+    (3500, [1.0000, 0.7799, 0.5464]),
+    (4000, [1.0000, 0.8285, 0.6482]),
+    (4500, [1.0000, 0.8686, 0.7369]),
+    (5000, [1.0000, 0.9020, 0.8147]),
+    (5500, [1.0000, 0.9454, 0.8940]),
+    (6000, [1.0000, 0.9711, 0.9431]),
+    (6500, [1.0000, 1.0000, 1.0000]),
+    (7000, [0.9516, 0.9698, 1.0000]),
+    (7500, [0.9119, 0.9447, 1.0000]),
+    (8000, [0.8791, 0.9236, 1.0000]),
+    (8500, [0.8513, 0.9056, 1.0000]),
+    (9000, [0.8275, 0.8901, 1.0000]),
+    (9500, [0.8068, 0.8767, 1.0000]),
+    (10000, [0.7883, 0.8648, 1.0000]),
+];
+
+impl Default for ColorAdjustment {
+    fn default() -> Self {
+        Self {
+            brightness: 1.0,
+            gamma: [1.0, 1.0, 1.0],
+            temperature: 6500,
+        }
+    }
+}
+
+impl ColorAdjustment {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scales all intensities, like `xrandr --brightness`
+    #[must_use]
+    pub fn with_brightness(mut self, brightness: f64) -> Self {
+        self.brightness = brightness;
+        self
+    }
+
+    /// Sets the gamma per channel, like redshift's `--gamma r:g:b`
+    #[must_use]
+    pub fn with_gamma(mut self, red: f64, green: f64, blue: f64) -> Self {
+        self.gamma = [red, green, blue];
+        self
+    }
+
+    /// Tints the display like a black body of `kelvin` degrees. Values
+    /// outside of 1000K to 10000K are clamped.
+    #[must_use]
+    pub fn with_temperature(mut self, kelvin: u32) -> Self {
+        self.temperature = kelvin;
+        self
+    }
+
+    /// Computes the gamma ramp with `size` entries per channel
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidColorAdjustment` - The brightness is negative,
+    ///   or a gamma is not positive
+    ///
+    pub fn ramp(&self, size: usize) -> Result<GammaRamp, XrandrError> {
+        self.apply_to(&GammaRamp::linear(size))
+    }
+
+    /// Adjusts an existing gamma ramp, e.g. the calibration curves of an
+    /// ICC profile (see `Vcgt::ramp`), instead of a linear one
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidColorAdjustment` - The brightness is negative,
+    ///   or a gamma is not positive
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let calibration = vcgt.ramp(xhandle.gamma_size(&dp_1)?);
+    /// let ramp = ColorAdjustment::new().with_temperature(4500).apply_to(&calibration)?;
+    /// xhandle.set_gamma(&dp_1, &ramp)?;
+    /// ```
+    ///
+    pub fn apply_to(&self, ramp: &GammaRamp) -> Result<GammaRamp, XrandrError> {
+        if !(self.brightness >= 0.0 && self.brightness.is_finite()) {
+            return Err(XrandrError::InvalidColorAdjustment(format!(
+                "brightness {}",
+                self.brightness
+            )));
+        }
+        if let Some(gamma) = self.gamma.iter().find(|g| !(**g > 0.0 && g.is_finite())) {
+            return Err(XrandrError::InvalidColorAdjustment(format!(
+                "gamma {}",
+                gamma
+            )));
+        }
+
+        let white = white_point(self.temperature);
+        let channel = |c: usize, values: &[u16]| -> Vec<u16> {
+            values
+                .iter()
+                .map(|v| {
+                    let v = f64::from(*v) / f64::from(u16::MAX);
+                    let v = (v * self.brightness * white[c]).powf(1.0 / self.gamma[c]);
+                    (v.clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16
+                })
+                .collect()
+        };
+
+        Ok(GammaRamp {
+            red: channel(0, &ramp.red),
+            green: channel(1, &ramp.green),
+            blue: channel(2, &ramp.blue),
+        })
+    }
+}
+
+// Interpolates the white point of `kelvin` from `BLACKBODY`
+fn white_point(kelvin: u32) -> [f64; 3] {
+    let (first, last) = (BLACKBODY[0], BLACKBODY[BLACKBODY.len() - 1]);
+    let kelvin = kelvin.clamp(first.0, last.0);
+
+    let i = BLACKBODY
+        .iter()
+        .position(|(k, _)| *k >= kelvin)
+        .expect("kelvin is clamped to the table");
+    if i == 0 {
+        return first.1;
+    }
+
+    let ((low_k, low), (high_k, high)) = (BLACKBODY[i - 1], BLACKBODY[i]);
+    let t = f64::from(kelvin - low_k) / f64::from(high_k - low_k);
+    [0, 1, 2].map(|c| low[c] + (high[c] - low[c]) * t)
+}
+
+// A wrapper that drops the pointer if it goes out of scope.
+// Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
+struct GammaHandle {
+    ptr: ptr::NonNull<xrandr::XRRCrtcGamma>,
+}
+
+#[cfg(feature = "xlib")]
+impl Drop for GammaHandle {
+    fn drop(&mut self) {
+        unsafe { xrandr::XRRFreeGamma(self.ptr.as_ptr()) };
+    }
+}
+
+impl GammaRamp {
+    /// The ramp that leaves all intensities unchanged
+    #[must_use]
+    pub fn linear(size: usize) -> Self {
+        let step = |i: usize| {
+            let max = (size.max(2) - 1) as f64;
+            (i as f64 / max * f64::from(u16::MAX)).round() as u16
+        };
+        let channel: Vec<u16> = (0..size).map(step).collect();
+
+        Self {
+            red: channel.clone(),
+            green: channel.clone(),
+            blue: channel,
+        }
+    }
+
+    /// Number of entries per channel
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.red.len()
+    }
+
+    /// Checks that every channel has `size` entries
+    ///
+    /// # Errors
+    /// * `XrandrError::GammaSizeMismatch` - A channel has a different size
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn check_size(&self, crtc: XId, size: usize) -> Result<(), XrandrError> {
+        for channel in [&self.red, &self.green, &self.blue] {
+            if channel.len() != size {
+                return Err(XrandrError::GammaSizeMismatch {
+                    crtc,
+                    expected: size,
+                    found: channel.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the current gamma ramp of a crtc
+    ///
+    /// # Errors
+    /// * `XrandrError::GetGamma(xid)` - The call to the x backend failed
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn get(handle: &mut XHandle, crtc: XId) -> Result<Self, XrandrError> {
+        let raw_ptr = unsafe { xrandr::XRRGetCrtcGamma(handle.sys.as_ptr(), crtc) };
+        let ptr = ptr::NonNull::new(raw_ptr).ok_or(XrandrError::GetGamma(crtc))?;
+        let gamma_handle = GammaHandle { ptr };
+
+        let xrandr::XRRCrtcGamma {
+            size,
+            red,
+            green,
+            blue,
+        } = unsafe { gamma_handle.ptr.as_ref() };
+
+        let size = usize::try_from(*size).map_err(|_| XrandrError::GetGamma(crtc))?;
+        let channel = |values: *mut u16| {
+            if size == 0 {
+                Vec::new()
+            } else {
+                unsafe { slice::from_raw_parts(values, size) }.to_vec()
+            }
+        };
+
+        Ok(Self {
+            red: channel(*red),
+            green: channel(*green),
+            blue: channel(*blue),
+        })
+    }
+
+    /// Sets this as the gamma ramp of a crtc
+    ///
+    /// # Errors
+    /// * `XrandrError::GammaSizeMismatch` - The ramp does not have the size
+    ///   the crtc needs
+    /// * `XrandrError::SetGamma(xid)` - The call to the x backend failed
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn set(&self, handle: &mut XHandle, crtc: XId) -> Result<(), XrandrError> {
+        self.check_size(crtc, gamma_size(handle, crtc)?)?;
+
+        let size = i32::try_from(self.size()).map_err(|_| XrandrError::SetGamma(crtc))?;
+        let raw_ptr = unsafe { xrandr::XRRAllocGamma(size) };
+        let ptr = ptr::NonNull::new(raw_ptr).ok_or(XrandrError::SetGamma(crtc))?;
+        let gamma_handle = GammaHandle { ptr };
+
+        unsafe {
+            let gamma = gamma_handle.ptr.as_ptr();
+            for (values, channel) in [
+                ((*gamma).red, &self.red),
+                ((*gamma).green, &self.green),
+                ((*gamma).blue, &self.blue),
+            ] {
+                ptr::copy_nonoverlapping(channel.as_ptr(), values, channel.len());
+            }
+
+            xrandr::XRRSetCrtcGamma(handle.sys.as_ptr(), crtc, gamma);
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of entries per channel in the gamma ramp of a crtc
+///
+/// # Errors
+/// * `XrandrError::GetGamma(xid)` - The crtc does not support gamma ramps
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn gamma_size(handle: &mut XHandle, crtc: XId) -> Result<usize, XrandrError> {
+    let size = unsafe { xrandr::XRRGetCrtcGammaSize(handle.sys.as_ptr(), crtc) };
+
+    match usize::try_from(size) {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(XrandrError::GetGamma(crtc)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_ramp_spans_full_range() {
+        let ramp = GammaRamp::linear(256);
+        assert_eq!(ramp.size(), 256);
+        assert_eq!(ramp.red[0], 0);
+        assert_eq!(ramp.green[1], 257);
+        assert_eq!(ramp.blue[255], u16::MAX);
+    }
+
+    #[test]
+    fn default_adjustment_is_linear() {
+        let ramp = ColorAdjustment::default().ramp(256).unwrap();
+        assert_eq!(ramp, GammaRamp::linear(256));
+    }
+
+    #[test]
+    fn adjusts_existing_ramps() {
+        let calibration = GammaRamp {
+            red: vec![0, 30000, 60000],
+            green: vec![0, 32768, u16::MAX],
+            blue: vec![1000, 2000, 3000],
+        };
+        let dimmed = ColorAdjustment::new()
+            .with_brightness(0.5)
+            .apply_to(&calibration)
+            .unwrap();
+        assert_eq!(dimmed.red, vec![0, 15000, 30000]);
+        assert_eq!(dimmed.blue, vec![500, 1000, 1500]);
+    }
+
+    #[test]
+    fn adjusts_brightness_gamma_and_temperature() {
+        let dimmed = ColorAdjustment::new().with_brightness(0.5).ramp(3).unwrap();
+        assert_eq!(dimmed.red, vec![0, 16384, 32768]);
+
+        let gamma = ColorAdjustment::new()
+            .with_gamma(2.0, 1.0, 1.0)
+            .ramp(3)
+            .unwrap();
+        assert_eq!(gamma.red[1], 46341);
+        assert_eq!(gamma.green[1], 32768);
+
+        let warm = ColorAdjustment::new()
+            .with_temperature(3250)
+            .ramp(2)
+            .unwrap();
+        assert_eq!(warm.red[1], u16::MAX);
+        assert_eq!(warm.green[1], 49141);
+        assert_eq!(warm.blue[1], 31948);
+
+        assert!(ColorAdjustment::new()
+            .with_gamma(0.0, 1.0, 1.0)
+            .ramp(2)
+            .is_err());
+        assert!(ColorAdjustment::new()
+            .with_brightness(-1.0)
+            .ramp(2)
+            .is_err());
+    }
+
+    #[test]
+    fn matches_redshift_white_points() {
+        assert_eq!(white_point(3000), [1.0, 0.7198, 0.4286]);
+        assert_eq!(white_point(4000), [1.0, 0.8285, 0.6482]);
+        assert_eq!(white_point(6500), [1.0, 1.0, 1.0]);
+        assert_eq!(white_point(500), white_point(1000));
+        assert_eq!(white_point(20000), [0.7883, 0.8648, 1.0]);
+    }
+
+    #[cfg(feature = "xlib")]
+    #[test]
+    fn rejects_wrong_size() {
+        let mut ramp = GammaRamp::linear(256);
+        assert!(ramp.check_size(1, 256).is_ok());
+        assert!(matches!(
+            ramp.check_size(1, 1024),
+            Err(XrandrError::GammaSizeMismatch {
+                crtc: 1,
+                expected: 1024,
+                found: 256
+            })
+        ));
+
+        ramp.blue.pop();
+        assert!(matches!(
+            ramp.check_size(1, 256),
+            Err(XrandrError::GammaSizeMismatch { found: 255, .. })
+        ));
+    }
+}
@@ -0,0 +1,289 @@
+#[cfg(feature = "xlib")]
+use std::ffi::CStr;
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "xlib")]
+use x11::xlib;
+
+use crate::gamma::GammaRamp;
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+use crate::XrandrError;
+
+// Size of the header of an ICC profile, the tag table follows it
+const HEADER_SIZE: usize = 128;
+
+// An ICC color profile, e.g. the result of calibrating a monitor. We only
+// look into it for the `vcgt` (video card gamma table) tag, which holds the
+// curves that should be loaded into the gamma ramp of the monitor's crtc.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct IccProfile {
+    pub data: Vec<u8>,
+}
+
+// The calibration curves of a `vcgt` tag, either sampled or as a formula per
+// channel. Channels are ordered red, green, blue.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Vcgt {
+    /// Equally spaced samples from 0 to 1
+    Table([Vec<f64>; 3]),
+    /// `min + (max - min) * x^gamma`
+    Formula {
+        gamma: [f64; 3],
+        min: [f64; 3],
+        max: [f64; 3],
+    },
+}
+
+impl IccProfile {
+    /// Checks that `data` looks like an ICC profile
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidIccProfile` - `data` is no ICC profile
+    ///
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, XrandrError> {
+        if data.len() < HEADER_SIZE + 4 || &data[36..40] != b"acsp" {
+            return Err(invalid("missing ICC header"));
+        }
+
+        let size = read_u32(&data, 0)? as usize;
+        if size > data.len() {
+            return Err(invalid("truncated profile"));
+        }
+
+        Ok(Self { data })
+    }
+
+    /// Reads an ICC profile from disk, see `from_bytes`
+    ///
+    /// # Errors
+    /// * `XrandrError::Io` - The file could not be read
+    /// * `XrandrError::InvalidIccProfile` - The file is no ICC profile
+    ///
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, XrandrError> {
+        Self::from_bytes(fs::read(path).map_err(XrandrError::Io)?)
+    }
+
+    /// The calibration curves of this profile, if it has any
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidIccProfile` - The tag table or `vcgt` tag is
+    ///   malformed
+    ///
+    pub fn vcgt(&self) -> Result<Option<Vcgt>, XrandrError> {
+        let data = &self.data;
+        let count = read_u32(data, HEADER_SIZE)? as usize;
+
+        for i in 0..count {
+            let entry = HEADER_SIZE + 4 + i * 12;
+            if read_bytes(data, entry, 4)? != b"vcgt" {
+                continue;
+            }
+
+            let offset = read_u32(data, entry + 4)? as usize;
+            let size = read_u32(data, entry + 8)? as usize;
+            let tag = read_bytes(data, offset, size)?;
+            return Vcgt::parse(tag).map(Some);
+        }
+
+        Ok(None)
+    }
+}
+
+impl Vcgt {
+    fn parse(tag: &[u8]) -> Result<Self, XrandrError> {
+        if read_bytes(tag, 0, 4)? != b"vcgt" {
+            return Err(invalid("vcgt tag has the wrong signature"));
+        }
+
+        match read_u32(tag, 8)? {
+            0 => {
+                let channels = read_u16(tag, 12)? as usize;
+                let count = read_u16(tag, 14)? as usize;
+                let entry_size = read_u16(tag, 16)? as usize;
+
+                let sample = |channel: usize, i: usize| -> Result<f64, XrandrError> {
+                    let offset = 18 + (channel * count + i) * entry_size;
+                    match entry_size {
+                        1 => Ok(f64::from(read_bytes(tag, offset, 1)?[0]) / f64::from(u8::MAX)),
+                        2 => Ok(f64::from(read_u16(tag, offset)?) / f64::from(u16::MAX)),
+                        _ => Err(invalid("unsupported vcgt entry size")),
+                    }
+                };
+                let curve = |channel: usize| -> Result<Vec<f64>, XrandrError> {
+                    (0..count).map(|i| sample(channel, i)).collect()
+                };
+
+                match channels {
+                    1 => {
+                        let curve = curve(0)?;
+                        Ok(Vcgt::Table([curve.clone(), curve.clone(), curve]))
+                    }
+                    3 => Ok(Vcgt::Table([curve(0)?, curve(1)?, curve(2)?])),
+                    _ => Err(invalid("unsupported number of vcgt channels")),
+                }
+            }
+            1 => {
+                // Per channel: gamma, min and max, in 16.16 fixed point
+                let value = |channel: usize, field: usize| -> Result<f64, XrandrError> {
+                    let fixed = read_u32(tag, 12 + (channel * 3 + field) * 4)?;
+                    Ok(f64::from(fixed) / 65536.0)
+                };
+                let field = |field: usize| -> Result<[f64; 3], XrandrError> {
+                    Ok([value(0, field)?, value(1, field)?, value(2, field)?])
+                };
+
+                Ok(Vcgt::Formula {
+                    gamma: field(0)?,
+                    min: field(1)?,
+                    max: field(2)?,
+                })
+            }
+            _ => Err(invalid("unknown vcgt type")),
+        }
+    }
+
+    /// The gamma ramp with `size` entries per channel, interpolating the
+    /// curves where needed
+    #[must_use]
+    pub fn ramp(&self, size: usize) -> GammaRamp {
+        let max = (size.max(2) - 1) as f64;
+        let position = |i: usize| i as f64 / max;
+
+        let channel = |c: usize| -> Vec<u16> {
+            (0..size)
+                .map(|i| {
+                    let x = position(i);
+                    let v = match self {
+                        Vcgt::Table(curves) => interpolate(&curves[c], x),
+                        Vcgt::Formula { gamma, min, max } => {
+                            min[c] + (max[c] - min[c]) * x.powf(gamma[c])
+                        }
+                    };
+                    (v.clamp(0.0, 1.0) * f64::from(u16::MAX)).round() as u16
+                })
+                .collect()
+        };
+
+        GammaRamp {
+            red: channel(0),
+            green: channel(1),
+            blue: channel(2),
+        }
+    }
+}
+
+/// Sets the `_ICC_PROFILE` property of the root window, where color managed
+/// applications look for the profile of the (first) screen
+#[cfg(feature = "xlib")]
+pub(crate) fn set_root_profile(handle: &mut XHandle, profile: &IccProfile) {
+    let name: &CStr = c"_ICC_PROFILE";
+
+    unsafe {
+        let atom = xlib::XInternAtom(handle.sys.as_ptr(), name.as_ptr(), xlib::False);
+        xlib::XChangeProperty(
+            handle.sys.as_ptr(),
+            handle.root(),
+            atom,
+            xlib::XA_CARDINAL,
+            8,
+            xlib::PropModeReplace,
+            profile.data.as_ptr(),
+            i32::try_from(profile.data.len()).unwrap_or(i32::MAX),
+        );
+        xlib::XSync(handle.sys.as_ptr(), xlib::False);
+    }
+}
+
+// Linearly interpolates the equally spaced `samples` at `x` in [0, 1]
+fn interpolate(samples: &[f64], x: f64) -> f64 {
+    match samples {
+        [] => x,
+        [only] => *only,
+        _ => {
+            let position = x * (samples.len() - 1) as f64;
+            let low = position.floor() as usize;
+            let high = (low + 1).min(samples.len() - 1);
+            let t = position - low as f64;
+            samples[low] + (samples[high] - samples[low]) * t
+        }
+    }
+}
+
+fn invalid(reason: &str) -> XrandrError {
+    XrandrError::InvalidIccProfile(reason.to_string())
+}
+
+fn read_bytes(data: &[u8], offset: usize, len: usize) -> Result<&[u8], XrandrError> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| invalid("unexpected end of data"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, XrandrError> {
+    let bytes = read_bytes(data, offset, 2)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, XrandrError> {
+    let bytes = read_bytes(data, offset, 4)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A minimal profile with nothing but a `vcgt` tag
+    fn profile(vcgt: &[u8]) -> IccProfile {
+        let mut data = vec![0; HEADER_SIZE];
+        data[36..40].copy_from_slice(b"acsp");
+        data.extend(1u32.to_be_bytes());
+        data.extend(b"vcgt");
+        data.extend((HEADER_SIZE as u32 + 16).to_be_bytes());
+        data.extend((vcgt.len() as u32).to_be_bytes());
+        data.extend(vcgt);
+
+        let size = data.len() as u32;
+        data[0..4].copy_from_slice(&size.to_be_bytes());
+        IccProfile::from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn interpolates_table() {
+        let mut vcgt = b"vcgt\0\0\0\0".to_vec();
+        vcgt.extend(0u32.to_be_bytes());
+        vcgt.extend([0, 1, 0, 3, 0, 2]);
+        for sample in [0u16, 0x8000, 0xffff] {
+            vcgt.extend(sample.to_be_bytes());
+        }
+
+        let vcgt = profile(&vcgt).vcgt().unwrap().unwrap();
+        let ramp = vcgt.ramp(5);
+        assert_eq!(ramp.red, vec![0, 16384, 32768, 49152, 65535]);
+        assert_eq!(ramp.red, ramp.blue);
+    }
+
+    #[test]
+    fn evaluates_formula() {
+        let mut vcgt = b"vcgt\0\0\0\0".to_vec();
+        vcgt.extend(1u32.to_be_bytes());
+        for _ in 0..3 {
+            // gamma 2, min 0, max 0.5
+            vcgt.extend([0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0x80, 0]);
+        }
+
+        let ramp = profile(&vcgt).vcgt().unwrap().unwrap().ramp(3);
+        assert_eq!(ramp.green, vec![0, 8192, 32768]);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        assert!(IccProfile::from_bytes(vec![0; 200]).is_err());
+
+        let truncated = profile(b"vcgt\0\0\0\0\0\0\0\0\0\x03\0\x10\0\x02");
+        assert!(truncated.vcgt().is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+
+use crate::crtc::{normalize_positions, Alignment, Crtc, Relation};
+use crate::validate;
+use crate::XrandrError;
+
+// A single rule of a layout: place `output` in `relation` to `target`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub output: String,
+    pub relation: Relation,
+    pub target: String,
+    pub alignment: Alignment,
+    /// Pixels between `output` and `target`, negative values overlap them
+    pub gap: i32,
+}
+
+// A declarative description of where outputs should go, relative to each
+// other. Outputs that are not positioned by any constraint keep their current
+// position and serve as anchors for the others.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub constraints: Vec<Constraint>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    InProgress,
+    Done,
+}
+
+impl Layout {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a constraint placing `output` in `relation` to `target`, with the
+    /// shared edges aligned at the start
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let layout = Layout::new()
+    ///     .place("DP-1", Relation::LeftOf, "eDP-1")
+    ///     .place("HDMI-1", Relation::Above, "eDP-1");
+    /// xhandle.apply_layout(&layout)?;
+    /// ```
+    ///
+    #[must_use]
+    pub fn place(self, output: &str, relation: Relation, target: &str) -> Self {
+        self.place_aligned(output, relation, target, Alignment::Start)
+    }
+
+    /// Like `place`, but with a specific alignment of the shared edges
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Bottom edges of DP-1 and eDP-1 are at the same height
+    /// let layout = Layout::new()
+    ///     .place_aligned("DP-1", Relation::LeftOf, "eDP-1", Alignment::End);
+    /// ```
+    ///
+    #[must_use]
+    pub fn place_aligned(
+        self,
+        output: &str,
+        relation: Relation,
+        target: &str,
+        alignment: Alignment,
+    ) -> Self {
+        self.place_with_gap(output, relation, target, alignment, 0)
+    }
+
+    /// Like `place_aligned`, but leaves `gap` pixels between `output` and
+    /// `target`. A negative gap makes them overlap, e.g. to hide bezels.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let layout = Layout::new()
+    ///     .place_with_gap("DP-1", Relation::RightOf, "DP-2", Alignment::Center, -20);
+    /// ```
+    ///
+    #[must_use]
+    pub fn place_with_gap(
+        mut self,
+        output: &str,
+        relation: Relation,
+        target: &str,
+        alignment: Alignment,
+        gap: i32,
+    ) -> Self {
+        self.constraints.push(Constraint {
+            output: output.to_string(),
+            relation,
+            target: target.to_string(),
+            alignment,
+            gap,
+        });
+        self
+    }
+
+    /// Computes the positions of a set of named crtcs, checks that they
+    /// neither overlap nor leave gaps (other than those the constraints ask
+    /// for), and normalizes them so the top-left corner is at (0,0).
+    ///
+    /// # Arguments
+    /// * `screens` - Every enabled output's name, with the crtc driving it
+    ///
+    /// # Errors
+    /// * `XrandrError::UnknownOutput(name)` - A constraint names an output
+    ///   that is not in `screens`
+    /// * `XrandrError::ConflictingConstraints(name)` - An output is
+    ///   positioned by more than one constraint
+    /// * `XrandrError::ConstraintCycle(name)` - The constraints depend on
+    ///   each other in a circle
+    /// * `XrandrError::Overlap(a, b)` - Two outputs that are not mirrored
+    ///   overlap
+    /// * `XrandrError::Gap(name)` - An output does not touch the rest
+    ///
+    pub fn solve(&self, screens: &mut [(String, Crtc)]) -> Result<(), XrandrError> {
+        let index: HashMap<&str, usize> = screens
+            .iter()
+            .enumerate()
+            .map(|(i, (name, _))| (name.as_str(), i))
+            .collect();
+
+        let mut placing: HashMap<usize, (&Constraint, usize)> = HashMap::new();
+        for constraint in &self.constraints {
+            let lookup = |name: &str| {
+                index
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| XrandrError::UnknownOutput(name.to_string()))
+            };
+            let output = lookup(&constraint.output)?;
+            let target = lookup(&constraint.target)?;
+
+            if placing.insert(output, (constraint, target)).is_some() {
+                return Err(XrandrError::ConflictingConstraints(
+                    constraint.output.clone(),
+                ));
+            }
+        }
+
+        let mut visits = HashMap::new();
+        for i in 0..screens.len() {
+            place(screens, &placing, &mut visits, i)?;
+        }
+
+        check_overlaps(screens, &placing)?;
+        check_gaps(screens, &placing)?;
+
+        let mut crtcs: Vec<Crtc> = screens.iter().map(|(_, c)| c.clone()).collect();
+        normalize_positions(&mut crtcs)?;
+        for ((_, old), new) in screens.iter_mut().zip(crtcs) {
+            *old = new;
+        }
+
+        Ok(())
+    }
+}
+
+// Positions screen `i`, after recursively positioning what it depends on
+fn place(
+    screens: &mut [(String, Crtc)],
+    placing: &HashMap<usize, (&Constraint, usize)>,
+    visits: &mut HashMap<usize, Visit>,
+    i: usize,
+) -> Result<(), XrandrError> {
+    match visits.get(&i) {
+        Some(Visit::Done) => return Ok(()),
+        Some(Visit::InProgress) => return Err(XrandrError::ConstraintCycle(screens[i].0.clone())),
+        None => {}
+    }
+
+    if let Some((constraint, target)) = placing.get(&i) {
+        visits.insert(i, Visit::InProgress);
+        place(screens, placing, visits, *target)?;
+
+        let target = screens[*target].1.clone();
+        let crtc = &mut screens[i].1;
+        (crtc.x, crtc.y) = crtc.relative_position(
+            constraint.relation,
+            constraint.alignment,
+            constraint.gap,
+            &target,
+        );
+    }
+
+    visits.insert(i, Visit::Done);
+    Ok(())
+}
+
+// The gap a constraint declares between screens `a` and `b`, if any
+fn declared_gap(placing: &HashMap<usize, (&Constraint, usize)>, a: usize, b: usize) -> Option<i32> {
+    let gap = |output, target| {
+        placing
+            .get(&output)
+            .filter(|(_, t)| *t == target)
+            .map(|(c, _)| c.gap)
+    };
+    gap(a, b).or_else(|| gap(b, a))
+}
+
+// Overlapping is fine for outputs that share their top left corner, as
+// that is what mirroring with `Relation::SameAs` results in, and for
+// outputs placed with a negative gap
+fn check_overlaps(
+    screens: &[(String, Crtc)],
+    placing: &HashMap<usize, (&Constraint, usize)>,
+) -> Result<(), XrandrError> {
+    let crtcs: Vec<&Crtc> = screens.iter().map(|(_, c)| c).collect();
+
+    let unexpected = validate::overlapping(&crtcs)
+        .into_iter()
+        .find(|(a, b)| declared_gap(placing, *a, *b).map_or(true, |gap| gap >= 0));
+    match unexpected {
+        Some((a, b)) => Err(XrandrError::Overlap(
+            screens[a].0.clone(),
+            screens[b].0.clone(),
+        )),
+        None => Ok(()),
+    }
+}
+
+// Every screen must be reachable from the first one by moving across
+// shared edges, otherwise the pointer cannot get there. Screens placed with
+// a positive gap count as adjacent, as the gap was asked for.
+fn check_gaps(
+    screens: &[(String, Crtc)],
+    placing: &HashMap<usize, (&Constraint, usize)>,
+) -> Result<(), XrandrError> {
+    let crtcs: Vec<&Crtc> = screens.iter().map(|(_, c)| c).collect();
+
+    let mut group = vec![0; screens.len()];
+    for (i, island) in validate::islands(&crtcs).iter().enumerate() {
+        for j in island {
+            group[*j] = i;
+        }
+    }
+    for (output, (constraint, target)) in placing {
+        let (from, to) = (group[*output], group[*target]);
+        if constraint.gap > 0 && from != to {
+            for g in &mut group {
+                if *g == from {
+                    *g = to;
+                }
+            }
+        }
+    }
+
+    match group.iter().position(|g| *g != group[0]) {
+        Some(i) => Err(XrandrError::Gap(screens[i].0.clone())),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn screen(name: &str, xid: u64, width: u32, height: u32) -> (String, Crtc) {
+        let crtc = fixtures::crtc(xid, 0, 0, width, height);
+        (name.to_string(), crtc)
+    }
+
+    fn position(screens: &[(String, Crtc)], name: &str) -> (i32, i32) {
+        let (_, crtc) = screens.iter().find(|(n, _)| n == name).unwrap();
+        (crtc.x, crtc.y)
+    }
+
+    #[test]
+    fn solves_chained_constraints() {
+        let mut screens = vec![
+            screen("eDP-1", 1, 1920, 1080),
+            screen("DP-1", 2, 2560, 1440),
+            screen("HDMI-1", 3, 1280, 1024),
+            screen("DP-2", 4, 1920, 1080),
+        ];
+
+        Layout::new()
+            .place_aligned("eDP-1", Relation::LeftOf, "DP-1", Alignment::End)
+            .place_aligned("HDMI-1", Relation::Above, "DP-1", Alignment::Center)
+            .place("DP-2", Relation::SameAs, "eDP-1")
+            .solve(&mut screens)
+            .unwrap();
+
+        assert_eq!(position(&screens, "HDMI-1"), (1920 + 640, 0));
+        assert_eq!(position(&screens, "DP-1"), (1920, 1024));
+        assert_eq!(position(&screens, "eDP-1"), (0, 1024 + 360));
+        assert_eq!(position(&screens, "DP-2"), (0, 1024 + 360));
+    }
+
+    #[test]
+    fn rejects_invalid_constraints() {
+        let mut screens = vec![screen("A", 1, 100, 100), screen("B", 2, 100, 100)];
+
+        let cycle =
+            Layout::new()
+                .place("A", Relation::LeftOf, "B")
+                .place("B", Relation::LeftOf, "A");
+        assert!(matches!(
+            cycle.solve(&mut screens),
+            Err(XrandrError::ConstraintCycle(_))
+        ));
+
+        let conflict =
+            Layout::new()
+                .place("A", Relation::LeftOf, "B")
+                .place("A", Relation::RightOf, "B");
+        assert!(matches!(
+            conflict.solve(&mut screens),
+            Err(XrandrError::ConflictingConstraints(_))
+        ));
+
+        let unknown = Layout::new().place("A", Relation::LeftOf, "C");
+        assert!(matches!(
+            unknown.solve(&mut screens),
+            Err(XrandrError::UnknownOutput(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_overlaps_and_gaps() {
+        let mut screens = vec![
+            screen("A", 1, 100, 100),
+            screen("B", 2, 100, 100),
+            screen("C", 3, 50, 50),
+        ];
+
+        let overlapping = Layout::new()
+            .place("B", Relation::RightOf, "A")
+            .place_aligned("C", Relation::Below, "B", Alignment::End);
+        assert!(overlapping.solve(&mut screens).is_ok());
+
+        screens[2].1.x = 150;
+        screens[2].1.y = 50;
+        let overlapping = Layout::new().place("B", Relation::RightOf, "A");
+        assert!(matches!(
+            overlapping.solve(&mut screens),
+            Err(XrandrError::Overlap(_, _))
+        ));
+
+        screens[2].1.x = 500;
+        assert!(matches!(
+            overlapping.solve(&mut screens),
+            Err(XrandrError::Gap(name)) if name == "C"
+        ));
+    }
+
+    #[test]
+    fn solves_gaps_and_overlaps() {
+        let mut screens = vec![
+            screen("A", 1, 100, 100),
+            screen("B", 2, 100, 100),
+            screen("C", 3, 100, 100),
+        ];
+
+        Layout::new()
+            .place_with_gap("B", Relation::RightOf, "A", Alignment::Start, 20)
+            .place_with_gap("C", Relation::Below, "B", Alignment::Start, -10)
+            .solve(&mut screens)
+            .unwrap();
+        assert_eq!(position(&screens, "B"), (120, 0));
+        assert_eq!(position(&screens, "C"), (120, 90));
+
+        // The gap is only fine between the screens it was declared for
+        screens[2].1.x = 0;
+        screens[2].1.y = 300;
+        let gap = Layout::new().place_with_gap("B", Relation::RightOf, "A", Alignment::Start, 20);
+        assert!(matches!(
+            gap.solve(&mut screens),
+            Err(XrandrError::Gap(name)) if name == "C"
+        ));
+    }
+}
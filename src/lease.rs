@@ -0,0 +1,301 @@
+#[cfg(feature = "xlib")]
+use std::ffi::{c_void, CStr};
+#[cfg(feature = "xlib")]
+use std::os::fd::FromRawFd;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+#[cfg(feature = "xlib")]
+use std::os::raw::{c_int, c_uint};
+#[cfg(feature = "xlib")]
+use std::ptr;
+#[cfg(feature = "xlib")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "xlib")]
+use x11::xlib;
+
+#[cfg(feature = "xlib")]
+use crate::crtc::Crtc;
+use crate::output::property::Value;
+use crate::output::Output;
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+use crate::XId;
+#[cfg(feature = "xlib")]
+use crate::XrandrError;
+
+// The output property that marks displays that are no part of the desktop,
+// e.g. VR headsets
+const NON_DESKTOP: &str = "non-desktop";
+
+// A set of crtcs and outputs handed over to another process (e.g. a VR
+// runtime), which drives them through the DRM file descriptor of the lease.
+// The x server does not use them until the lease is freed.
+#[derive(Debug)]
+pub struct Lease {
+    pub xid: XId,
+    pub crtcs: Vec<XId>,
+    pub outputs: Vec<XId>,
+    fd: OwnedFd,
+}
+
+impl Lease {
+    /// The DRM lease file descriptor, to pass on to the lessee
+    #[must_use]
+    pub fn fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Output {
+    /// True iff this output is for a display that should not be part of the
+    /// desktop, like a VR headset. Such outputs are meant to be leased.
+    #[must_use]
+    pub fn is_non_desktop(&self) -> bool {
+        self.properties
+            .get(NON_DESKTOP)
+            .is_some_and(|p| matches!(&p.value, Value::Integer32(v) if v.first() == Some(&1)))
+    }
+}
+
+/// Checks that `output` may become part of the desktop
+///
+/// # Errors
+/// * `XrandrError::NonDesktopOutput(name)` - The output is non-desktop and
+///   `allowed` is false
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn check_desktop(output: &Output, allowed: bool) -> Result<(), XrandrError> {
+    if output.is_non_desktop() && !allowed {
+        return Err(XrandrError::NonDesktopOutput(output.name.clone()));
+    }
+    Ok(())
+}
+
+// libXrandr has no lease requests, so they go through libxcb-randr on the
+// xcb connection underneath the display. Those libraries are loaded when
+// first needed, which keeps them optional.
+#[cfg(feature = "xlib")]
+#[repr(C)]
+struct XcbConnection {
+    _private: [u8; 0],
+}
+
+#[cfg(feature = "xlib")]
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct XcbCookie {
+    sequence: c_uint,
+}
+
+#[cfg(feature = "xlib")]
+#[repr(C)]
+struct XcbGenericError {
+    response_type: u8,
+    error_code: u8,
+    sequence: u16,
+    resource_id: u32,
+    minor_code: u16,
+    major_code: u8,
+    pad0: u8,
+    pad: [u32; 5],
+    full_sequence: u32,
+}
+
+#[cfg(feature = "xlib")]
+#[repr(C)]
+struct CreateLeaseReply {
+    response_type: u8,
+    nfd: u8,
+    sequence: u16,
+    length: u32,
+    pad0: [u8; 24],
+}
+
+#[cfg(feature = "xlib")]
+struct LeaseApi {
+    get_xcb_connection: unsafe extern "C" fn(*mut xlib::Display) -> *mut XcbConnection,
+    generate_id: unsafe extern "C" fn(*mut XcbConnection) -> u32,
+    create_lease: unsafe extern "C" fn(
+        *mut XcbConnection,
+        u32,
+        u32,
+        u16,
+        u16,
+        *const u32,
+        *const u32,
+    ) -> XcbCookie,
+    create_lease_reply: unsafe extern "C" fn(
+        *mut XcbConnection,
+        XcbCookie,
+        *mut *mut XcbGenericError,
+    ) -> *mut CreateLeaseReply,
+    create_lease_reply_fds:
+        unsafe extern "C" fn(*mut XcbConnection, *mut CreateLeaseReply) -> *mut c_int,
+    free_lease: unsafe extern "C" fn(*mut XcbConnection, u32, u8) -> XcbCookie,
+    flush: unsafe extern "C" fn(*mut XcbConnection) -> c_int,
+}
+
+#[cfg(feature = "xlib")]
+impl LeaseApi {
+    fn get() -> Result<&'static Self, XrandrError> {
+        static API: OnceLock<Result<LeaseApi, String>> = OnceLock::new();
+
+        API.get_or_init(|| unsafe { Self::load() })
+            .as_ref()
+            .map_err(|e| XrandrError::LeasesUnsupported(e.clone()))
+    }
+
+    unsafe fn load() -> Result<Self, String> {
+        let x11_xcb = open(c"libX11-xcb.so.1")?;
+        let xcb = open(c"libxcb.so.1")?;
+        let randr = open(c"libxcb-randr.so.0")?;
+
+        Ok(Self {
+            get_xcb_connection: symbol(x11_xcb, c"XGetXCBConnection")?,
+            generate_id: symbol(xcb, c"xcb_generate_id")?,
+            create_lease: symbol(randr, c"xcb_randr_create_lease")?,
+            create_lease_reply: symbol(randr, c"xcb_randr_create_lease_reply")?,
+            create_lease_reply_fds: symbol(randr, c"xcb_randr_create_lease_reply_fds")?,
+            free_lease: symbol(randr, c"xcb_randr_free_lease")?,
+            flush: symbol(xcb, c"xcb_flush")?,
+        })
+    }
+}
+
+#[cfg(feature = "xlib")]
+unsafe fn open(name: &CStr) -> Result<*mut c_void, String> {
+    let lib = libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+    if lib.is_null() {
+        return Err(format!("{} could not be loaded", name.to_string_lossy()));
+    }
+    Ok(lib)
+}
+
+// Looks up the function `name`, which must have the signature `F`
+#[cfg(feature = "xlib")]
+unsafe fn symbol<F: Copy>(lib: *mut c_void, name: &CStr) -> Result<F, String> {
+    let symbol = libc::dlsym(lib, name.as_ptr());
+    if symbol.is_null() {
+        return Err(format!("{} is missing", name.to_string_lossy()));
+    }
+    Ok(std::mem::transmute_copy::<*mut c_void, F>(&symbol))
+}
+
+/// Leases `crtcs` and `outputs`, like `XRRCreateLease` would
+///
+/// # Errors
+/// * `XrandrError::LeasesUnsupported` - libxcb-randr is not available
+/// * `XrandrError::CreateLease(code)` - The x server refused the lease, e.g.
+///   because a crtc or output is in use
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn create(
+    handle: &mut XHandle,
+    crtcs: &[Crtc],
+    outputs: &[Output],
+) -> Result<Lease, XrandrError> {
+    let api = LeaseApi::get()?;
+    // Xids are 32 bits on the wire
+    let crtcs: Vec<u32> = crtcs.iter().map(|c| c.xid as u32).collect();
+    let outputs: Vec<u32> = outputs.iter().map(|o| o.xid as u32).collect();
+    let too_many = || XrandrError::CreateLease(xlib::BadLength);
+
+    unsafe {
+        // Requests sent through xlib must go out before ours
+        xlib::XFlush(handle.sys.as_ptr());
+        let conn = (api.get_xcb_connection)(handle.sys.as_ptr());
+        let lid = (api.generate_id)(conn);
+
+        let cookie = (api.create_lease)(
+            conn,
+            handle.root() as u32,
+            lid,
+            u16::try_from(crtcs.len()).map_err(|_| too_many())?,
+            u16::try_from(outputs.len()).map_err(|_| too_many())?,
+            crtcs.as_ptr(),
+            outputs.as_ptr(),
+        );
+
+        let mut error = ptr::null_mut();
+        let reply = (api.create_lease_reply)(conn, cookie, &mut error);
+        if !error.is_null() {
+            let code = (*error).error_code;
+            libc::free(error.cast());
+            return Err(XrandrError::CreateLease(code));
+        }
+        if reply.is_null() {
+            return Err(XrandrError::CreateLease(0));
+        }
+
+        let fds = (api.create_lease_reply_fds)(conn, reply);
+        let fd = ((*reply).nfd > 0).then(|| OwnedFd::from_raw_fd(*fds));
+        libc::free(reply.cast());
+
+        // The lease exists without a file descriptor to use it, so end it
+        // again to give back its crtcs and outputs
+        let Some(fd) = fd else {
+            (api.free_lease)(conn, lid, 1);
+            (api.flush)(conn);
+            return Err(XrandrError::CreateLease(0));
+        };
+
+        Ok(Lease {
+            xid: XId::from(lid),
+            crtcs: crtcs.into_iter().map(XId::from).collect(),
+            outputs: outputs.into_iter().map(XId::from).collect(),
+            fd,
+        })
+    }
+}
+
+/// Ends `lease`, which gives its crtcs and outputs back to the x server
+///
+/// # Errors
+/// * `XrandrError::LeasesUnsupported` - libxcb-randr is not available
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn free(handle: &mut XHandle, lease: Lease) -> Result<(), XrandrError> {
+    let api = LeaseApi::get()?;
+
+    unsafe {
+        let conn = (api.get_xcb_connection)(handle.sys.as_ptr());
+        (api.free_lease)(conn, lease.xid as u32, 1);
+        (api.flush)(conn);
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "xlib"))]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::Property;
+
+    fn headset() -> Output {
+        let mut output = fixtures::output(1, "DP-2");
+        output.properties.insert(
+            NON_DESKTOP.to_string(),
+            Property {
+                name: NON_DESKTOP.to_string(),
+                value: Value::Integer32(vec![1]),
+                values: None,
+                is_immutable: true,
+                is_pending: false,
+            },
+        );
+        output
+    }
+
+    #[test]
+    fn refuses_non_desktop_outputs() {
+        assert!(headset().is_non_desktop());
+        assert!(!fixtures::output(2, "DP-1").is_non_desktop());
+
+        assert!(matches!(
+            check_desktop(&headset(), false),
+            Err(XrandrError::NonDesktopOutput(name)) if name == "DP-2"
+        ));
+        assert!(check_desktop(&headset(), true).is_ok());
+        assert!(check_desktop(&fixtures::output(2, "DP-1"), false).is_ok());
+    }
+}
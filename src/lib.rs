@@ -1,34 +1,83 @@
-use itertools::EitherOrBoth as ZipEntry;
-use itertools::Itertools;
+#[cfg(feature = "xlib")]
 use std::collections::HashMap;
+#[cfg(feature = "xlib")]
 use std::ffi::CStr;
 use std::fmt::Debug;
 use std::os::raw::c_ulong;
+#[cfg(feature = "xlib")]
 use std::ptr;
 
-use crtc::normalize_positions;
 pub use indexmap;
 pub use screen_resources::ScreenResources;
 use thiserror::Error;
-use x11::{xlib, xrandr};
+#[cfg(feature = "xlib")]
+use x11::xlib;
 
+pub use crate::backend::RandrBackend;
+pub use crate::backlight::{Backlight, BacklightSource};
 pub use crate::crtc::Crtc;
-pub use crate::crtc::{Relation, Rotation};
+pub use crate::crtc::{Alignment, Reflection, Relation, Rotation};
+pub use crate::fake::FakeBackend;
+pub use crate::gamma::{ColorAdjustment, GammaRamp};
+pub use crate::icc::{IccProfile, Vcgt};
+pub use crate::layout::{Constraint, Layout};
+pub use crate::lease::Lease;
 pub use crate::mode::Mode;
-pub use crate::monitor::Monitor;
+#[cfg(feature = "xlib")]
 use crate::monitor::MonitorHandle;
-pub use crate::screensize::ScreenSize;
+pub use crate::monitor::{Monitor, MonitorGeometry};
+pub use crate::panning::Panning;
+pub use crate::profile::{
+    match_profiles, Candidate, MatchKind, OutputLayout, Profile, ProfileMatch, ProfileOutput,
+    Rejected, Rejection,
+};
+pub use crate::provider::{Capabilities, Provider};
+pub use crate::screensize::{ScreenSize, ScreenSizeRange};
+#[cfg(feature = "xlib")]
+pub use crate::shared::{init_threads, SharedHandle};
+pub use crate::snapshot::Snapshot;
+pub use crate::split::Split;
+pub use crate::transform::Transform;
+pub use crate::validate::{validate, Diagnostic};
+#[cfg(feature = "x11rb")]
+pub use crate::x11rb_backend::X11rbBackend;
+#[cfg(feature = "xlib")]
+use output::property::{check_settable, PropertyOwner};
 pub use output::{
     property::{Property, Range, Ranges, Supported, Value, Values},
     Output,
 };
 
+mod backend;
+mod backlight;
 mod crtc;
+mod fake;
+#[cfg(test)]
+mod fixtures;
+mod gamma;
+mod icc;
+mod layout;
+mod lease;
+#[cfg(feature = "xlib")]
+mod mirror;
 mod mode;
 mod monitor;
 mod output;
+mod panning;
+mod parse;
+mod profile;
+mod provider;
+mod query;
 mod screen_resources;
 mod screensize;
+#[cfg(feature = "xlib")]
+mod shared;
+mod snapshot;
+mod split;
+mod transform;
+mod validate;
+#[cfg(feature = "x11rb")]
+mod x11rb_backend;
 
 // All retrieved information is timestamped by when that information was
 // last changed in the backend. If we alter an object (e.g. crtc, output) we
@@ -37,18 +86,33 @@ mod screensize;
 // will not go through.
 pub type XTime = c_ulong;
 // Xrandr seems to want the time `0` when calling setter functions
+#[cfg(feature = "xlib")]
 const CURRENT_TIME: c_ulong = 0;
 // Unique identifiers for the various objects in the x backend
 // (crtcs,outputs,modes, etc.)
 pub type XId = c_ulong;
 
 // The main handle consists simply of a pointer to the display
+#[cfg(feature = "xlib")]
 type HandleSys = ptr::NonNull<xlib::Display>;
+#[cfg(feature = "xlib")]
 #[derive(Debug)]
 pub struct XHandle {
     sys: HandleSys,
+    // Whether non-desktop outputs (e.g. VR headsets) are listed and enabled
+    non_desktop: bool,
+    // Whether the display is closed on drop, i.e. whether we opened it
+    owned: bool,
 }
 
+// SAFETY: Xlib keeps no per-thread state, so a display can move to another
+// thread as long as only one thread uses it at a time. `&mut self` on every
+// call that touches it ensures that. Sharing it (`Sync`) needs a lock and
+// `XInitThreads`, which `SharedHandle` provides.
+#[cfg(feature = "xlib")]
+unsafe impl Send for XHandle {}
+
+#[cfg(feature = "xlib")]
 impl XHandle {
     /// Open a handle to the lib-xrandr backend. This will be
     /// used for nearly all interactions with the xrandr lib
@@ -68,7 +132,89 @@ impl XHandle {
         let sys = ptr::NonNull::new(unsafe { xlib::XOpenDisplay(ptr::null()) })
             .ok_or(XrandrError::Open)?;
 
-        Ok(Self { sys })
+        Ok(Self {
+            sys,
+            non_desktop: false,
+            owned: true,
+        })
+    }
+
+    /// Wraps a display connection that was opened elsewhere, e.g. by a
+    /// window manager, so it does not need a second one.
+    /// Xlib can only wrap a display it opened itself, so an xcb connection
+    /// has to come from `XGetXCBConnection` on a display, which is the one to
+    /// pass here.
+    ///
+    /// # Arguments
+    /// * `display`
+    ///   The open display to use
+    /// * `owned`
+    ///   Whether the handle closes the display when it is dropped
+    ///
+    /// # Errors
+    /// * `XrandrError::Open` - `display` is a null pointer
+    ///
+    /// # Safety
+    /// `display` must be an open display that stays open as long as the
+    /// handle exists, and is not used by another thread at the same time
+    /// (unless `XInitThreads` was called). If `owned` is true, nothing else
+    /// may close it.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let xhandle = unsafe { XHandle::from_raw(wm.display(), false)? };
+    /// let outputs = xhandle.all_outputs()?;
+    /// ```
+    ///
+    pub unsafe fn from_raw(display: *mut xlib::Display, owned: bool) -> Result<Self, XrandrError> {
+        let sys = ptr::NonNull::new(display).ok_or(XrandrError::Open)?;
+
+        Ok(Self {
+            sys,
+            non_desktop: false,
+            owned,
+        })
+    }
+
+    /// The display connection underneath, e.g. to make Xlib calls this crate
+    /// has no wrapper for. It stays valid as long as the handle.
+    #[must_use]
+    pub fn as_raw(&self) -> *mut xlib::Display {
+        self.sys.as_ptr()
+    }
+
+    /// Gives up the display connection without closing it. The caller is
+    /// responsible for closing it, if the handle owned it.
+    #[must_use]
+    pub fn into_raw(self) -> *mut xlib::Display {
+        let display = self.sys.as_ptr();
+        std::mem::forget(self);
+        display
+    }
+
+    /// Reads the whole state of the screen, including the gamma ramps of
+    /// the enabled crtcs. See `Snapshot::query` and `Snapshot::verbose` to
+    /// print it like xrandr.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// print!("{}", xhandle.snapshot()?.verbose());
+    /// ```
+    ///
+    pub fn snapshot(&mut self) -> Result<Snapshot, XrandrError> {
+        let mut snapshot = Snapshot::read(self)?;
+        snapshot.screen = unsafe { xlib::XDefaultScreen(self.sys.as_ptr()) };
+
+        // Like xrandr, leave out the gamma of crtcs that do not have one
+        for crtc in snapshot.crtcs.iter().filter(|c| c.mode != 0) {
+            if let Ok(ramp) = GammaRamp::get(self, crtc.xid) {
+                snapshot.gamma.insert(crtc.xid, ramp);
+            }
+        }
+        Ok(snapshot)
     }
 
     /// List every monitor
@@ -106,38 +252,335 @@ impl XHandle {
             .collect::<Result<_, _>>()
     }
 
-    /// List every monitor's outputs
+    /// Creates a monitor, like `xrandr --setmonitor`. A monitor with the same
+    /// name is replaced. Monitors can cover part of an output (e.g. half of
+    /// an ultrawide display), or several outputs (e.g. a video wall).
+    ///
+    /// # Arguments
+    /// * `name` - The name of the new monitor
+    /// * `geometry` - Where the monitor is on the screen, and its physical
+    ///   size
+    /// * `outputs` - The outputs that belong to the monitor, may be empty
     ///
     /// # Errors
+    /// * `XrandrError::InvalidMonitorName(name)` - The name is empty
+    /// * `XrandrError::MonitorOutOfScreen(name)` - The geometry is not within
+    ///   the screen
+    /// * `XrandrError::UnknownOutput(name)` - An output does not exist
     /// * `XrandrError::_` - various calls to the xrandr backend may fail
     ///
     /// # Examples
+    /// ```ignore
+    /// // Group a 2x2 wall of 1920x1080 displays into one monitor
+    /// let geometry = MonitorGeometry {
+    ///     x: 0,
+    ///     y: 0,
+    ///     width_px: 3840,
+    ///     height_px: 2160,
+    ///     width_mm: 1210,
+    ///     height_mm: 680,
+    /// };
+    /// xhandle.create_monitor("wall", &geometry, &[&dp_1, &dp_2, &dp_3, &dp_4])?;
     /// ```
-    /// let dp_1 = xhandle.all_outputs()?[0];
+    ///
+    pub fn create_monitor(
+        &mut self,
+        name: &str,
+        geometry: &MonitorGeometry,
+        outputs: &[&Output],
+    ) -> Result<(), XrandrError> {
+        let screen = unsafe {
+            (
+                xlib::XDisplayWidth(self.sys.as_ptr(), 0),
+                xlib::XDisplayHeight(self.sys.as_ptr(), 0),
+            )
+        };
+        monitor::check_geometry(name, geometry, screen)?;
+
+        let existing: Vec<XId> = self.all_outputs()?.iter().map(|o| o.xid).collect();
+        if let Some(missing) = outputs.iter().find(|o| !existing.contains(&o.xid)) {
+            return Err(XrandrError::UnknownOutput(missing.name.clone()));
+        }
+
+        let xids: Vec<XId> = outputs.iter().map(|o| o.xid).collect();
+        monitor::set(self, name, geometry, &xids);
+        Ok(())
+    }
+
+    /// Deletes a monitor, like `xrandr --delmonitor`
+    ///
+    /// # Errors
+    /// * `XrandrError::UnknownMonitor(name)` - There is no such monitor
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// xhandle.delete_monitor("wall")?;
     /// ```
     ///
-    pub fn all_outputs(&mut self) -> Result<Vec<Output>, XrandrError> {
-        ScreenResources::new(self)?.outputs(self)
+    pub fn delete_monitor(&mut self, name: &str) -> Result<(), XrandrError> {
+        if !self.monitors()?.iter().any(|m| m.name == name) {
+            return Err(XrandrError::UnknownMonitor(name.to_string()));
+        }
+
+        monitor::delete(self, name);
+        Ok(())
     }
 
-    // TODO: this seems to be more complicated in xrandr.c
-    // Finds an available Crtc for a given (disabled) output
-    fn find_available_crtc(&mut self, o: &Output) -> Result<Crtc, XrandrError> {
-        let res = ScreenResources::new(self)?;
-        let crtcs = res.crtcs(self)?;
+    /// Splits an output into several monitors, named after the output with
+    /// a `~n` suffix (e.g. `DP-1~1`, `DP-1~2`). Replaces an earlier split of
+    /// the output.
+    ///
+    /// # Arguments
+    /// * `output` - The output to split
+    /// * `split` - How to split it
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::InvalidSplit` - See `Split::geometries`
+    /// * `XrandrError::_` - See `create_monitor`
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let names = xhandle.split_output(&dp_1, &"60/40 horizontal".parse()?)?;
+    /// // ...
+    /// xhandle.unsplit_output(&dp_1)?;
+    /// ```
+    ///
+    pub fn split_output(
+        &mut self,
+        output: &Output,
+        split: &Split,
+    ) -> Result<Vec<String>, XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+        let crtc = ScreenResources::new(self)?.crtc(self, crtc_id)?;
+        let geometries = split.geometries(&split::output_geometry(output, &crtc))?;
+
+        self.unsplit_output(output)?;
+
+        // The output can only belong to one monitor, the others are
+        // purely virtual
+        let mut names: Vec<String> = Vec::new();
+        for (i, geometry) in geometries.iter().enumerate() {
+            let name = split::monitor_name(&output.name, i);
+            let outputs = if i == 0 { vec![output] } else { vec![] };
+
+            // Do not leave a partial split behind
+            if let Err(error) = self.create_monitor(&name, geometry, &outputs) {
+                for name in &names {
+                    monitor::delete(self, name);
+                }
+                return Err(error);
+            }
+            names.push(name);
+        }
+
+        Ok(names)
+    }
 
-        for crtc in crtcs {
-            if crtc.possible.contains(&o.xid) && crtc.outputs.is_empty() {
-                return Ok(crtc);
+    /// Deletes the monitors `split_output` created for an output
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    pub fn unsplit_output(&mut self, output: &Output) -> Result<(), XrandrError> {
+        for monitor in self.monitors()? {
+            if split::is_part_of(&monitor.name, &output.name) {
+                monitor::delete(self, &monitor.name);
             }
         }
+        Ok(())
+    }
+
+    /// List every provider, i.e. usually every GPU
+    ///
+    /// # Errors
+    /// * `XrandrError::GetProviders` - The x backend has no provider support
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// for provider in xhandle.providers()? {
+    ///     println!("{}: {:?}", provider.name, provider.capabilities);
+    /// }
+    /// ```
+    ///
+    pub fn providers(&mut self) -> Result<Vec<Provider>, XrandrError> {
+        Provider::all(self)
+    }
+
+    /// Makes `source` render the image shown on the outputs of `sink`, like
+    /// `xrandr --setprovideroutputsource sink source`. `None` as source
+    /// disconnects them again.
+    ///
+    /// # Errors
+    /// * `XrandrError::ProviderCapability` - `sink` is no output sink, or
+    ///   `source` no output source
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Drive the outputs wired to the discrete GPU
+    /// let providers = xhandle.providers()?;
+    /// xhandle.set_provider_output_source(&providers[1], Some(&providers[0]))?;
+    /// ```
+    ///
+    pub fn set_provider_output_source(
+        &mut self,
+        sink: &Provider,
+        source: Option<&Provider>,
+    ) -> Result<(), XrandrError> {
+        sink.set_output_source(self, source)
+    }
+
+    /// Makes `source` render for `sink`, like
+    /// `xrandr --setprovideroffloadsink source sink`. `None` as sink
+    /// disconnects them again.
+    ///
+    /// # Errors
+    /// * `XrandrError::ProviderCapability` - `source` is no offload source,
+    ///   or `sink` no offload sink
+    ///
+    pub fn set_provider_offload_sink(
+        &mut self,
+        source: &Provider,
+        sink: Option<&Provider>,
+    ) -> Result<(), XrandrError> {
+        source.set_offload_sink(self, sink)
+    }
+
+    /// Sets a property of an output, like `xrandr --output o --set name v`
+    ///
+    /// # Arguments
+    /// * `output` - The output whose property to change
+    /// * `name` - The name of the property, e.g. `"Broadcast RGB"`
+    /// * `value` - The new value, with the type of the current one
+    ///
+    /// # Errors
+    /// * `XrandrError::UnknownProperty(name)` - The output has no such property
+    /// * `XrandrError::ImmutableProperty(name)` - The property is read-only
+    /// * `XrandrError::PropertyTypeMismatch(name)` - `value` has another
+    ///   type than the current value
+    /// * `XrandrError::PropertyValueNotAllowed(name)` - `value` is not one
+    ///   of the supported values, or outside the ranges, of the property
+    /// * `XrandrError::UnsupportedPropertyValue(name)` - `value` cannot be
+    ///   written, e.g. an EDID
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let value = Value::Atom("Full".to_string());
+    /// xhandle.set_output_property(&dp_1, "Broadcast RGB", &value)?;
+    /// ```
+    ///
+    pub fn set_output_property(
+        &mut self,
+        output: &Output,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), XrandrError> {
+        check_settable(&output.properties, name, value)?;
+        Property::set(self, PropertyOwner::Output(output.xid), name, value)
+    }
+
+    /// Sets a property of a provider, like
+    /// `xrandr --setprovider p --set name v` would
+    ///
+    /// # Errors
+    /// * `XrandrError::UnknownProperty(name)` - The provider has no such
+    ///   property
+    /// * `XrandrError::ImmutableProperty(name)` - The property is read-only
+    /// * `XrandrError::PropertyTypeMismatch(name)` - `value` has another
+    ///   type than the current value
+    /// * `XrandrError::PropertyValueNotAllowed(name)` - `value` is not one
+    ///   of the supported values, or outside the ranges, of the property
+    /// * `XrandrError::UnsupportedPropertyValue(name)` - `value` cannot be
+    ///   written
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let sync = Value::Integer32(vec![1]);
+    /// xhandle.set_provider_property(&providers[0], "PRIME Synchronization", &sync)?;
+    /// ```
+    ///
+    pub fn set_provider_property(
+        &mut self,
+        provider: &Provider,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), XrandrError> {
+        check_settable(&provider.properties, name, value)?;
+        Property::set(self, PropertyOwner::Provider(provider.xid), name, value)
+    }
+
+    /// List every monitor's outputs. Non-desktop outputs (e.g. VR headsets)
+    /// are skipped, unless allowed with `allow_non_desktop`.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```
+    /// let dp_1 = xhandle.all_outputs()?[0];
+    /// ```
+    ///
+    pub fn all_outputs(&mut self) -> Result<Vec<Output>, XrandrError> {
+        let outputs = ScreenResources::new(self)?.outputs(self)?;
+        Ok(outputs
+            .into_iter()
+            .filter(|o| self.non_desktop || !o.is_non_desktop())
+            .collect())
+    }
+
+    /// Whether to list and enable non-desktop outputs, like those of VR
+    /// headsets, which are normally leased instead. Off by default.
+    pub fn allow_non_desktop(&mut self, allow: bool) {
+        self.non_desktop = allow;
+    }
 
-        Err(XrandrError::NoCrtcAvailable)
+    /// Leases crtcs and outputs to another process, e.g. a VR runtime,
+    /// which drives them through the DRM file descriptor of the lease
+    ///
+    /// # Errors
+    /// * `XrandrError::LeasesUnsupported` - libxcb-randr is not available
+    /// * `XrandrError::CreateLease(code)` - The x server refused the lease,
+    ///   e.g. because a crtc or output is in use
+    ///
+    /// # Examples
+    /// ```ignore
+    /// xhandle.allow_non_desktop(true);
+    /// let headset = xhandle.all_outputs()?.into_iter().find(Output::is_non_desktop)?;
+    /// let crtc = ScreenResources::new(&mut xhandle)?.crtc(&mut xhandle, headset.crtcs[0])?;
+    /// let lease = xhandle.create_lease(&[crtc], &[headset])?;
+    /// ```
+    ///
+    pub fn create_lease(
+        &mut self,
+        crtcs: &[Crtc],
+        outputs: &[Output],
+    ) -> Result<Lease, XrandrError> {
+        lease::create(self, crtcs, outputs)
+    }
+
+    /// Ends a lease, giving its crtcs and outputs back to the x server
+    ///
+    /// # Errors
+    /// * `XrandrError::LeasesUnsupported` - libxcb-randr is not available
+    ///
+    pub fn free_lease(&mut self, lease: Lease) -> Result<(), XrandrError> {
+        lease::free(self, lease)
+    }
+
+    // Finds an available Crtc for a given (disabled) output
+    fn find_available_crtc(&mut self, o: &Output) -> Result<Crtc, XrandrError> {
+        self.available_crtc(o)
     }
 
     /// Enable the given output by setting it to its preferred mode
     ///
     /// # Errors
+    /// * `XrandrError::NonDesktopOutput(name)` - The output is non-desktop,
+    ///   and those are not allowed (see `allow_non_desktop`)
     /// * `XrandrError::_` - various calls to the xrandr backend may fail
     ///
     /// # Examples
@@ -147,6 +590,8 @@ impl XHandle {
     /// ```
     ///
     pub fn enable(&mut self, output: &Output, rotation: &Rotation) -> Result<(), XrandrError> {
+        lease::check_desktop(output, self.non_desktop)?;
+
         if output.current_mode.is_some() {
             return Ok(());
         }
@@ -159,12 +604,9 @@ impl XHandle {
         let mut crtc = self.find_available_crtc(output)?;
         let mode = ScreenResources::new(self)?.mode(*target_mode)?;
 
-        crtc.mode = mode.xid;
-        crtc.width = mode.width;
-        crtc.height = mode.height;
         crtc.outputs = vec![output.xid];
-        (crtc.width, crtc.height) = crtc.rotated_size(*rotation);
         crtc.rotation = *rotation;
+        crtc.set_mode(&mode);
 
         self.apply_new_crtcs(&mut [crtc])
     }
@@ -204,10 +646,8 @@ impl XHandle {
     /// xhandle.set_primary(dp_1)?;
     /// ```
     ///
-    pub fn set_primary(&mut self, o: &Output) {
-        unsafe {
-            xrandr::XRRSetOutputPrimary(self.sys.as_ptr(), self.root(), o.xid);
-        }
+    pub fn set_primary(&mut self, o: &Output) -> Result<(), XrandrError> {
+        RandrBackend::set_primary(self, o.xid)
     }
 
     // - xrandr does not seem to resize after a rotation, and this feels
@@ -234,14 +674,13 @@ impl XHandle {
             .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
         let mut crtc = ScreenResources::new(self)?.crtc(self, crtc_id)?;
 
-        crtc.mode = mode.xid;
-        //Width and Height required by apply_new_crtcs to recalculate ScreenSize in fitting_crtcs
-        crtc.height = mode.height;
-        crtc.width = mode.width;
+        // Width and Height required by apply_new_crtcs to recalculate ScreenSize in fitting_crtcs
+        crtc.set_mode(mode);
         self.apply_new_crtcs(&mut [crtc])
     }
 
-    /// Sets the position of a given output, relative to another
+    /// Sets the position of a given output, relative to another. The edges
+    /// that touch are aligned at the start (top or left).
     ///
     /// # Arguments
     /// * `output` - The output to reposition
@@ -263,6 +702,36 @@ impl XHandle {
         output: &Output,
         relation: &Relation,
         relative_output: &Output,
+    ) -> Result<(), XrandrError> {
+        self.set_position_aligned(output, relation, relative_output, Alignment::Start, 0)
+    }
+
+    /// Sets the position of a given output, relative to another, with
+    /// control over how the touching edges line up
+    ///
+    /// # Arguments
+    /// * `output` - The output to reposition
+    /// * `relation` - The relation `output` will have to `rel_output`
+    /// * `rel_output` - The output to position relative to
+    /// * `alignment` - How to align the edges that touch
+    /// * `gap` - Pixels between the outputs, negative values overlap them
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Bottom edges line up, like most monitor stands
+    /// xhandle.set_position_aligned(dp_1, &Relation::LeftOf, hdmi_1, Alignment::End, 0)?;
+    /// ```
+    ///
+    pub fn set_position_aligned(
+        &mut self,
+        output: &Output,
+        relation: &Relation,
+        relative_output: &Output,
+        alignment: Alignment,
+        gap: i32,
     ) -> Result<(), XrandrError> {
         let crtc_id = output
             .crtc
@@ -277,21 +746,173 @@ impl XHandle {
 
         // Calculate new (x,y) based on:
         // - own width/height & relative output's width/height/x/y
-        let (w, h) = (crtc.width as i32, crtc.height as i32);
-        let (rel_w, rel_h) = (rel_crtc.width as i32, rel_crtc.height as i32);
-        let (rel_x, rel_y) = (rel_crtc.x, rel_crtc.y);
-
-        (crtc.x, crtc.y) = match relation {
-            Relation::LeftOf => (rel_x - w, rel_y),
-            Relation::RightOf => (rel_x + rel_w, rel_y),
-            Relation::Above => (rel_x, rel_y - h),
-            Relation::Below => (rel_x, rel_y + rel_h),
-            Relation::SameAs => (rel_x, rel_y),
-        };
+        (crtc.x, crtc.y) = crtc.relative_position(*relation, alignment, gap, &rel_crtc);
 
         self.apply_new_crtcs(&mut [crtc])
     }
 
+    /// Moves the top left corner of a given output to (`x`, `y`), like
+    /// `xrandr --pos`. The screen is shifted back to (0,0) afterwards, if
+    /// needed.
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// xhandle.set_position_absolute(&dp_1, 1920, 0)?;
+    /// ```
+    ///
+    pub fn set_position_absolute(
+        &mut self,
+        output: &Output,
+        x: i32,
+        y: i32,
+    ) -> Result<(), XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+        let mut crtc = ScreenResources::new(self)?.crtc(self, crtc_id)?;
+        (crtc.x, crtc.y) = (x, y);
+
+        self.apply_new_crtcs(&mut [crtc])
+    }
+
+    /// Mirrors a set of outputs, so they all show the same area as the
+    /// first one. Like xrandr's `--same-as`, but also picks a mode every
+    /// output supports.
+    ///
+    /// If the outputs can be cloned (see `Output::clones`) and support the
+    /// same mode, they are driven by one crtc. Otherwise each output gets its
+    /// own crtc, using the largest resolution they have in common. If there
+    /// is no such resolution, each output uses its preferred mode, scaled to
+    /// the size of the first output (like xrandr's `--scale-from`).
+    /// Other outputs that share a crtc with one of `outputs` keep showing
+    /// what they did.
+    ///
+    /// # Arguments
+    /// * `outputs` - The outputs to mirror. The first one keeps its position
+    ///   and rotation.
+    ///
+    /// # Errors
+    /// * `XrandrError::NoCrtcAvailable` - Not enough crtcs for the outputs
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let outputs = xhandle.all_outputs()?;
+    /// xhandle.mirror(&[&outputs[0], &outputs[1]])?;
+    /// ```
+    ///
+    pub fn mirror(&mut self, outputs: &[&Output]) -> Result<(), XrandrError> {
+        let Some(first) = outputs.first() else {
+            return Ok(());
+        };
+
+        let res = ScreenResources::new(self)?;
+        let crtcs = res.crtcs(self)?;
+        let current = crtcs.iter().find(|c| Some(c.xid) == first.crtc);
+        let (x, y) = current.map_or((0, 0), |c| (c.x, c.y));
+        let rotation = current.map_or(Rotation::Normal, |c| c.rotation);
+
+        let is_rotated = matches!(rotation, Rotation::Left | Rotation::Right);
+        let modes = mirror::mirror_modes(outputs, &res.modes, is_rotated)?;
+
+        // Sharing a crtc requires the exact same mode on every output
+        let same_mode = modes
+            .iter()
+            .all(|m| m.mode.xid == modes[0].mode.xid && m.scale == (1.0, 1.0));
+        let shared = if same_mode {
+            mirror::shared_crtc(outputs, &crtcs)
+        } else {
+            None
+        };
+
+        let mut changed = if let Some(crtc) = shared {
+            let mut crtc = crtc.clone();
+            crtc.outputs = outputs.iter().map(|o| o.xid).collect();
+            crtc.rotation = rotation;
+            crtc.set_mode(&modes[0].mode);
+            crtc.transform = Transform::identity();
+            vec![crtc]
+        } else {
+            let mut assigned = mirror::assign_crtcs(outputs, &crtcs)?;
+
+            for (crtc, mirrored) in assigned.iter_mut().zip(modes) {
+                crtc.rotation = rotation;
+                crtc.set_mode(&mirrored.mode);
+                crtc.transform = Transform::scale(mirrored.scale.0, mirrored.scale.1);
+            }
+            assigned
+        };
+
+        for crtc in &mut changed {
+            (crtc.x, crtc.y) = (x, y);
+        }
+        // Crtcs of the outputs that are not needed anymore
+        changed.extend(mirror::released_crtcs(outputs, &crtcs, &changed));
+
+        self.apply_new_crtcs(&mut changed)
+    }
+
+    /// Positions a set of outputs according to a declarative layout, and
+    /// applies the result in one go. Outputs not positioned by the layout
+    /// keep their current position.
+    ///
+    /// # Arguments
+    /// * `layout` - The constraints describing where outputs should go
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - A constrained output is off
+    /// * `XrandrError::_` - See `Layout::solve`. Nothing is changed if the
+    ///   layout cannot be solved.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let layout = Layout::new()
+    ///     .place_aligned("DP-1", Relation::LeftOf, "eDP-1", Alignment::End)
+    ///     .place("HDMI-1", Relation::SameAs, "DP-1");
+    /// xhandle.apply_layout(&layout)?;
+    /// ```
+    ///
+    pub fn apply_layout(&mut self, layout: &Layout) -> Result<(), XrandrError> {
+        let res = ScreenResources::new(self)?;
+        let outputs = res.outputs(self)?;
+        let crtcs = res.enabled_crtcs(self)?;
+
+        let mut screens: Vec<(String, Crtc)> = Vec::new();
+        for output in &outputs {
+            if let Some(crtc) = crtcs.iter().find(|c| Some(c.xid) == output.crtc) {
+                screens.push((output.name.clone(), crtc.clone()));
+            }
+        }
+
+        for constraint in &layout.constraints {
+            for name in [&constraint.output, &constraint.target] {
+                if screens.iter().all(|(n, _)| n != name) && outputs.iter().any(|o| &o.name == name)
+                {
+                    return Err(XrandrError::OutputDisabled(name.clone()));
+                }
+            }
+        }
+
+        layout.solve(&mut screens)?;
+
+        // Outputs cloned on one crtc appear more than once. The ones that were
+        // positioned by a constraint take precedence.
+        let mut new_crtcs: HashMap<XId, Crtc> = HashMap::new();
+        let (constrained, others): (Vec<_>, Vec<_>) = screens
+            .into_iter()
+            .partition(|(name, _)| layout.constraints.iter().any(|c| &c.output == name));
+        for (_, crtc) in others.into_iter().chain(constrained) {
+            new_crtcs.insert(crtc.xid, crtc);
+        }
+
+        let mut new_crtcs: Vec<Crtc> = new_crtcs.into_values().collect();
+        self.apply_new_crtcs(&mut new_crtcs)
+    }
+
     /// Sets the position of a given output, relative to another
     ///
     /// # Arguments
@@ -325,84 +946,277 @@ impl XHandle {
         self.apply_new_crtcs(&mut [crtc])
     }
 
-    /// Applies some set of altered crtcs
-    /// Due to xrandr's structure, changing one or more crtcs properly can be
-    /// quite complicated. One should therefore call this function on any crtcs
-    /// that you want to change.
+    /// The number of entries per channel in the gamma ramp of a given
+    /// output
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::GetGamma(xid)` - The crtc does not support gamma ramps
+    ///
+    pub fn gamma_size(&mut self, output: &Output) -> Result<usize, XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
+        gamma::gamma_size(self, crtc_id)
+    }
+
+    /// Gets the gamma ramp of a given output
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::GetGamma(xid)` - The call to the x backend failed
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let ramp = xhandle.gamma(&dp_1)?;
+    /// println!("{} entries", ramp.size());
+    /// ```
+    ///
+    pub fn gamma(&mut self, output: &Output) -> Result<GammaRamp, XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
+        GammaRamp::get(self, crtc_id)
+    }
+
+    /// Sets the gamma ramp of a given output
+    ///
     /// # Arguments
-    /// * `changes`
-    ///     Altered crtcs. Must be mutable because of crct.apply() calls.
+    /// * `output` - The output to set the gamma ramp of
+    /// * `ramp` - The new gamma ramp, with `gamma_size(output)` entries
     ///
-    fn apply_new_crtcs(&mut self, changed: &mut [Crtc]) -> Result<(), XrandrError> {
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::GammaSizeMismatch` - The ramp has the wrong size
+    /// * `XrandrError::SetGamma(xid)` - The call to the x backend failed
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let size = xhandle.gamma_size(&dp_1)?;
+    /// xhandle.set_gamma(&dp_1, &GammaRamp::linear(size))?;
+    /// ```
+    ///
+    pub fn set_gamma(&mut self, output: &Output, ramp: &GammaRamp) -> Result<(), XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
+        ramp.set(self, crtc_id)
+    }
+
+    /// Adjusts the colors of a given output through its gamma ramp, e.g. to
+    /// dim it or to make it warmer at night. Replaces any earlier adjustment
+    /// or gamma ramp, including the calibration `apply_icc_profile` loads.
+    /// To keep that, adjust the calibration with `ColorAdjustment::apply_to`
+    /// and pass the result to `set_gamma`.
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::InvalidColorAdjustment` - See `ColorAdjustment::ramp`
+    /// * `XrandrError::_` - See `set_gamma`
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Like `xrandr --output DP-1 --brightness 0.8` at 4500K
+    /// let adjustment = ColorAdjustment::new()
+    ///     .with_brightness(0.8)
+    ///     .with_temperature(4500);
+    /// xhandle.adjust_colors(&dp_1, &adjustment)?;
+    /// ```
+    ///
+    pub fn adjust_colors(
+        &mut self,
+        output: &Output,
+        adjustment: &ColorAdjustment,
+    ) -> Result<(), XrandrError> {
+        let ramp = adjustment.ramp(self.gamma_size(output)?)?;
+        self.set_gamma(output, &ramp)
+    }
+
+    /// Reverts any color adjustment or gamma ramp of a given output to a
+    /// linear one. This also discards an ICC profile's calibration.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - See `adjust_colors`
+    ///
+    pub fn reset_colors(&mut self, output: &Output) -> Result<(), XrandrError> {
+        self.adjust_colors(output, &ColorAdjustment::default())
+    }
+
+    /// Loads the calibration curves (`vcgt` tag) of an ICC profile into the
+    /// gamma ramp of a given output. Profiles without calibration curves
+    /// reset the gamma ramp.
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidIccProfile` - The `vcgt` tag is malformed
+    /// * `XrandrError::_` - See `set_gamma`
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let profile = IccProfile::from_file("/usr/share/color/icc/dell.icc")?;
+    /// xhandle.apply_icc_profile(&dp_1, &profile)?;
+    /// xhandle.set_icc_profile_atom(&profile);
+    /// ```
+    ///
+    pub fn apply_icc_profile(
+        &mut self,
+        output: &Output,
+        profile: &IccProfile,
+    ) -> Result<(), XrandrError> {
+        let size = self.gamma_size(output)?;
+        let ramp = match profile.vcgt()? {
+            Some(vcgt) => vcgt.ramp(size),
+            None => GammaRamp::linear(size),
+        };
+        self.set_gamma(output, &ramp)
+    }
+
+    /// Publishes an ICC profile as the `_ICC_PROFILE` property of the root
+    /// window, like colord and xcalib do, so color managed applications
+    /// pick it up
+    pub fn set_icc_profile_atom(&mut self, profile: &IccProfile) {
+        icc::set_root_profile(self, profile);
+    }
+
+    /// Sets the backlight brightness of a given output, see
+    /// `Output::backlight`. Values outside of 0 to 100 percent are clamped.
+    ///
+    /// # Errors
+    /// * `XrandrError::NoBacklight(name)` - The output has no backlight
+    /// * `XrandrError::Io` - Accessing the sysfs backlight device failed
+    ///
+    /// # Examples
+    /// ```ignore
+    /// xhandle.set_backlight(&edp_1, 40.0)?;
+    /// ```
+    ///
+    pub fn set_backlight(&mut self, output: &Output, percent: f64) -> Result<(), XrandrError> {
+        let current = output
+            .backlight()?
+            .ok_or(XrandrError::NoBacklight(output.name.clone()))?;
+
+        backlight::set(self, output.xid, &current, percent)
+    }
+
+    /// Sets or removes the panning area of a given output. The screen grows
+    /// as needed to fit the panning area.
+    ///
+    /// # Arguments
+    /// * `output` - The output that should pan
+    /// * `panning` - The new panning area, `None` to stop panning
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::SetPanning(xid)` - The x backend rejected the panning
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Like `xrandr --output eDP-1 --panning 2560x1600`
+    /// xhandle.set_panning(&edp_1, Some(&Panning::new(0, 0, 2560, 1600)))?;
+    /// ```
+    ///
+    pub fn set_panning(
+        &mut self,
+        output: &Output,
+        panning: Option<&Panning>,
+    ) -> Result<(), XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
+        let mut crtc = ScreenResources::new(self)?.crtc(self, crtc_id)?;
+        crtc.panning = panning.copied();
+
+        self.apply_new_crtcs(&mut [crtc])
+    }
+
+    /// Sets the reflection of a given output, keeping its rotation
+    ///
+    /// # Arguments
+    /// * `output` - The output to reflect
+    /// * `reflection`
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Like `xrandr --output DP-1 --reflect x`
+    /// xhandle.set_reflection(dp_1, &Reflection::X)?;
+    /// ```
+    ///
+    pub fn set_reflection(
+        &mut self,
+        output: &Output,
+        reflection: &Reflection,
+    ) -> Result<(), XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
         let res = ScreenResources::new(self)?;
-        let old_crtcs = res.enabled_crtcs(self)?;
-
-        // Construct new crtcs out of the old ones and the new where provided
-        let mut changed_map: HashMap<XId, Crtc> = HashMap::new();
-        changed.iter().cloned().for_each(|c| {
-            changed_map.insert(c.xid, c);
-        });
-
-        let mut new_crtcs: Vec<Crtc> = Vec::new();
-        for crtc in &old_crtcs {
-            match changed_map.remove(&crtc.xid) {
-                None => new_crtcs.push(crtc.clone()),
-                Some(c) => new_crtcs.push(c.clone()),
-            }
-        }
-        new_crtcs.extend(changed_map.drain().map(|(_, v)| v));
-
-        // In case the top-left corner is no longer at (0,0), renormalize
-        normalize_positions(&mut new_crtcs);
-        let new_size = ScreenSize::fitting_crtcs(self, &new_crtcs);
-
-        // Disable crtcs that do not fit before setting the new size
-        // Note that this should only be crtcs that were changed, but `changed`
-        // contains the already altered crtc, so we have to use `old_crtcs`
-        let mut old_crtcs = old_crtcs;
-        for crtc in &mut old_crtcs {
-            if !new_size.fits_crtc(crtc) {
-                crtc.set_disable();
-                crtc.apply(self)?;
-            }
-        }
-        self.set_screensize(&new_size);
-
-        // Find the crtcs that were changed. Done this late to also account
-        // for crtcs that were altered by normalize_positions()
-        let mut to_apply: Vec<&mut Crtc> = Vec::new();
-        for pair in old_crtcs.iter().zip_longest(new_crtcs.iter_mut()) {
-            match pair {
-                ZipEntry::Both(old, new) => {
-                    assert!(old.xid == new.xid, "invalid new_crtcs");
-                    if new.timestamp < old.timestamp {
-                        return Err(XrandrError::CrtcChanged(new.xid));
-                    }
-                    if new != old {
-                        to_apply.push(new);
-                    }
-                }
-                ZipEntry::Right(new) => to_apply.push(new),
-                ZipEntry::Left(_) => unreachable!("invalid new_crtcs"),
-            }
-        }
+        let mut crtc = res.crtc(self, crtc_id)?;
+        crtc.reflection = *reflection;
 
-        // Move and re-enable the crtcs
-        to_apply.iter_mut().try_for_each(|c| c.apply(self))
-    }
-
-    /// Sets the screen size in the x backend
-    fn set_screensize(&mut self, size: &ScreenSize) {
-        unsafe {
-            xrandr::XRRSetScreenSize(
-                self.sys.as_ptr(),
-                self.root(),
-                size.width,
-                size.height,
-                size.width_mm,
-                size.height_mm,
-            );
-        }
+        self.apply_new_crtcs(&mut [crtc])
+    }
+
+    /// Sets the transformation of a given output, e.g. to scale it
+    ///
+    /// # Arguments
+    /// * `output` - The output to transform
+    /// * `transform` - The transformation, replacing the current one
+    ///
+    /// # Errors
+    /// * `XrandrError::OutputDisabled(name)` - The output is disabled
+    /// * `XrandrError::InvalidFilter(name)` - The filter contains a nul byte
+    /// * `XrandrError::_` - various calls to the xrandr backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// // Like `xrandr --output eDP-1 --scale 1.5x1.5`
+    /// xhandle.set_transform(&edp_1, &Transform::scale(1.5, 1.5))?;
+    /// ```
+    ///
+    pub fn set_transform(
+        &mut self,
+        output: &Output,
+        transform: &Transform,
+    ) -> Result<(), XrandrError> {
+        let crtc_id = output
+            .crtc
+            .ok_or(XrandrError::OutputDisabled(output.name.clone()))?;
+
+        let res = ScreenResources::new(self)?;
+        let mut crtc = res.crtc(self, crtc_id)?;
+        crtc.transform = transform.clone();
+
+        self.apply_new_crtcs(&mut [crtc])
+    }
+
+    /// Applies some set of altered crtcs, see `RandrBackend::apply_crtcs`
+    fn apply_new_crtcs(&mut self, changed: &mut [Crtc]) -> Result<(), XrandrError> {
+        self.apply_crtcs(changed)
+    }
+
+    /// The smallest and largest screen size the x backend supports. Layouts
+    /// that do not fit in the maximum cannot be applied.
+    ///
+    /// # Errors
+    /// * `XrandrError::GetScreenSizeRange` - The query failed
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let range = xhandle.screen_size_range()?;
+    /// println!("At most {}x{}", range.max_width, range.max_height);
+    /// ```
+    ///
+    pub fn screen_size_range(&mut self) -> Result<ScreenSizeRange, XrandrError> {
+        RandrBackend::screen_size_range(self)
     }
 
     fn root(&mut self) -> c_ulong {
@@ -410,12 +1224,16 @@ impl XHandle {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl Drop for XHandle {
     fn drop(&mut self) {
-        unsafe { xlib::XCloseDisplay(self.sys.as_ptr()) };
+        if self.owned {
+            unsafe { xlib::XCloseDisplay(self.sys.as_ptr()) };
+        }
     }
 }
 
+#[cfg(feature = "xlib")]
 fn real_bool(sys: xlib::Bool) -> bool {
     assert!(
         sys == 0 || sys == 1,
@@ -424,6 +1242,7 @@ fn real_bool(sys: xlib::Bool) -> bool {
     sys == 1
 }
 
+#[cfg(feature = "xlib")]
 fn atom_name(handle: &mut HandleSys, atom: xlib::Atom) -> Result<String, XrandrError> {
     let chars = ptr::NonNull::new(unsafe { xlib::XGetAtomName(handle.as_ptr(), atom) })
         .ok_or(XrandrError::GetAtomName(atom))?;
@@ -460,37 +1279,172 @@ pub enum XrandrError {
     InvalidRotation(u16),
 
     #[error("Could not get info on mode with xid {0}")]
-    GetMode(xlib::XID),
+    GetMode(XId),
 
     #[error("Crtc changed since last requesting its state")]
-    CrtcChanged(xlib::XID),
+    CrtcChanged(XId),
 
     #[error("Call to XRRGetCrtcInfo for CRTC with xid {0} failed")]
-    GetCrtcInfo(xlib::XID),
+    GetCrtcInfo(XId),
 
     #[error("Failed to get Crtc: No Crtc with ID {0}")]
-    GetCrtc(xlib::XID),
+    GetCrtc(XId),
 
     #[error("Call to XRRGetOutputInfo for output with xid {0} failed")]
-    GetOutputInfo(xlib::XID),
+    GetOutputInfo(XId),
 
     #[error("No preferred modes found for output with xid {0}")]
-    NoPreferredModes(xlib::XID),
+    NoPreferredModes(XId),
 
     #[error("No mode found with xid {0}")]
-    GetModeInfo(xlib::XID),
+    GetModeInfo(XId),
 
     #[error("Failed to get the properties of output with xid {0}")]
-    GetOutputProp(xlib::XID),
+    GetOutputProp(XId),
 
     #[error("Failed to name of atom {0}")]
-    GetAtomName(xlib::Atom),
+    GetAtomName(c_ulong),
+
+    #[error("No output named '{0}'")]
+    UnknownOutput(String),
+
+    #[error("The output '{0}' is positioned by more than one constraint")]
+    ConflictingConstraints(String),
+
+    #[error("The constraints positioning '{0}' depend on each other")]
+    ConstraintCycle(String),
+
+    #[error("The outputs '{0}' and '{1}' overlap")]
+    Overlap(String, String),
+
+    #[error("The output '{0}' is not adjacent to the other outputs")]
+    Gap(String),
+
+    #[error("Call to XRRGetScreenSizeRange failed")]
+    GetScreenSizeRange,
+
+    #[error("Call to XRRSetPanning for CRTC with xid {0} failed")]
+    SetPanning(XId),
+
+    #[error("Could not get the gamma ramp of CRTC with xid {0}")]
+    GetGamma(XId),
+
+    #[error("Could not set the gamma ramp of CRTC with xid {0}")]
+    SetGamma(XId),
+
+    #[error("Call to XRRGetProviderResources failed")]
+    GetProviders,
+
+    #[error("Call to XRRGetProviderInfo for provider with xid {0} failed")]
+    GetProviderInfo(XId),
+
+    #[error("The provider '{0}' cannot act as {1}")]
+    ProviderCapability(String, &'static str),
+
+    #[error("The configuration of CRTC with xid {0} was rejected")]
+    InvalidCrtcConfig(XId),
+
+    #[error("The output '{0}' is non-desktop")]
+    NonDesktopOutput(String),
+
+    #[error("Leases are not supported: {0}")]
+    LeasesUnsupported(String),
+
+    #[error("Could not create the lease, the x server replied with error code {0}")]
+    CreateLease(u8),
+
+    #[error("Failed to get the properties of provider with xid {0}")]
+    GetProviderProp(XId),
+
+    #[error("No property named '{0}'")]
+    UnknownProperty(String),
+
+    #[error("The property '{0}' is immutable")]
+    ImmutableProperty(String),
+
+    #[error("The value for property '{0}' cannot be written")]
+    UnsupportedPropertyValue(String),
+
+    #[error("The value for property '{0}' has another type than the current one")]
+    PropertyTypeMismatch(String),
+
+    #[error("The value for property '{0}' is not one of its supported values")]
+    PropertyValueNotAllowed(String),
+
+    #[error("Invalid monitor name '{0}'")]
+    InvalidMonitorName(String),
+
+    #[error("The monitor '{0}' does not fit on the screen")]
+    MonitorOutOfScreen(String),
+
+    #[error("Invalid split: {0}")]
+    InvalidSplit(String),
+
+    #[error("No monitor named '{0}'")]
+    UnknownMonitor(String),
+
+    #[error("The output '{0}' has no backlight")]
+    NoBacklight(String),
+
+    #[error("Invalid ICC profile: {0}")]
+    InvalidIccProfile(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[source] std::io::Error),
+
+    #[error("Invalid color adjustment: {0}")]
+    InvalidColorAdjustment(String),
+
+    #[error("CRTC with xid {crtc} needs gamma ramps of size {expected}, got {found}")]
+    GammaSizeMismatch {
+        crtc: XId,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error(
+        "The layout needs a screen of {width}x{height}, but at most \
+        {max_width}x{max_height} is supported"
+    )]
+    ScreenTooLarge {
+        width: i32,
+        height: i32,
+        max_width: i32,
+        max_height: i32,
+    },
+
+    #[error("Cannot calculate the physical size of a {0}x{1} screen")]
+    PhysicalScreenSize(i32, i32),
+
+    #[error("Crtc {0} would be positioned outside the coordinate range")]
+    PositionOutOfRange(XId),
+
+    #[error("X11 protocol error: {0}")]
+    Protocol(String),
+
+    #[error("Call to XInitThreads failed")]
+    InitThreads,
+
+    #[error("The output '{0}' has no mode '{1}'")]
+    UnknownMode(String, String),
+
+    #[error("Could not parse line {0} of the xrandr output: {1}")]
+    ParseDump(usize, String),
+
+    #[error("The filter name '{0}' contains a nul byte")]
+    InvalidFilter(String),
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "xlib"))]
 mod tests {
     use super::*;
 
+    #[test]
+    fn rejects_null_displays() {
+        let null = unsafe { XHandle::from_raw(ptr::null_mut(), true) };
+        assert!(matches!(null, Err(XrandrError::Open)));
+    }
+
     fn handle() -> XHandle {
         XHandle::open().unwrap()
     }
@@ -502,6 +1456,6 @@ mod tests {
 
     #[test]
     fn can_debug_format_monitors() {
-        format!("{:#?}", handle().monitors().unwrap());
+        let _ = format!("{:#?}", handle().monitors().unwrap());
     }
 }
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use crate::crtc::Crtc;
+use crate::{Mode, Output, XId, XrandrError};
+
+// What each output shows when mirroring: the mode it is driven with, and how
+// much it has to be scaled to cover the same area as the first output
+#[derive(Debug, Clone)]
+pub(crate) struct MirrorMode {
+    pub(crate) mode: Mode,
+    pub(crate) scale: (f64, f64),
+}
+
+/// The crtc that can drive all outputs at once, if the outputs can be
+/// cloned. Prefers the crtc the first output is currently using.
+pub(crate) fn shared_crtc<'a>(outputs: &[&Output], crtcs: &'a [Crtc]) -> Option<&'a Crtc> {
+    let xids: HashSet<XId> = outputs.iter().map(|o| o.xid).collect();
+
+    let can_clone = outputs.iter().all(|o| {
+        xids.iter()
+            .all(|xid| *xid == o.xid || o.clones.contains(xid))
+    });
+    if !can_clone {
+        return None;
+    }
+
+    let usable = |c: &&Crtc| {
+        xids.iter().all(|xid| c.possible.contains(xid))
+            && c.outputs.iter().all(|xid| xids.contains(xid))
+    };
+
+    let current = outputs[0].crtc;
+    crtcs
+        .iter()
+        .filter(usable)
+        .find(|c| Some(c.xid) == current)
+        .or_else(|| crtcs.iter().find(usable))
+}
+
+/// Picks a mode for every output so that they all show the same area. Uses
+/// the largest resolution all outputs support, at the highest refresh rate
+/// each output has for it. If there is none, every output uses its preferred
+/// mode, scaled to the first output's size.
+///
+/// # Arguments
+/// * `rotated` - Whether the outputs are rotated by 90 or 270 degrees
+///
+/// # Errors
+/// * `XrandrError::NoPreferredModes(xid)` - Scaling is needed, but an output
+///   has no modes
+pub(crate) fn mirror_modes(
+    outputs: &[&Output],
+    modes: &[Mode],
+    rotated: bool,
+) -> Result<Vec<MirrorMode>, XrandrError> {
+    let supported =
+        |o: &Output| -> Vec<&Mode> { modes.iter().filter(|m| o.modes.contains(&m.xid)).collect() };
+
+    // Resolutions every output supports, largest first
+    let mut sizes: Vec<(u32, u32)> = supported(outputs[0])
+        .iter()
+        .map(|m| (m.width, m.height))
+        .filter(|size| {
+            outputs
+                .iter()
+                .all(|o| supported(o).iter().any(|m| (m.width, m.height) == *size))
+        })
+        .collect();
+    sizes.sort_by_key(|(w, h)| std::cmp::Reverse(u64::from(*w) * u64::from(*h)));
+
+    if let Some(size) = sizes.first() {
+        return Ok(outputs
+            .iter()
+            .map(|o| {
+                let mode = supported(o)
+                    .into_iter()
+                    .filter(|m| (m.width, m.height) == *size)
+                    .max_by(|a, b| by_size_and_rate(a, b))
+                    .cloned()
+                    .expect("size is supported by every output");
+                MirrorMode {
+                    mode,
+                    scale: (1.0, 1.0),
+                }
+            })
+            .collect());
+    }
+
+    let preferred = |o: &Output| -> Result<Mode, XrandrError> {
+        o.preferred_modes
+            .first()
+            .or_else(|| o.modes.first())
+            .and_then(|xid| modes.iter().find(|m| m.xid == *xid))
+            .cloned()
+            .ok_or(XrandrError::NoPreferredModes(o.xid))
+    };
+
+    let on_screen = |m: &Mode| {
+        let (w, h) = (f64::from(m.width), f64::from(m.height));
+        if rotated {
+            (h, w)
+        } else {
+            (w, h)
+        }
+    };
+
+    let (width, height) = on_screen(&preferred(outputs[0])?);
+    outputs
+        .iter()
+        .map(|o| {
+            let mode = preferred(o)?;
+            let (w, h) = on_screen(&mode);
+            Ok(MirrorMode {
+                mode,
+                scale: (width / w, height / h),
+            })
+        })
+        .collect()
+}
+
+/// Finds a crtc for every output. Outputs keep their current crtc where
+/// possible, the others get one that is not in use. A crtc that also drives
+/// outputs which are not mirrored is left to those.
+///
+/// # Errors
+/// * `XrandrError::NoCrtcAvailable` - There are not enough crtcs
+pub(crate) fn assign_crtcs(outputs: &[&Output], crtcs: &[Crtc]) -> Result<Vec<Crtc>, XrandrError> {
+    let xids: HashSet<XId> = outputs.iter().map(|o| o.xid).collect();
+    let mut taken: HashSet<XId> = HashSet::new();
+    let mut assigned = Vec::new();
+
+    for output in outputs {
+        let current = crtcs.iter().find(|c| {
+            Some(c.xid) == output.crtc
+                && c.outputs.iter().all(|xid| xids.contains(xid))
+                && !taken.contains(&c.xid)
+        });
+        let crtc = current
+            .or_else(|| {
+                crtcs.iter().find(|c| {
+                    c.possible.contains(&output.xid)
+                        && c.outputs.is_empty()
+                        && !taken.contains(&c.xid)
+                })
+            })
+            .ok_or(XrandrError::NoCrtcAvailable)?;
+
+        taken.insert(crtc.xid);
+        let mut crtc = crtc.clone();
+        crtc.outputs = vec![output.xid];
+        assigned.push(crtc);
+    }
+
+    Ok(assigned)
+}
+
+/// The crtcs the outputs used before, but not anymore. They are disabled,
+/// unless they also drive outputs which are not mirrored, which keep them.
+pub(crate) fn released_crtcs(outputs: &[&Output], crtcs: &[Crtc], used: &[Crtc]) -> Vec<Crtc> {
+    let xids: HashSet<XId> = outputs.iter().map(|o| o.xid).collect();
+
+    crtcs
+        .iter()
+        .filter(|c| outputs.iter().any(|o| o.crtc == Some(c.xid)))
+        .filter(|c| used.iter().all(|u| u.xid != c.xid))
+        .cloned()
+        .map(|mut c| {
+            c.outputs.retain(|xid| !xids.contains(xid));
+            if c.outputs.is_empty() {
+                c.set_disable();
+            }
+            c
+        })
+        .collect()
+}
+
+fn by_size_and_rate(a: &Mode, b: &Mode) -> std::cmp::Ordering {
+    let area = |m: &Mode| u64::from(m.width) * u64::from(m.height);
+    area(a).cmp(&area(b)).then(a.rate.total_cmp(&b.rate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn modes() -> Vec<Mode> {
+        vec![
+            fixtures::mode(1, 3840, 2160, 60.0),
+            fixtures::mode(2, 1920, 1080, 60.0),
+            fixtures::mode(3, 1920, 1080, 50.0),
+            fixtures::mode(4, 1280, 720, 60.0),
+            fixtures::mode(5, 1366, 768, 60.0),
+        ]
+    }
+
+    fn output(xid: XId, modes: &[XId]) -> Output {
+        let mut output = fixtures::output(xid, &format!("out-{}", xid));
+        output.modes = modes.to_vec();
+        output.preferred_modes = modes[..1].to_vec();
+        output
+    }
+
+    #[test]
+    fn uses_largest_common_resolution() {
+        let a = output(10, &[1, 2, 4]);
+        let b = output(11, &[3, 4]);
+
+        let mirrored = mirror_modes(&[&a, &b], &modes(), false).unwrap();
+        assert_eq!(mirrored[0].mode.xid, 2);
+        assert_eq!(mirrored[1].mode.xid, 3);
+        assert_eq!(mirrored[1].scale, (1.0, 1.0));
+    }
+
+    #[test]
+    fn scales_without_common_resolution() {
+        let a = output(10, &[1]);
+        let b = output(11, &[2]);
+
+        let mirrored = mirror_modes(&[&a, &b], &modes(), false).unwrap();
+        assert_eq!(mirrored[0].scale, (1.0, 1.0));
+        assert_eq!(mirrored[1].mode.xid, 2);
+        assert_eq!(mirrored[1].scale, (2.0, 2.0));
+    }
+
+    #[test]
+    fn shares_crtc_between_clones() {
+        let mut a = output(10, &[2]);
+        let mut b = output(11, &[2]);
+
+        let mut crtc = fixtures::crtc(20, 0, 0, 1920, 1080);
+        crtc.outputs = vec![];
+        crtc.possible = vec![10, 11];
+        let crtcs = [fixtures::crtc(21, 0, 0, 1920, 1080), crtc];
+
+        assert!(shared_crtc(&[&a, &b], &crtcs).is_none());
+
+        a.clones = vec![11];
+        b.clones = vec![10];
+        assert_eq!(shared_crtc(&[&a, &b], &crtcs).unwrap().xid, 20);
+    }
+
+    #[test]
+    fn assigns_free_crtcs() {
+        let mut a = output(10, &[2]);
+        let b = output(11, &[2]);
+        a.crtc = Some(20);
+
+        let mut free = fixtures::crtc(21, 0, 0, 0, 0);
+        free.outputs = vec![];
+        free.possible = vec![10, 11];
+        let mut current = fixtures::crtc(20, 0, 0, 1920, 1080);
+        current.outputs = vec![10];
+        let mut crtcs = [current, free];
+
+        let assigned = assign_crtcs(&[&a, &b], &crtcs).unwrap();
+        assert_eq!(assigned[0].xid, 20);
+        assert_eq!(assigned[1].xid, 21);
+        assert_eq!(assigned[1].outputs, vec![11]);
+
+        let c = output(12, &[2]);
+        assert!(matches!(
+            assign_crtcs(&[&a, &b, &c], &crtcs),
+            Err(XrandrError::NoCrtcAvailable)
+        ));
+
+        // The current crtc also drives another output, which keeps it
+        crtcs[0].outputs = vec![10, 12];
+        let assigned = assign_crtcs(&[&a], &crtcs).unwrap();
+        assert_eq!(assigned[0].xid, 21);
+        let released = released_crtcs(&[&a], &crtcs, &assigned);
+        assert_eq!(released.len(), 1);
+        assert_eq!((released[0].xid, released[0].mode), (20, 1));
+        assert_eq!(released[0].outputs, vec![12]);
+
+        crtcs[0].outputs = vec![10];
+        let released = released_crtcs(&[&a], &crtcs, &assigned);
+        assert_eq!((released[0].mode, released[0].outputs.len()), (0, 0));
+    }
+}
@@ -1,4 +1,6 @@
+#[cfg(feature = "xlib")]
 use std::slice;
+#[cfg(feature = "xlib")]
 use x11::xrandr;
 
 use crate::XId;
@@ -28,28 +30,38 @@ pub struct Mode {
     pub rate: f64,
 }
 
+// Calculate the refresh rate for a mode
+// This is not given by xrandr, but tends to be useful for end-users
+pub(crate) fn refresh_rate(dot_clock: u64, htotal: u32, vtotal: u32, flags: u64) -> f64 {
+    assert!(
+        htotal != 0 && vtotal != 0,
+        "Framerate calculation would divide by zero"
+    );
+
+    let v_total = if flags & RR_DOUBLE_SCAN != 0 {
+        vtotal * 2
+    } else if flags & RR_INTERLACE != 0 {
+        vtotal / 2
+    } else {
+        vtotal
+    };
+
+    dot_clock as f64 / (f64::from(htotal) * f64::from(v_total))
+}
+
+#[cfg(feature = "xlib")]
 impl From<&xrandr::XRRModeInfo> for Mode {
     fn from(x_mode: &xrandr::XRRModeInfo) -> Self {
         let name_b =
             unsafe { slice::from_raw_parts(x_mode.name as *const u8, x_mode.nameLength as usize) };
 
-        // Calculate the refresh rate for this mode
-        // This is not given by xrandr, but tends to be useful for end-users
-        assert!(
-            x_mode.hTotal != 0 && x_mode.vTotal != 0,
-            "Framerate calculation would divide by zero"
+        let rate = refresh_rate(
+            x_mode.dotClock,
+            x_mode.hTotal,
+            x_mode.vTotal,
+            x_mode.modeFlags,
         );
 
-        let v_total = if x_mode.modeFlags & RR_DOUBLE_SCAN != 0 {
-            x_mode.vTotal * 2
-        } else if x_mode.modeFlags & RR_INTERLACE != 0 {
-            x_mode.vTotal / 2
-        } else {
-            x_mode.vTotal
-        };
-
-        let rate = x_mode.dotClock as f64 / (f64::from(x_mode.hTotal) * f64::from(v_total));
-
         Self {
             xid: x_mode.id,
             name: String::from_utf8_lossy(name_b).into_owned(),
@@ -1,20 +1,31 @@
+#[cfg(feature = "xlib")]
 use core::ptr;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "xlib")]
+use std::ffi::CString;
+#[cfg(feature = "xlib")]
 use std::slice;
-use x11::xrandr;
+#[cfg(feature = "xlib")]
+use x11::{xlib, xrandr};
 
 use crate::output::Output;
+#[cfg(feature = "xlib")]
 use crate::XHandle;
+#[cfg(feature = "xlib")]
+use crate::XId;
+#[cfg(feature = "xlib")]
 use crate::XrandrError;
 
 // A wrapper that drops the pointer if it goes out of scope.
 // Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
 pub(crate) struct MonitorHandle {
     ptr: ptr::NonNull<xrandr::XRRMonitorInfo>,
     count: i32,
 }
 
+#[cfg(feature = "xlib")]
 impl MonitorHandle {
     pub(crate) fn new(handle: &mut XHandle) -> Result<Self, XrandrError> {
         let mut count = 0;
@@ -36,6 +47,7 @@ impl MonitorHandle {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl Drop for MonitorHandle {
     fn drop(&mut self) {
         unsafe { xrandr::XRRFreeMonitors(self.ptr.as_ptr()) };
@@ -58,3 +70,135 @@ pub struct Monitor {
     /// can have more than one output.
     pub outputs: Vec<Output>,
 }
+
+// Where a monitor is on the screen, and its physical size. Used to create
+// monitors, see `XHandle::create_monitor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct MonitorGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width_px: i32,
+    pub height_px: i32,
+    pub width_mm: i32,
+    pub height_mm: i32,
+}
+
+/// Checks that a monitor called `name` can be created at `geometry` on a
+/// screen of `screen` (width, height) pixels
+///
+/// # Errors
+/// * `XrandrError::InvalidMonitorName(name)` - The name is empty
+/// * `XrandrError::MonitorOutOfScreen(name)` - The monitor is empty, or
+///   (partly) outside of the screen
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn check_geometry(
+    name: &str,
+    geometry: &MonitorGeometry,
+    screen: (i32, i32),
+) -> Result<(), XrandrError> {
+    if name.is_empty() || name.contains('\0') {
+        return Err(XrandrError::InvalidMonitorName(name.to_string()));
+    }
+
+    let MonitorGeometry {
+        x,
+        y,
+        width_px,
+        height_px,
+        ..
+    } = *geometry;
+    let right = i64::from(x) + i64::from(width_px);
+    let bottom = i64::from(y) + i64::from(height_px);
+
+    if width_px <= 0
+        || height_px <= 0
+        || x < 0
+        || y < 0
+        || right > i64::from(screen.0)
+        || bottom > i64::from(screen.1)
+    {
+        return Err(XrandrError::MonitorOutOfScreen(name.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Creates a monitor, or replaces the one with the same name
+#[cfg(feature = "xlib")]
+pub(crate) fn set(handle: &mut XHandle, name: &str, geometry: &MonitorGeometry, outputs: &[XId]) {
+    let name = CString::new(name).expect("Monitor names are checked for nul bytes");
+    let mut outputs = outputs.to_vec();
+
+    unsafe {
+        let mut info = xrandr::XRRMonitorInfo {
+            name: xlib::XInternAtom(handle.sys.as_ptr(), name.as_ptr(), xlib::False),
+            primary: xlib::False,
+            automatic: xlib::False,
+            noutput: i32::try_from(outputs.len()).unwrap(),
+            x: geometry.x,
+            y: geometry.y,
+            width: geometry.width_px,
+            height: geometry.height_px,
+            mwidth: geometry.width_mm,
+            mheight: geometry.height_mm,
+            outputs: outputs.as_mut_ptr(),
+        };
+
+        xrandr::XRRSetMonitor(handle.sys.as_ptr(), handle.root(), &mut info);
+        xlib::XSync(handle.sys.as_ptr(), xlib::False);
+    }
+}
+
+/// Deletes the monitor called `name`, which has to exist
+#[cfg(feature = "xlib")]
+pub(crate) fn delete(handle: &mut XHandle, name: &str) {
+    let name = CString::new(name).expect("Names of existing monitors contain no nul bytes");
+
+    unsafe {
+        let atom = xlib::XInternAtom(handle.sys.as_ptr(), name.as_ptr(), xlib::True);
+        xrandr::XRRDeleteMonitor(handle.sys.as_ptr(), handle.root(), atom);
+        xlib::XSync(handle.sys.as_ptr(), xlib::False);
+    }
+}
+
+#[cfg(all(test, feature = "xlib"))]
+mod tests {
+    use super::*;
+
+    fn geometry(x: i32, y: i32, width_px: i32, height_px: i32) -> MonitorGeometry {
+        MonitorGeometry {
+            x,
+            y,
+            width_px,
+            height_px,
+            width_mm: 0,
+            height_mm: 0,
+        }
+    }
+
+    #[test]
+    fn checks_geometry_against_screen() {
+        let screen = (3440, 1440);
+        assert!(check_geometry("left", &geometry(0, 0, 1720, 1440), screen).is_ok());
+        assert!(check_geometry("right", &geometry(1720, 0, 1720, 1440), screen).is_ok());
+
+        for outside in [
+            geometry(1720, 0, 1721, 1440),
+            geometry(-1, 0, 100, 100),
+            geometry(0, 0, 0, 1440),
+            geometry(i32::MAX, 0, 1, 1),
+        ] {
+            assert!(matches!(
+                check_geometry("wall", &outside, screen),
+                Err(XrandrError::MonitorOutOfScreen(_))
+            ));
+        }
+
+        assert!(matches!(
+            check_geometry("", &geometry(0, 0, 100, 100), screen),
+            Err(XrandrError::InvalidMonitorName(_))
+        ));
+    }
+}
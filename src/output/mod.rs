@@ -1,20 +1,31 @@
 pub mod property;
 
+use crate::backlight::{self, Backlight};
+#[cfg(feature = "xlib")]
 use crate::screen_resources::ScreenResourcesHandle;
-use crate::{ScreenResources, XHandle, XrandrError};
+use crate::XrandrError;
+#[cfg(feature = "xlib")]
+use crate::{ScreenResources, XHandle};
 use indexmap::IndexMap;
+#[cfg(feature = "xlib")]
+use property::PropertyOwner;
 use property::{Property, Value};
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "xlib")]
 use std::os::raw::c_int;
+use std::path::Path;
+#[cfg(feature = "xlib")]
 use std::{ptr, slice};
-use x11::{xlib, xrandr};
+#[cfg(feature = "xlib")]
+use x11::xrandr;
 
 use crate::XId;
 use crate::XTime;
+#[cfg(feature = "xlib")]
 use crate::CURRENT_TIME;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Output {
     pub xid: XId,
@@ -36,10 +47,12 @@ pub struct Output {
 
 // A wrapper that drops the pointer if it goes out of scope.
 // Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
 struct OutputHandle {
     ptr: ptr::NonNull<xrandr::XRROutputInfo>,
 }
 
+#[cfg(feature = "xlib")]
 impl OutputHandle {
     fn new(handle: &mut XHandle, xid: XId) -> Result<Self, XrandrError> {
         let res = ScreenResourcesHandle::new(handle)?;
@@ -52,6 +65,7 @@ impl OutputHandle {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl Drop for OutputHandle {
     fn drop(&mut self) {
         unsafe { xrandr::XRRFreeOutputInfo(self.ptr.as_ptr()) };
@@ -74,6 +88,33 @@ impl Output {
         })
     }
 
+    /// A short, stable identifier of the display connected to this output,
+    /// derived from its EDID. Used to recognize a display across connectors
+    /// and sessions, e.g. in a [`Profile`](crate::Profile).
+    #[must_use]
+    pub fn fingerprint(&self) -> Option<String> {
+        self.edid().map(|edid| format!("{:016x}", fnv1a(&edid)))
+    }
+
+    /// The brightness of this output's backlight. Read from the `Backlight`
+    /// (or legacy `BACKLIGHT`) output property, or, for internal panels
+    /// without one, from `/sys/class/backlight`.
+    ///
+    /// # Errors
+    /// * `XrandrError::Io` - A sysfs backlight device could not be read
+    ///
+    /// # Examples
+    /// ```ignore
+    /// if let Some(backlight) = edp_1.backlight()? {
+    ///     println!("{:.0}%", backlight.percent());
+    /// }
+    /// ```
+    ///
+    pub fn backlight(&self) -> Result<Option<Backlight>, XrandrError> {
+        backlight::find(self, Path::new(backlight::SYSFS_ROOT))
+    }
+
+    #[cfg(feature = "xlib")]
     pub(crate) fn from_xid(handle: &mut XHandle, xid: u64) -> Result<Self, XrandrError> {
         let output_info = OutputHandle::new(handle, xid)?;
 
@@ -117,7 +158,7 @@ impl Output {
         let name_b = unsafe { slice::from_raw_parts(*name as *const u8, *nameLen as usize) };
 
         let name = String::from_utf8_lossy(name_b).to_string();
-        let properties = Self::get_props(handle, xid)?;
+        let properties = Property::all(handle, PropertyOwner::Output(xid))?;
         let connected = c_int::from(*connection) == xrandr::RR_Connected;
 
         let result = Self {
@@ -141,29 +182,7 @@ impl Output {
         Ok(result)
     }
 
-    fn get_props(
-        handle: &mut XHandle,
-        xid: xlib::XID,
-    ) -> Result<IndexMap<String, Property>, XrandrError> {
-        let mut props_len = 0;
-        let props_data =
-            unsafe { xrandr::XRRListOutputProperties(handle.sys.as_ptr(), xid, &mut props_len) };
-
-        let props_slice = unsafe { slice::from_raw_parts(props_data, props_len as usize) };
-
-        let props = props_slice
-            .iter()
-            .map(|prop_id| {
-                let prop = Property::get(handle, xid, *prop_id)?;
-                Ok((prop.name.clone(), prop))
-            })
-            .collect();
-
-        unsafe { xlib::XFree(props_data.cast()) };
-
-        props
-    }
-
+    #[cfg(feature = "xlib")]
     pub(crate) unsafe fn from_list(
         handle: &mut XHandle,
         data: *mut xrandr::RROutput,
@@ -176,7 +195,15 @@ impl Output {
     }
 }
 
-#[cfg(test)]
+// 64 bit FNV-1a. Unlike `DefaultHasher`, its output is guaranteed to stay
+// the same between Rust releases, so fingerprints can be saved to disk.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(all(test, feature = "xlib"))]
 mod tests {
     use crate::XHandle;
 
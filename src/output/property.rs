@@ -1,13 +1,153 @@
+#[cfg(feature = "xlib")]
 use std::convert::TryInto;
-use std::{ptr, slice};
-
+#[cfg(feature = "xlib")]
+use std::ffi::CString;
+use std::os::raw::c_ulong;
+#[cfg(feature = "xlib")]
+use std::os::raw::{c_int, c_long};
+#[cfg(feature = "xlib")]
+use std::{mem, ptr, slice};
+
+#[cfg(feature = "xlib")]
+use indexmap::IndexMap;
 #[cfg(feature = "serialize")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "xlib")]
 use x11::{xlib, xrandr};
 
-use crate::{atom_name, real_bool, HandleSys, XHandle, XrandrError};
+#[cfg(feature = "xlib")]
+use crate::{atom_name, real_bool, HandleSys, XHandle, XId, XrandrError};
+
+// Outputs and providers both have properties, which only differ in the calls
+// used to access them
+#[cfg(feature = "xlib")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PropertyOwner {
+    Output(XId),
+    Provider(XId),
+}
+
+#[cfg(feature = "xlib")]
+impl PropertyOwner {
+    fn error(self) -> XrandrError {
+        match self {
+            Self::Output(xid) => XrandrError::GetOutputProp(xid),
+            Self::Provider(xid) => XrandrError::GetProviderProp(xid),
+        }
+    }
+
+    unsafe fn list(self, handle: &mut HandleSys, len: &mut c_int) -> *mut xlib::Atom {
+        match self {
+            Self::Output(xid) => xrandr::XRRListOutputProperties(handle.as_ptr(), xid, len),
+            Self::Provider(xid) => xrandr::XRRListProviderProperties(handle.as_ptr(), xid, len),
+        }
+    }
 
-#[derive(Debug)]
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn get(
+        self,
+        handle: &mut HandleSys,
+        id: xlib::Atom,
+        value_type: &mut xlib::Atom,
+        format: &mut c_int,
+        items_len: &mut c_ulong,
+        bytes_after: &mut c_ulong,
+        prop: &mut *mut u8,
+    ) -> c_int {
+        let get = match self {
+            Self::Output(_) => xrandr::XRRGetOutputProperty,
+            Self::Provider(_) => xrandr::XRRGetProviderProperty,
+        };
+        let (Self::Output(xid) | Self::Provider(xid)) = self;
+
+        get(
+            handle.as_ptr(),
+            xid,
+            id,
+            0,
+            100,
+            xlib::False,
+            xlib::False,
+            xlib::AnyPropertyType as xlib::Atom,
+            value_type,
+            format,
+            items_len,
+            bytes_after,
+            prop,
+        )
+    }
+
+    unsafe fn query(self, handle: &mut HandleSys, id: xlib::Atom) -> *mut xrandr::XRRPropertyInfo {
+        match self {
+            Self::Output(xid) => xrandr::XRRQueryOutputProperty(handle.as_ptr(), xid, id),
+            Self::Provider(xid) => xrandr::XRRQueryProviderProperty(handle.as_ptr(), xid, id),
+        }
+    }
+
+    unsafe fn change(self, handle: &mut HandleSys, id: xlib::Atom, raw: &RawValue) {
+        let change = match self {
+            Self::Output(_) => xrandr::XRRChangeOutputProperty,
+            Self::Provider(_) => xrandr::XRRChangeProviderProperty,
+        };
+        let (Self::Output(xid) | Self::Provider(xid)) = self;
+
+        change(
+            handle.as_ptr(),
+            xid,
+            id,
+            raw.value_type,
+            raw.format,
+            xlib::PropModeReplace,
+            raw.data.as_ptr(),
+            raw.len,
+        );
+    }
+}
+
+/// Checks that `properties` has a mutable property called `name`, which
+/// `value` can replace
+///
+/// # Errors
+/// * `XrandrError::UnknownProperty(name)` - There is no such property
+/// * `XrandrError::ImmutableProperty(name)` - The property is read-only
+/// * `XrandrError::PropertyTypeMismatch(name)` - `value` has another type
+///   than the current value
+/// * `XrandrError::PropertyValueNotAllowed(name)` - `value` is not one of the
+///   supported values, or not within the ranges, of the property. The x
+///   backend would fail with a (fatal) BadValue.
+///
+#[cfg(feature = "xlib")]
+pub(crate) fn check_settable(
+    properties: &IndexMap<String, Property>,
+    name: &str,
+    value: &Value,
+) -> Result<(), XrandrError> {
+    match properties.get(name) {
+        None => Err(XrandrError::UnknownProperty(name.to_string())),
+        Some(property) if property.is_immutable => {
+            Err(XrandrError::ImmutableProperty(name.to_string()))
+        }
+        Some(property) if mem::discriminant(&property.value) != mem::discriminant(value) => {
+            Err(XrandrError::PropertyTypeMismatch(name.to_string()))
+        }
+        Some(property) if !property.values.as_ref().map_or(true, |v| v.allow(value)) => {
+            Err(XrandrError::PropertyValueNotAllowed(name.to_string()))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+// A value as the x backend takes it. Items of format 32 are longs.
+#[cfg(feature = "xlib")]
+#[derive(Debug, PartialEq, Eq)]
+struct RawValue {
+    value_type: xlib::Atom,
+    format: c_int,
+    data: Vec<u8>,
+    len: c_int,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Property {
     pub name: String,
@@ -17,10 +157,44 @@ pub struct Property {
     pub is_pending: bool,
 }
 
+#[cfg(feature = "xlib")]
 impl Property {
+    /// Gets every property of an output or provider, by name
+    ///
+    /// # Errors
+    /// * `XrandrError::GetOutputProp(xid)` - Getting an output's property
+    ///   failed
+    /// * `XrandrError::GetProviderProp(xid)` - Getting a provider's property
+    ///   failed
+    ///
+    pub(crate) fn all(
+        handle: &mut XHandle,
+        owner: PropertyOwner,
+    ) -> Result<IndexMap<String, Property>, XrandrError> {
+        let mut props_len = 0;
+        let props_data = unsafe { owner.list(&mut handle.sys, &mut props_len) };
+        if props_data.is_null() {
+            return Ok(IndexMap::new());
+        }
+
+        let props_slice = unsafe { slice::from_raw_parts(props_data, props_len as usize) };
+
+        let props = props_slice
+            .iter()
+            .map(|prop_id| {
+                let prop = Property::get(handle, owner, *prop_id)?;
+                Ok((prop.name.clone(), prop))
+            })
+            .collect();
+
+        unsafe { xlib::XFree(props_data.cast()) };
+
+        props
+    }
+
     pub(crate) fn get(
         handle: &mut XHandle,
-        output: xlib::XID,
+        owner: PropertyOwner,
         id: xlib::Atom,
     ) -> Result<Property, XrandrError> {
         // Based on https://gitlab.freedesktop.org/xorg/app/xrandr/-/blob/master/xrandr.c#L3867
@@ -34,15 +208,9 @@ impl Property {
         let mut prop = ptr::null_mut();
 
         unsafe {
-            let status = xrandr::XRRGetOutputProperty(
-                handle.sys.as_ptr(),
-                output,
+            let status = owner.get(
+                &mut handle.sys,
                 id,
-                0,
-                100,
-                xlib::False,
-                xlib::False,
-                xlib::AnyPropertyType as xlib::Atom,
                 &mut value_type,
                 &mut format,
                 &mut items_len,
@@ -51,7 +219,7 @@ impl Property {
             );
 
             if status != 0 {
-                return Err(XrandrError::GetOutputProp(output));
+                return Err(owner.error());
             }
         };
 
@@ -61,12 +229,7 @@ impl Property {
         let value = Self::get_value(&mut handle.sys, &name, value_type, format, items_len, prop)?;
 
         let info = unsafe {
-            ptr::NonNull::new(xrandr::XRRQueryOutputProperty(
-                handle.sys.as_ptr(),
-                output,
-                id,
-            ))
-            .ok_or(XrandrError::GetOutputProp(output))?
+            ptr::NonNull::new(owner.query(&mut handle.sys, id)).ok_or_else(|| owner.error())?
         };
 
         let is_immutable = unsafe { real_bool(info.as_ref().immutable) };
@@ -89,6 +252,38 @@ impl Property {
         })
     }
 
+    /// Sets the property called `name` of an output or provider to `value`
+    ///
+    /// # Errors
+    /// * `XrandrError::UnsupportedPropertyValue(name)` - `value` cannot be
+    ///   written
+    ///
+    pub(crate) fn set(
+        handle: &mut XHandle,
+        owner: PropertyOwner,
+        name: &str,
+        value: &Value,
+    ) -> Result<(), XrandrError> {
+        let mut intern = |name: &str| {
+            let name = CString::new(name).ok()?;
+            let atom =
+                unsafe { xlib::XInternAtom(handle.sys.as_ptr(), name.as_ptr(), xlib::False) };
+            Some(atom)
+        };
+
+        let id =
+            intern(name).ok_or_else(|| XrandrError::UnsupportedPropertyValue(name.to_string()))?;
+        let raw = value
+            .to_raw(&mut intern)
+            .ok_or_else(|| XrandrError::UnsupportedPropertyValue(name.to_string()))?;
+
+        unsafe {
+            owner.change(&mut handle.sys, id, &raw);
+            xlib::XSync(handle.sys.as_ptr(), xlib::False);
+        }
+        Ok(())
+    }
+
     fn get_value(
         handle: &mut HandleSys,
         name: &str,
@@ -174,6 +369,7 @@ impl Property {
     }
 }
 
+#[cfg(feature = "xlib")]
 #[derive(Debug, Clone, Copy)]
 enum ValueType {
     Atom,
@@ -182,6 +378,7 @@ enum ValueType {
     Unrecognized(xlib::Atom),
 }
 
+#[cfg(feature = "xlib")]
 impl From<xlib::Atom> for ValueType {
     fn from(value: xlib::Atom) -> Self {
         match value {
@@ -193,6 +390,7 @@ impl From<xlib::Atom> for ValueType {
     }
 }
 
+#[cfg(feature = "xlib")]
 #[derive(Debug, Clone, Copy)]
 enum ValueFormat {
     B8,
@@ -200,6 +398,7 @@ enum ValueFormat {
     B32,
 }
 
+#[cfg(feature = "xlib")]
 impl From<ValueFormat> for i32 {
     fn from(value: ValueFormat) -> Self {
         match value {
@@ -210,6 +409,7 @@ impl From<ValueFormat> for i32 {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl From<i32> for ValueFormat {
     fn from(value: i32) -> Self {
         match value {
@@ -221,7 +421,7 @@ impl From<i32> for ValueFormat {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Value {
     Edid(Vec<u8>),
@@ -233,9 +433,10 @@ pub enum Value {
     Cardinal8(Vec<u8>),
     Cardinal16(Vec<u16>),
     Cardinal32(Vec<u32>),
-    Unrecognized { value_type: xlib::Atom, format: i32 },
+    Unrecognized { value_type: c_ulong, format: i32 },
 }
 
+#[cfg(feature = "xlib")]
 impl Value {
     fn unrecognized(value_type: xlib::Atom, format: ValueFormat) -> Self {
         Self::Unrecognized {
@@ -243,6 +444,47 @@ impl Value {
             format: format.into(),
         }
     }
+
+    // How to pass this value to the x backend, `None` for values that
+    // cannot be written. `intern` gets the atom for a name.
+    fn to_raw(&self, intern: &mut impl FnMut(&str) -> Option<xlib::Atom>) -> Option<RawValue> {
+        fn raw<T: Copy>(value_type: xlib::Atom, format: c_int, items: &[T]) -> Option<RawValue> {
+            let data = items
+                .iter()
+                .flat_map(|item| unsafe {
+                    slice::from_raw_parts((item as *const T).cast::<u8>(), std::mem::size_of::<T>())
+                })
+                .copied()
+                .collect();
+            Some(RawValue {
+                value_type,
+                format,
+                data,
+                len: items.len().try_into().ok()?,
+            })
+        }
+
+        // Format 32 items are passed as longs, even where those are 64 bits
+        let long = |items: Vec<c_long>| raw(xlib::XA_INTEGER, 32, &items);
+
+        match self {
+            Value::Atom(name) => {
+                let atom = intern(name)?;
+                raw(xlib::XA_ATOM, 32, &[atom as c_long])
+            }
+            Value::Integer8(items) => raw(xlib::XA_INTEGER, 8, items),
+            Value::Integer16(items) => raw(xlib::XA_INTEGER, 16, items),
+            Value::Integer32(items) => long(items.iter().map(|i| c_long::from(*i)).collect()),
+            Value::Cardinal8(items) => raw(xlib::XA_CARDINAL, 8, items),
+            Value::Cardinal16(items) => raw(xlib::XA_CARDINAL, 16, items),
+            Value::Cardinal32(items) => {
+                let items: Vec<c_long> = items.iter().map(|i| c_long::from(*i)).collect();
+                raw(xlib::XA_CARDINAL, 32, &items)
+            }
+            Value::Edid(_) | Value::Guid(_) | Value::Unrecognized { .. } => None,
+        }
+    }
+
     fn from_edid(data: *const u8, len: u64) -> Self {
         let edid = unsafe { slice::from_raw_parts(data, len.try_into().unwrap()) };
         Self::Edid(edid.to_vec())
@@ -291,15 +533,49 @@ impl Value {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Values {
     Range(Ranges),
     Supported(Supported),
-    Unrecognized { value_type: xlib::Atom, format: i32 },
+    Unrecognized { value_type: c_ulong, format: i32 },
 }
 
+#[cfg(feature = "xlib")]
 impl Values {
+    // True iff `value` is one of these values, or within these ranges. Items
+    // are checked against the range at their index. Values of another type
+    // and atom ranges are left to the x backend.
+    fn allow(&self, value: &Value) -> bool {
+        fn supported<T: PartialEq>(items: &[T], supported: &[T]) -> bool {
+            items.iter().all(|item| supported.contains(item))
+        }
+        fn in_ranges<T: PartialOrd>(items: &[T], ranges: &[Range<T>]) -> bool {
+            items.len() <= ranges.len()
+                && items
+                    .iter()
+                    .zip(ranges)
+                    .all(|(item, range)| range.lower <= *item && *item <= range.upper)
+        }
+
+        match (value, self) {
+            (Value::Atom(name), Values::Supported(Supported::Atom(names))) => names.contains(name),
+            (Value::Integer8(v), Values::Supported(Supported::Integer8(s))) => supported(v, s),
+            (Value::Integer16(v), Values::Supported(Supported::Integer16(s))) => supported(v, s),
+            (Value::Integer32(v), Values::Supported(Supported::Integer32(s))) => supported(v, s),
+            (Value::Cardinal8(v), Values::Supported(Supported::Cardinal8(s))) => supported(v, s),
+            (Value::Cardinal16(v), Values::Supported(Supported::Cardinal16(s))) => supported(v, s),
+            (Value::Cardinal32(v), Values::Supported(Supported::Cardinal32(s))) => supported(v, s),
+            (Value::Integer8(v), Values::Range(Ranges::Integer8(r))) => in_ranges(v, r),
+            (Value::Integer16(v), Values::Range(Ranges::Integer16(r))) => in_ranges(v, r),
+            (Value::Integer32(v), Values::Range(Ranges::Integer32(r))) => in_ranges(v, r),
+            (Value::Cardinal8(v), Values::Range(Ranges::Cardinal8(r))) => in_ranges(v, r),
+            (Value::Cardinal16(v), Values::Range(Ranges::Cardinal16(r))) => in_ranges(v, r),
+            (Value::Cardinal32(v), Values::Range(Ranges::Cardinal32(r))) => in_ranges(v, r),
+            _ => true,
+        }
+    }
+
     fn unrecognized(value_type: xlib::Atom, format: ValueFormat) -> Self {
         Self::Unrecognized {
             value_type,
@@ -320,7 +596,7 @@ impl From<Supported> for Values {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Ranges {
     Atom(Vec<Range<String>>),
@@ -332,13 +608,14 @@ pub enum Ranges {
     Cardinal32(Vec<Range<u32>>),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Range<T> {
     pub lower: T,
     pub upper: T,
 }
 
+#[cfg(feature = "xlib")]
 impl Ranges {
     fn from_atom(handle: &mut HandleSys, values: &[i64]) -> Result<Self, XrandrError> {
         let values = values
@@ -398,7 +675,7 @@ impl Ranges {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Supported {
     Atom(Vec<String>),
@@ -410,6 +687,7 @@ pub enum Supported {
     Cardinal32(Vec<u32>),
 }
 
+#[cfg(feature = "xlib")]
 impl Supported {
     fn from_atom(handle: &mut HandleSys, values: &[i64]) -> Result<Self, XrandrError> {
         let values = values
@@ -456,3 +734,105 @@ impl Supported {
             .collect()
     }
 }
+
+#[cfg(all(test, feature = "xlib"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_settable_properties() {
+        let mut properties = IndexMap::new();
+        for (name, is_immutable) in [("Broadcast RGB", false), ("EDID", true)] {
+            let property = Property {
+                name: name.to_string(),
+                value: Value::Atom("Automatic".to_string()),
+                values: None,
+                is_immutable,
+                is_pending: false,
+            };
+            properties.insert(name.to_string(), property);
+        }
+
+        let full = Value::Atom("Full".to_string());
+        assert!(check_settable(&properties, "Broadcast RGB", &full).is_ok());
+        assert!(matches!(
+            check_settable(&properties, "Broadcast RGB", &Value::Integer32(vec![1])),
+            Err(XrandrError::PropertyTypeMismatch(_))
+        ));
+        assert!(matches!(
+            check_settable(&properties, "EDID", &full),
+            Err(XrandrError::ImmutableProperty(_))
+        ));
+        assert!(matches!(
+            check_settable(&properties, "max bpc", &full),
+            Err(XrandrError::UnknownProperty(_))
+        ));
+
+        // Only the supported values, and values within the ranges
+        let supported = ["Automatic", "Full", "Limited 16:235"].map(String::from);
+        properties["Broadcast RGB"].values = Some(Supported::Atom(supported.to_vec()).into());
+        assert!(check_settable(&properties, "Broadcast RGB", &full).is_ok());
+        assert!(matches!(
+            check_settable(
+                &properties,
+                "Broadcast RGB",
+                &Value::Atom("Half".to_string())
+            ),
+            Err(XrandrError::PropertyValueNotAllowed(_))
+        ));
+
+        let bpc = Property {
+            name: "max bpc".to_string(),
+            value: Value::Integer32(vec![12]),
+            values: Some(
+                Ranges::Integer32(vec![Range {
+                    lower: 6,
+                    upper: 12,
+                }])
+                .into(),
+            ),
+            is_immutable: false,
+            is_pending: false,
+        };
+        properties.insert(bpc.name.clone(), bpc);
+        let max_bpc =
+            |bpc: Vec<i32>| check_settable(&properties, "max bpc", &Value::Integer32(bpc));
+        assert!(max_bpc(vec![8]).is_ok());
+        assert!(matches!(
+            max_bpc(vec![16]),
+            Err(XrandrError::PropertyValueNotAllowed(_))
+        ));
+        assert!(max_bpc(vec![8, 8]).is_err());
+    }
+
+    #[test]
+    fn encodes_values() {
+        let mut intern = |name: &str| (name == "Full").then_some(42);
+
+        let raw = Value::Integer32(vec![-1, 2]).to_raw(&mut intern).unwrap();
+        assert_eq!(
+            (raw.value_type, raw.format, raw.len),
+            (xlib::XA_INTEGER, 32, 2)
+        );
+        assert_eq!(raw.data.len(), 2 * std::mem::size_of::<c_long>());
+        assert_eq!(&raw.data[..4], &(-1i32).to_ne_bytes());
+
+        let raw = Value::Cardinal16(vec![1, 2, 3])
+            .to_raw(&mut intern)
+            .unwrap();
+        assert_eq!(
+            (raw.value_type, raw.format, raw.len),
+            (xlib::XA_CARDINAL, 16, 3)
+        );
+        assert_eq!(raw.data.len(), 6);
+
+        let raw = Value::Atom("Full".to_string()).to_raw(&mut intern).unwrap();
+        assert_eq!(raw.value_type, xlib::XA_ATOM);
+        assert_eq!(&raw.data[..8], &42i64.to_ne_bytes());
+
+        assert!(Value::Atom("None".to_string())
+            .to_raw(&mut intern)
+            .is_none());
+        assert!(Value::Edid(vec![0; 128]).to_raw(&mut intern).is_none());
+    }
+}
@@ -0,0 +1,211 @@
+#[cfg(feature = "xlib")]
+use std::convert::TryFrom;
+#[cfg(feature = "xlib")]
+use std::ptr;
+
+#[cfg(feature = "xlib")]
+use x11::xrandr;
+
+#[cfg(feature = "xlib")]
+use crate::screen_resources::ScreenResourcesHandle;
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+#[cfg(feature = "xlib")]
+use crate::XId;
+#[cfg(feature = "xlib")]
+use crate::XrandrError;
+
+// A panning area lets a crtc show part of a larger region of the screen. The
+// crtc follows the pointer while it is inside the tracking area, and starts
+// moving once the pointer is within the borders of the crtc's edges. A
+// tracking area of all zeroes tracks the entire panning area.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Panning {
+    pub left: i32,
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    pub track_left: i32,
+    pub track_top: i32,
+    pub track_width: u32,
+    pub track_height: u32,
+    pub border_left: i32,
+    pub border_top: i32,
+    pub border_right: i32,
+    pub border_bottom: i32,
+}
+
+// A wrapper that drops the pointer if it goes out of scope.
+// Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
+struct PanningHandle {
+    ptr: ptr::NonNull<xrandr::XRRPanning>,
+}
+
+#[cfg(feature = "xlib")]
+impl Drop for PanningHandle {
+    fn drop(&mut self) {
+        unsafe { xrandr::XRRFreePanning(self.ptr.as_ptr()) };
+    }
+}
+
+impl Panning {
+    /// A panning area of `width` x `height` pixels at (`left`, `top`),
+    /// tracking the pointer everywhere in it and without borders. Like
+    /// xrandr's `--panning WxH+X+Y`.
+    #[must_use]
+    pub fn new(left: i32, top: i32, width: u32, height: u32) -> Self {
+        Self {
+            left,
+            top,
+            width,
+            height,
+            ..Self::default()
+        }
+    }
+
+    /// Whether this actually pans, i.e. its area is not empty
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.width != 0 && self.height != 0
+    }
+
+    /// The most down and right coordinates of the panning area
+    pub(crate) fn max_coordinates(&self) -> (i32, i32) {
+        let width = i32::try_from(self.width).unwrap_or(i32::MAX);
+        let height = i32::try_from(self.height).unwrap_or(i32::MAX);
+        (
+            self.left.saturating_add(width),
+            self.top.saturating_add(height),
+        )
+    }
+
+    /// Moves the panning and tracking areas, `None` if they would end up
+    /// out of range
+    pub(crate) fn offset(&self, offset: (i32, i32)) -> Option<Self> {
+        let track_offset = if self.track_width == 0 || self.track_height == 0 {
+            (0, 0)
+        } else {
+            offset
+        };
+
+        Some(Self {
+            left: self.left.checked_add(offset.0)?,
+            top: self.top.checked_add(offset.1)?,
+            track_left: self.track_left.checked_add(track_offset.0)?,
+            track_top: self.track_top.checked_add(track_offset.1)?,
+            ..*self
+        })
+    }
+
+    /// Gets the panning of a crtc, `None` if it does not pan or the x
+    /// backend does not support panning
+    ///
+    /// # Errors
+    /// * `XrandrError::GetResources` - Getting the screen resources failed
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn get(handle: &mut XHandle, crtc: XId) -> Result<Option<Self>, XrandrError> {
+        let res = ScreenResourcesHandle::new(handle)?;
+        let raw_ptr = unsafe { xrandr::XRRGetPanning(handle.sys.as_ptr(), res.ptr(), crtc) };
+
+        let Some(ptr) = ptr::NonNull::new(raw_ptr) else {
+            return Ok(None);
+        };
+        let panning_handle = PanningHandle { ptr };
+
+        let xrandr::XRRPanning {
+            left,
+            top,
+            width,
+            height,
+            track_left,
+            track_top,
+            track_width,
+            track_height,
+            border_left,
+            border_top,
+            border_right,
+            border_bottom,
+            ..
+        } = unsafe { panning_handle.ptr.as_ref() };
+
+        let coordinate = |c: &u32| i32::try_from(*c).unwrap_or(i32::MAX);
+        let panning = Self {
+            left: coordinate(left),
+            top: coordinate(top),
+            width: *width,
+            height: *height,
+            track_left: coordinate(track_left),
+            track_top: coordinate(track_top),
+            track_width: *track_width,
+            track_height: *track_height,
+            border_left: *border_left,
+            border_top: *border_top,
+            border_right: *border_right,
+            border_bottom: *border_bottom,
+        };
+
+        Ok(Some(panning).filter(Panning::is_enabled))
+    }
+
+    /// Sets the panning of a crtc. A disabled panning (see `is_enabled`)
+    /// turns panning off.
+    ///
+    /// # Errors
+    /// * `XrandrError::GetResources` - Getting the screen resources failed
+    /// * `XrandrError::SetPanning(xid)` - The x backend rejected the panning
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn set(&self, handle: &mut XHandle, crtc: XId) -> Result<(), XrandrError> {
+        let coordinate = |c: i32| u32::try_from(c).map_err(|_| XrandrError::SetPanning(crtc));
+
+        let mut panning = xrandr::XRRPanning {
+            timestamp: 0,
+            left: coordinate(self.left)?,
+            top: coordinate(self.top)?,
+            width: self.width,
+            height: self.height,
+            track_left: coordinate(self.track_left)?,
+            track_top: coordinate(self.track_top)?,
+            track_width: self.track_width,
+            track_height: self.track_height,
+            border_left: self.border_left,
+            border_top: self.border_top,
+            border_right: self.border_right,
+            border_bottom: self.border_bottom,
+        };
+
+        let res = ScreenResourcesHandle::new(handle)?;
+        let status =
+            unsafe { xrandr::XRRSetPanning(handle.sys.as_ptr(), res.ptr(), crtc, &mut panning) };
+
+        match status {
+            0 => Ok(()),
+            _ => Err(XrandrError::SetPanning(crtc)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_panning_and_tracking() {
+        let mut panning = Panning::new(100, 0, 2560, 1600);
+        let moved = panning.offset((-100, 50)).unwrap();
+        assert_eq!((moved.left, moved.top), (0, 50));
+        assert_eq!((moved.track_left, moved.track_top), (0, 0));
+        assert_eq!(moved.max_coordinates(), (2560, 1650));
+
+        panning.track_left = 100;
+        panning.track_width = 1280;
+        panning.track_height = 800;
+        let moved = panning.offset((-100, 50)).unwrap();
+        assert_eq!((moved.track_left, moved.track_top), (0, 50));
+
+        assert!(panning.offset((i32::MAX, 0)).is_none());
+        assert!(!Panning::default().is_enabled());
+    }
+}
@@ -0,0 +1,923 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use indexmap::IndexMap;
+
+use crate::crtc::{supported_reflections, supported_rotations, Crtc, Reflection, Rotation};
+use crate::mode::{refresh_rate, Mode};
+use crate::output::property::{Property, Range, Ranges, Supported, Value, Values};
+use crate::output::Output;
+use crate::panning::Panning;
+use crate::query::{MODE_FLAGS, SUBPIXEL_ORDERS};
+use crate::snapshot::Snapshot;
+use crate::transform::Transform;
+use crate::{ScreenSize, ScreenSizeRange, XId, XTime, XrandrError};
+
+// An output as it is read, before its crtcs (which the dump only lists by
+// index) get an xid
+struct ParsedOutput {
+    output: Output,
+    position: Option<(i32, i32)>,
+    rotation: Option<Rotation>,
+    reflection: Reflection,
+    rotations: u16,
+    crtc: Option<usize>,
+    crtcs: Vec<usize>,
+    clones: Vec<String>,
+    panning: Panning,
+    transform: Vec<[f64; 3]>,
+    filter: String,
+    properties: Vec<ParsedProperty>,
+}
+
+// A property as it is read, it can continue on the next lines
+#[derive(Default)]
+struct ParsedProperty {
+    name: String,
+    items: Vec<String>,
+    hex: String,
+    ranges: Option<String>,
+    supported: Option<String>,
+}
+
+impl FromStr for Snapshot {
+    type Err = XrandrError;
+
+    /// Parses what `xrandr --verbose` printed, e.g. in a bug report, so it
+    /// can be inspected, validated or loaded into a `FakeBackend`.
+    ///
+    /// The dump does not have everything: crtcs only have an index, so they
+    /// get made up xids, and the physical size of the screen, gamma ramps
+    /// and the types of property values are not known. Numbers are read as
+    /// `Integer32` (or `Cardinal32` if too large), everything else but the
+    /// EDID and GUIDs as an atom. Modes that no output has are listed after
+    /// the last output; they are only kept in `modes`, unless that output
+    /// is connected or enabled, in which case they cannot be told apart
+    /// from its own.
+    ///
+    /// # Errors
+    /// * `XrandrError::ParseDump(line, message)` - A line could not be read
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let snapshot: Snapshot = std::fs::read_to_string("xrandr.txt")?.parse()?;
+    /// let diagnostics = validate(&snapshot.crtcs, &snapshot.range);
+    /// ```
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::default();
+        for (i, line) in s.lines().enumerate() {
+            parser
+                .line(line)
+                .map_err(|message| XrandrError::ParseDump(i + 1, message))?;
+        }
+        parser
+            .finish()
+            .map_err(|message| XrandrError::ParseDump(s.lines().count(), message))
+    }
+}
+
+#[derive(Default)]
+struct Parser {
+    screen: Option<(i32, ScreenSize, ScreenSizeRange)>,
+    outputs: Vec<ParsedOutput>,
+    modes: Vec<Mode>,
+    // The mode whose timings are read next
+    mode: Option<usize>,
+}
+
+impl Parser {
+    fn line(&mut self, line: &str) -> Result<(), String> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        if let Some(rest) = line.strip_prefix("Screen ") {
+            self.screen = Some(screen(rest).ok_or("invalid screen line")?);
+            return Ok(());
+        }
+        if let Some(rest) = line.strip_prefix("        h: ") {
+            return self.timings(rest, true);
+        }
+        if let Some(rest) = line.strip_prefix("        v: ") {
+            return self.timings(rest, false);
+        }
+        if let Some(rest) = line.strip_prefix("  ") {
+            return self.mode_line(rest);
+        }
+
+        if !line.starts_with('\t') {
+            self.outputs.push(header(line)?);
+            return Ok(());
+        }
+        let output = self.outputs.last_mut().ok_or("details before any output")?;
+
+        // Pasted dumps often lose the trailing spaces, so the lines are
+        // told apart by how they start
+        let line = line.trim_end();
+        if let Some(rest) = line.strip_prefix("\t\t") {
+            let property = output
+                .properties
+                .last_mut()
+                .ok_or("property values before any property")?;
+            continuation(property, rest);
+        } else if line.starts_with("\t ") {
+            // The rest of the transform, indented below its first row
+            let row = line.trim();
+            match row.strip_prefix("filter:") {
+                Some(filter) => output.filter = filter.trim().to_string(),
+                None => output.transform.push(matrix_row(row)?),
+            }
+        } else {
+            detail(output, &line[1..])?;
+        }
+        Ok(())
+    }
+
+    // A mode of the current output: `1920x1080 (0x48) 141.000MHz -HSync`
+    fn mode_line(&mut self, line: &str) -> Result<(), String> {
+        let invalid = || {
+            format!(
+                "invalid mode '{}', only --verbose output is supported",
+                line
+            )
+        };
+        let (name, rest) = line.rsplit_once(" (0x").ok_or_else(invalid)?;
+        let (xid, rest) = rest.split_once(')').ok_or_else(invalid)?;
+        let xid = XId::from_str_radix(xid, 16).map_err(|_| invalid())?;
+
+        let mut words = rest.split_whitespace();
+        let clock = words.next().and_then(|c| c.strip_suffix("MHz"));
+        let clock: f64 = clock.and_then(|c| c.parse().ok()).ok_or_else(invalid)?;
+        let (mut flags, mut current, mut preferred) = (0, false, false);
+        for word in words {
+            match word {
+                "*current" => current = true,
+                "+preferred" => preferred = true,
+                _ => {
+                    let (flag, _) = MODE_FLAGS
+                        .iter()
+                        .find(|(_, name)| *name == word)
+                        .ok_or_else(invalid)?;
+                    flags |= *flag as u64;
+                }
+            }
+        }
+
+        let index = match self.modes.iter().position(|m| m.xid == xid) {
+            Some(index) => index,
+            None => {
+                self.modes.push(Mode {
+                    xid,
+                    width: 0,
+                    height: 0,
+                    dot_clock: (clock * 1_000_000.0).round() as u64,
+                    hsync_tart: 0,
+                    hsync_end: 0,
+                    htotal: 0,
+                    hskew: 0,
+                    vsync_start: 0,
+                    vsync_end: 0,
+                    vtotal: 0,
+                    name: name.to_string(),
+                    flags,
+                    rate: 0.0,
+                });
+                self.modes.len() - 1
+            }
+        };
+        self.mode = Some(index);
+
+        // Modes that no output has are listed after the last output. That is
+        // usually a disconnected one, which has no modes of its own unless
+        // it is still enabled.
+        let owner = self.outputs.last_mut();
+        if let Some(parsed) = owner.filter(|o| o.output.connected || o.position.is_some()) {
+            let output = &mut parsed.output;
+            output.modes.push(xid);
+            if preferred {
+                output.preferred_modes.push(xid);
+            }
+            if current {
+                output.current_mode = Some(xid);
+            }
+        }
+        Ok(())
+    }
+
+    // `width  1920 start 1936 end 1952 total 2104 skew    0 clock  67.02KHz`
+    // or `height 1080 start 1083 end 1097 total 1116           clock  60.05Hz`
+    fn timings(&mut self, line: &str, horizontal: bool) -> Result<(), String> {
+        let invalid = || format!("invalid mode timings '{}'", line);
+        let mode = self
+            .mode
+            .and_then(|i| self.modes.get_mut(i))
+            .ok_or("mode timings before any mode")?;
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let value = |key: &str| -> Result<u32, String> {
+            let i = words.iter().position(|w| *w == key).ok_or_else(invalid)?;
+            let value = words.get(i + 1).ok_or_else(invalid)?;
+            value.parse().map_err(|_| invalid())
+        };
+
+        if horizontal {
+            mode.width = value("width")?;
+            mode.hsync_tart = value("start")?;
+            mode.hsync_end = value("end")?;
+            mode.htotal = value("total")?;
+            mode.hskew = value("skew")?;
+        } else {
+            mode.height = value("height")?;
+            mode.vsync_start = value("start")?;
+            mode.vsync_end = value("end")?;
+            mode.vtotal = value("total")?;
+
+            mode.rate = if mode.htotal != 0 && mode.vtotal != 0 {
+                refresh_rate(mode.dot_clock, mode.htotal, mode.vtotal, mode.flags)
+            } else {
+                let clock = words.last().and_then(|c| c.strip_suffix("Hz"));
+                clock.and_then(|c| c.parse().ok()).ok_or_else(invalid)?
+            };
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Snapshot, String> {
+        let (screen, size, range) = self.screen.ok_or("no screen line")?;
+
+        // Crtcs get xids after everything else in the dump
+        let crtc_count = self
+            .outputs
+            .iter()
+            .flat_map(|o| o.crtcs.iter().chain(&o.crtc))
+            .map(|i| i + 1)
+            .max()
+            .unwrap_or(0);
+        let first_xid = self
+            .outputs
+            .iter()
+            .map(|o| o.output.xid)
+            .chain(self.modes.iter().map(|m| m.xid))
+            .max()
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or("no xids left for the crtcs")?;
+        // Every index is below the count, so this covers all of them
+        first_xid
+            .checked_add(crtc_count as XId)
+            .ok_or("no xids left for the crtcs")?;
+        let crtc_xid = |index: usize| first_xid + index as XId;
+
+        let mut crtcs: Vec<Crtc> = (0..crtc_count)
+            .map(|index| Crtc {
+                xid: crtc_xid(index),
+                timestamp: 0,
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+                mode: 0,
+                rotation: Rotation::Normal,
+                reflection: Reflection::Normal,
+                outputs: Vec::new(),
+                rotations: Vec::new(),
+                reflections: Vec::new(),
+                possible: Vec::new(),
+                transform: Transform::identity(),
+                panning: None,
+            })
+            .collect();
+
+        let names: HashMap<String, XId> = self
+            .outputs
+            .iter()
+            .map(|o| (o.output.name.clone(), o.output.xid))
+            .collect();
+        let mut outputs = Vec::new();
+
+        for parsed in self.outputs {
+            let mut output = parsed.output;
+            output.crtcs = parsed.crtcs.iter().map(|i| crtc_xid(*i)).collect();
+            output.crtc = parsed.crtc.map(crtc_xid);
+            output.clones = parsed
+                .clones
+                .iter()
+                .map(|name| names.get(name).copied())
+                .collect::<Option<_>>()
+                .ok_or_else(|| format!("{} has an unknown clone", output.name))?;
+
+            for index in &parsed.crtcs {
+                let crtc = &mut crtcs[*index];
+                crtc.possible.push(output.xid);
+                crtc.rotations = supported_rotations(parsed.rotations);
+                crtc.reflections = supported_reflections(parsed.rotations);
+            }
+
+            if let (Some(index), Some(mode)) = (parsed.crtc, output.current_mode) {
+                let crtc = &mut crtcs[index];
+                crtc.outputs.push(output.xid);
+
+                // Clones on the same crtc are listed with the same settings
+                if crtc.mode == 0 {
+                    let mode = self
+                        .modes
+                        .iter()
+                        .find(|m| m.xid == mode)
+                        .ok_or_else(|| format!("{} has an unknown mode", output.name))?;
+                    let rotation = parsed.rotation.unwrap_or(Rotation::Normal);
+                    (crtc.x, crtc.y) = parsed.position.unwrap_or_default();
+                    crtc.mode = mode.xid;
+                    crtc.rotation = rotation;
+                    crtc.reflection = parsed.reflection;
+                    (crtc.width, crtc.height) = match rotation {
+                        Rotation::Normal | Rotation::Inverted => (mode.width, mode.height),
+                        Rotation::Left | Rotation::Right => (mode.height, mode.width),
+                    };
+                    crtc.panning = parsed.panning.is_enabled().then_some(parsed.panning);
+                    if let Ok(matrix) = <[[f64; 3]; 3]>::try_from(parsed.transform.as_slice()) {
+                        crtc.transform = Transform::from_matrix(matrix);
+                        crtc.transform.filter = parsed.filter.clone();
+                    }
+                }
+            }
+
+            for property in parsed.properties {
+                let property = property.finish()?;
+                output.properties.insert(property.name.clone(), property);
+            }
+            outputs.push(output);
+        }
+
+        Ok(Snapshot {
+            screen,
+            size,
+            range,
+            modes: self.modes,
+            crtcs,
+            outputs,
+            gamma: HashMap::new(),
+        })
+    }
+}
+
+// `0: minimum 320 x 200, current 1920 x 1080, maximum 16384 x 16384`
+fn screen(line: &str) -> Option<(i32, ScreenSize, ScreenSizeRange)> {
+    let (screen, rest) = line.split_once(": ")?;
+    let mut sizes = rest.split(", ").map(|part| {
+        let (_, size) = part.split_once(' ')?;
+        let (width, height) = size.split_once(" x ")?;
+        Some((width.parse().ok()?, height.parse().ok()?))
+    });
+    let (min_width, min_height) = sizes.next()??;
+    let (width, height) = sizes.next()??;
+    let (max_width, max_height) = sizes.next()??;
+
+    Some((
+        screen.parse().ok()?,
+        ScreenSize {
+            width,
+            width_mm: 0,
+            height,
+            height_mm: 0,
+        },
+        ScreenSizeRange {
+            min_width,
+            min_height,
+            max_width,
+            max_height,
+        },
+    ))
+}
+
+fn rotation(name: &str) -> Option<Rotation> {
+    match name {
+        "normal" => Some(Rotation::Normal),
+        "left" => Some(Rotation::Left),
+        "inverted" => Some(Rotation::Inverted),
+        "right" => Some(Rotation::Right),
+        _ => None,
+    }
+}
+
+// `eDP-1 connected primary 1920x1080+0+0 (0x48) normal X axis (normal left
+// inverted right x axis y axis) 344mm x 194mm panning ...`
+fn header(line: &str) -> Result<ParsedOutput, String> {
+    let invalid = || format!("invalid output '{}'", line);
+    let mut words = line.split_whitespace().peekable();
+    let mut parsed = ParsedOutput {
+        output: Output {
+            xid: 0,
+            properties: IndexMap::new(),
+            timestamp: 0,
+            is_primary: false,
+            crtc: None,
+            name: words.next().ok_or_else(invalid)?.to_string(),
+            mm_width: 0,
+            mm_height: 0,
+            connected: false,
+            subpixel_order: 0,
+            crtcs: Vec::new(),
+            clones: Vec::new(),
+            modes: Vec::new(),
+            preferred_modes: Vec::new(),
+            current_mode: None,
+        },
+        position: None,
+        rotation: None,
+        reflection: Reflection::Normal,
+        rotations: Rotation::Normal as u16,
+        crtc: None,
+        crtcs: Vec::new(),
+        clones: Vec::new(),
+        panning: Panning::default(),
+        transform: Vec::new(),
+        filter: String::new(),
+        properties: Vec::new(),
+    };
+
+    parsed.output.connected = match words.next() {
+        Some("connected") => true,
+        Some("disconnected") => false,
+        Some("unknown") if words.next() == Some("connection") => false,
+        _ => return Err(invalid()),
+    };
+    if words.next_if_eq(&"primary").is_some() {
+        parsed.output.is_primary = true;
+    }
+
+    // Only enabled outputs have a geometry
+    if let Some(geometry) = words.next_if(|w| w.contains('x') && w.contains('+')) {
+        let (_, position) = geometry.split_once('+').ok_or_else(invalid)?;
+        let (x, y) = position.split_once('+').ok_or_else(invalid)?;
+        parsed.position = Some((
+            x.parse().map_err(|_| invalid())?,
+            y.parse().map_err(|_| invalid())?,
+        ));
+        words.next_if(|w| w.starts_with("(0x"));
+        parsed.rotation = words.next_if(|w| rotation(w).is_some()).and_then(rotation);
+        parsed.reflection = match words.peek() {
+            Some(&"X") => Reflection::X,
+            Some(&"Y") => Reflection::Y,
+            _ => Reflection::Normal,
+        };
+        if parsed.reflection != Reflection::Normal {
+            let reflection: Vec<&str> = words.by_ref().take_while(|w| *w != "axis").collect();
+            if reflection == ["X", "and", "Y"] {
+                parsed.reflection = Reflection::XY;
+            }
+        }
+    }
+
+    if words.peek().is_some_and(|w| w.starts_with('(')) {
+        let mut bits = 0;
+        for word in words.by_ref() {
+            let name = word.trim_matches(|c| c == '(' || c == ')');
+            bits |= match name {
+                "x" => Reflection::X as u16,
+                "y" => Reflection::Y as u16,
+                _ => rotation(name).map_or(0, |r| r as u16),
+            };
+            if word.ends_with(')') {
+                break;
+            }
+        }
+        parsed.rotations = bits;
+    }
+
+    if let Some(width) = words.next_if(|w| w.ends_with("mm")) {
+        let height = words.nth(1).ok_or_else(invalid)?;
+        let mm = |w: &str| w.trim_end_matches("mm").parse().map_err(|_| invalid());
+        parsed.output.mm_width = mm(width)?;
+        parsed.output.mm_height = mm(height)?;
+    }
+    Ok(parsed)
+}
+
+// `WxH+X+Y`, as unsigned size and signed position
+fn rectangle(value: &str) -> Option<(u32, u32, i32, i32)> {
+    let (width, rest) = value.split_once('x')?;
+    let mut parts = rest.split('+');
+    Some((
+        width.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    ))
+}
+
+// The lines after the output, `Identifier: 0x42` etc., or its properties
+fn detail(output: &mut ParsedOutput, line: &str) -> Result<(), String> {
+    let invalid = || format!("invalid line '{}'", line);
+    let (key, value) = line.split_once(':').ok_or_else(invalid)?;
+    let value = value.trim();
+    let numbers = |value: &str| -> Result<Vec<i64>, String> {
+        value
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .filter(|n| !n.is_empty())
+            .map(|n| n.parse().map_err(|_| invalid()))
+            .collect()
+    };
+
+    match key {
+        "Identifier" => {
+            let xid = value.strip_prefix("0x").ok_or_else(invalid)?;
+            output.output.xid = XId::from_str_radix(xid, 16).map_err(|_| invalid())?;
+        }
+        // Printed as a C int
+        "Timestamp" => {
+            let timestamp = value.parse::<i32>().map_err(|_| invalid())?;
+            output.output.timestamp = XTime::from(timestamp as u32);
+        }
+        "Subpixel" => {
+            let order = SUBPIXEL_ORDERS.iter().position(|o| *o == value);
+            output.output.subpixel_order = order.ok_or_else(invalid)? as u16;
+        }
+        // The gamma ramp cannot be recovered from its approximation
+        "Gamma" | "Brightness" => {}
+        "Clones" => output.clones = value.split_whitespace().map(String::from).collect(),
+        // The protocol counts crtcs with a CARD16, which also keeps a
+        // bogus index from making up billions of them
+        "CRTC" => output.crtc = Some(crtc_index(value).ok_or_else(invalid)?),
+        "CRTCs" => {
+            let indices = value.split_whitespace().map(crtc_index);
+            output.crtcs = indices.collect::<Option<_>>().ok_or_else(invalid)?;
+        }
+        "Panning" => {
+            let (width, height, left, top) = rectangle(value).ok_or_else(invalid)?;
+            (output.panning.width, output.panning.height) = (width, height);
+            (output.panning.left, output.panning.top) = (left, top);
+        }
+        "Tracking" => {
+            let (width, height, left, top) = rectangle(value).ok_or_else(invalid)?;
+            (output.panning.track_width, output.panning.track_height) = (width, height);
+            (output.panning.track_left, output.panning.track_top) = (left, top);
+        }
+        // The `Border` property has spaces between its values instead
+        "Border" if value.contains('/') => {
+            let [left, top, right, bottom] = numbers(value)?[..] else {
+                return Err(invalid());
+            };
+            let pan = &mut output.panning;
+            (pan.border_left, pan.border_top) = (left as i32, top as i32);
+            (pan.border_right, pan.border_bottom) = (right as i32, bottom as i32);
+        }
+        "Transform" => output.transform.push(matrix_row(value)?),
+        // Anything else is a property, `name: value value`
+        _ => output.properties.push(ParsedProperty {
+            name: key.to_string(),
+            items: value.split_whitespace().map(String::from).collect(),
+            ..ParsedProperty::default()
+        }),
+    }
+    Ok(())
+}
+
+fn matrix_row(row: &str) -> Result<[f64; 3], String> {
+    let invalid = || format!("invalid transform '{}'", row);
+    let values: Vec<f64> = row
+        .split_whitespace()
+        .map(|v| v.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    values.try_into().map_err(|_| invalid())
+}
+
+// A line of a property after the first, with more of the value, or the
+// values it supports
+fn continuation(property: &mut ParsedProperty, line: &str) {
+    if let Some(ranges) = line
+        .strip_prefix("range: ")
+        .or_else(|| line.strip_prefix("ranges: "))
+    {
+        property.ranges = Some(ranges.to_string());
+    } else if let Some(supported) = line.strip_prefix("supported: ") {
+        property.supported = Some(supported.to_string());
+    } else if property.name == "EDID" {
+        // Only the EDID is dumped in hex, starting on the next line
+        property.hex.push_str(line.trim());
+    } else {
+        property
+            .items
+            .extend(line.split_whitespace().map(String::from));
+    }
+}
+
+impl ParsedProperty {
+    fn finish(self) -> Result<Property, String> {
+        let invalid = |what: &str| format!("invalid {} of property '{}'", what, self.name);
+
+        let value = if self.name == "EDID" {
+            let bytes = (0..self.hex.len())
+                .step_by(2)
+                .map(|i| {
+                    self.hex
+                        .get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| invalid("value"))?;
+            Value::Edid(bytes)
+        } else if let Some(guid) = self
+            .items
+            .first()
+            .filter(|_| self.items.len() == 1)
+            .and_then(|guid| guid_of(guid))
+        {
+            Value::Guid(guid)
+        } else if self.items == ["?"] {
+            Value::Unrecognized {
+                value_type: 0,
+                format: 0,
+            }
+        } else if let Ok(values) = self.items.iter().map(|i| i.parse()).collect() {
+            Value::Integer32(values)
+        } else if let Ok(values) = self.items.iter().map(|i| i.parse()).collect() {
+            Value::Cardinal32(values)
+        } else {
+            Value::Atom(self.items.join(" "))
+        };
+
+        let values = if let Some(ranges) = &self.ranges {
+            let pairs: Vec<(&str, &str)> = ranges
+                .trim_start_matches('(')
+                .trim_end_matches(')')
+                .split("), (")
+                .map(|pair| pair.split_once(", "))
+                .collect::<Option<_>>()
+                .ok_or_else(|| invalid("range"))?;
+            Some(Values::Range(match &value {
+                Value::Integer32(_) => Ranges::Integer32(ranges_of(&pairs)?),
+                Value::Cardinal32(_) => Ranges::Cardinal32(ranges_of(&pairs)?),
+                _ => Ranges::Atom(
+                    pairs
+                        .iter()
+                        .map(|(lower, upper)| Range {
+                            lower: lower.to_string(),
+                            upper: upper.to_string(),
+                        })
+                        .collect(),
+                ),
+            }))
+        } else if let Some(supported) = &self.supported {
+            let items: Vec<&str> = supported.split(", ").collect();
+            let supported_of = || invalid("supported values");
+            Some(Values::Supported(match &value {
+                Value::Integer32(_) => {
+                    Supported::Integer32(parse_all(&items).ok_or_else(supported_of)?)
+                }
+                Value::Cardinal32(_) => {
+                    Supported::Cardinal32(parse_all(&items).ok_or_else(supported_of)?)
+                }
+                _ => Supported::Atom(items.iter().map(|i| i.to_string()).collect()),
+            }))
+        } else {
+            None
+        };
+
+        Ok(Property {
+            name: self.name,
+            value,
+            values,
+            is_immutable: false,
+            is_pending: false,
+        })
+    }
+}
+
+// `{12345678-9ABC-DEF0-0123-456789ABCDEF}`
+fn guid_of(value: &str) -> Option<[u8; 16]> {
+    let hex: String = value
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .split('-')
+        .collect();
+    if hex.len() != 32 {
+        return None;
+    }
+    let bytes = (0..32)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes.try_into().ok()
+}
+
+// A crtc index, `0`
+fn crtc_index(value: &str) -> Option<usize> {
+    value.parse::<u16>().ok().map(usize::from)
+}
+
+fn parse_all<T: FromStr>(items: &[&str]) -> Option<Vec<T>> {
+    items.iter().map(|i| i.parse().ok()).collect()
+}
+
+fn ranges_of<T: FromStr>(pairs: &[(&str, &str)]) -> Result<Vec<Range<T>>, String> {
+    pairs
+        .iter()
+        .map(|(lower, upper)| {
+            Ok(Range {
+                lower: lower
+                    .parse()
+                    .map_err(|_| format!("invalid range {}", lower))?,
+                upper: upper
+                    .parse()
+                    .map_err(|_| format!("invalid range {}", upper))?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{validate, FakeBackend, RandrBackend};
+
+    // From xrandr 1.5.2 on a laptop with an external monitor, as pasted
+    // into a bug report (without the trailing spaces)
+    const DUMP: &str = "\
+Screen 0: minimum 320 x 200, current 4480 x 1440, maximum 16384 x 16384
+eDP-1 connected primary 1920x1080+0+0 (0x48) normal (normal left inverted right x axis y axis) 309mm x 174mm
+\tIdentifier: 0x5f
+\tTimestamp:  17052
+\tSubpixel:   unknown
+\tGamma:      1.0:1.0:1.0
+\tBrightness: 1.0
+\tClones:
+\tCRTC:       0
+\tCRTCs:      0 1 2
+\tTransform:  1.000000 0.000000 0.000000
+\t            0.000000 1.000000 0.000000
+\t            0.000000 0.000000 1.000000
+\t           filter:
+\tEDID:
+\t\t00ffffffffffff0030e4d80200000000
+\t\t00160104951f1178ea4b5097562e2f5e
+\t\t00000001010101010101010101010101
+\t\t010101011c3780a070381e4030203500
+\t\t35ae1000001900000000000000000000
+\t\t00000000000000000000fe004c472044
+\t\t6973706c61790a2020000000fe004c50
+\t\t3134305746362d53504237000000005c
+\tscaling mode: Full aspect
+\t\tsupported: Full, Center, Full aspect
+\tmax bpc: 12
+\t\trange: (6, 12)
+\tBroadcast RGB: Automatic
+\t\tsupported: Automatic, Full, Limited 16:235
+\tlink-status: Good
+\t\tsupported: Good, Bad
+\tCONNECTOR_ID: 95
+\t\tsupported: 95
+\tnon-desktop: 0
+\t\trange: (0, 1)
+  1920x1080 (0x48) 141.000MHz -HSync -VSync *current +preferred
+        h: width  1920 start 1936 end 1952 total 2104 skew    0 clock  67.02KHz
+        v: height 1080 start 1083 end 1097 total 1116           clock  60.05Hz
+  1280x720 (0x49) 74.250MHz +HSync +VSync
+        h: width  1280 start 1390 end 1430 total 1650 skew    0 clock  45.00KHz
+        v: height  720 start  725 end  730 total  750           clock  60.00Hz
+DP-2 connected 2560x1440+1920+0 (0x4b) normal (normal left inverted right x axis y axis) 597mm x 336mm
+\tIdentifier: 0x60
+\tTimestamp:  17052
+\tSubpixel:   unknown
+\tGamma:      1.0:1.0:1.0
+\tBrightness: 1.0
+\tClones:
+\tCRTC:       1
+\tCRTCs:      0 1 2
+\tTransform:  1.000000 0.000000 0.000000
+\t            0.000000 1.000000 0.000000
+\t            0.000000 0.000000 1.000000
+\t           filter:
+\tGUID: {1A2B3C4D-0000-1000-8000-0080C7A5E1F0}
+\tlink-status: Good
+\t\tsupported: Good, Bad
+\tCONNECTOR_ID: 96
+\t\tsupported: 96
+\tnon-desktop: 0
+\t\trange: (0, 1)
+  2560x1440 (0x4b) 241.500MHz +HSync -VSync *current +preferred
+        h: width  2560 start 2608 end 2640 total 2720 skew    0 clock  88.79KHz
+        v: height 1440 start 1443 end 1448 total 1481           clock  59.95Hz
+  1920x1080 (0x48) 141.000MHz -HSync -VSync
+        h: width  1920 start 1936 end 1952 total 2104 skew    0 clock  67.02KHz
+        v: height 1080 start 1083 end 1097 total 1116           clock  60.05Hz
+HDMI-1 disconnected (normal left inverted right x axis y axis)
+\tIdentifier: 0x61
+\tTimestamp:  17052
+\tSubpixel:   unknown
+\tClones:
+\tCRTCs:      0 1 2
+\tTransform:  1.000000 0.000000 0.000000
+\t            0.000000 1.000000 0.000000
+\t            0.000000 0.000000 1.000000
+\t           filter:
+\tlink-status: Good
+\t\tsupported: Good, Bad
+\tCONNECTOR_ID: 97
+\t\tsupported: 97
+\tnon-desktop: 0
+\t\trange: (0, 1)
+  1920x1080_60.00 (0x4e) 173.000MHz -HSync +VSync
+        h: width  1920 start 2048 end 2248 total 2576 skew    0 clock  67.16KHz
+        v: height 1080 start 1083 end 1088 total 1120           clock  59.96Hz
+";
+
+    #[test]
+    fn parses_verbose_dumps() {
+        let snapshot: Snapshot = DUMP.parse().unwrap();
+
+        assert_eq!(snapshot.size.width, 4480);
+        assert_eq!(snapshot.range.max_height, 16384);
+        assert_eq!(snapshot.modes.len(), 4);
+        assert_eq!(snapshot.crtcs.len(), 3);
+
+        let edp = snapshot.output("eDP-1").unwrap();
+        assert!(edp.is_primary && edp.connected);
+        assert_eq!((edp.xid, edp.mm_width), (0x5f, 309));
+        assert_eq!(edp.current_mode, Some(0x48));
+        assert_eq!(edp.edid().unwrap().len(), 128);
+        assert!(matches!(
+            &edp.properties["scaling mode"].value,
+            Value::Atom(name) if name == "Full aspect"
+        ));
+        assert!(matches!(
+            &edp.properties["Broadcast RGB"].values,
+            Some(Values::Supported(Supported::Atom(names))) if names[2] == "Limited 16:235"
+        ));
+
+        let dp = snapshot.output("DP-2").unwrap();
+        let crtc = snapshot.crtc(dp.crtc.unwrap()).unwrap();
+        assert_eq!((crtc.x, crtc.width, crtc.mode), (1920, 2560, 0x4b));
+        assert_eq!(crtc.possible, vec![0x5f, 0x60, 0x61]);
+        assert_eq!(dp.modes, vec![0x4b, 0x48]);
+        assert!(matches!(
+            dp.properties["GUID"].value,
+            Value::Guid([0x1a, 0x2b, ..])
+        ));
+        assert!((snapshot.mode(0x4b).unwrap().rate - 59.95).abs() < 0.01);
+
+        // The mode after the disconnected output is not one of its own
+        assert!(snapshot.output("HDMI-1").unwrap().modes.is_empty());
+        assert!(snapshot.mode(0x4e).is_some());
+
+        // The dump is printed the same again, but for the gamma
+        let verbose = snapshot.verbose();
+        let gamma =
+            |line: &&str| !line.starts_with("\tGamma:") && !line.starts_with("\tBrightness:");
+        assert_eq!(
+            verbose.lines().map(str::trim_end).collect::<Vec<_>>(),
+            DUMP.lines().filter(gamma).collect::<Vec<_>>()
+        );
+        assert!(validate(&snapshot.crtcs, &snapshot.range).is_empty());
+    }
+
+    #[test]
+    fn loads_into_fake_backends() {
+        let snapshot: Snapshot = DUMP.parse().unwrap();
+        let hdmi = snapshot.output("HDMI-1").unwrap().xid;
+        let mut fake = FakeBackend::from(snapshot);
+
+        let mut crtcs = fake.enabled_crtcs().unwrap();
+        assert_eq!(crtcs.len(), 2);
+        crtcs[1].x = 0;
+        crtcs[1].y = 1080;
+        fake.apply_crtcs(&mut crtcs[1..]).unwrap();
+        assert_eq!(fake.screen_size().unwrap().height, 2520);
+
+        // Nor can the disconnected output use it
+        let unused = fake.screen_resources().unwrap().crtcs[2];
+        let mut crtc = fake.crtc_info(unused).unwrap();
+        crtc.mode = 0x4e;
+        (crtc.width, crtc.height) = (1920, 1080);
+        crtc.outputs = vec![hdmi];
+        assert!(fake.apply_crtcs(&mut [crtc]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_dumps() {
+        let query = "Screen 0: minimum 320 x 200, current 1920 x 1080, maximum 16384 x 16384\n\
+                     eDP-1 connected 1920x1080+0+0 344mm x 194mm\n   \
+                     1920x1080     60.02*+\n";
+        assert!(matches!(
+            query.parse::<Snapshot>(),
+            Err(XrandrError::ParseDump(3, message)) if message.contains("--verbose")
+        ));
+        assert!(matches!(
+            "eDP-1 connected\n".parse::<Snapshot>(),
+            Err(XrandrError::ParseDump(1, _))
+        ));
+
+        let screen = "Screen 0: minimum 320 x 200, current 1920 x 1080, maximum 16384 x 16384\n\
+                      eDP-1 connected 1920x1080+0+0 (0x47) normal (normal) 344mm x 194mm\n";
+        for (detail, line) in [
+            ("\tCRTCs: -1\n", 3),
+            ("\tCRTCs: 0 4611686018427387903\n", 3),
+            ("\tIdentifier: 0xffffffffffffffff\n\tCRTCs: 0\n", 4),
+        ] {
+            let dump = format!("{}{}", screen, detail);
+            assert!(matches!(
+                dump.parse::<Snapshot>(),
+                Err(XrandrError::ParseDump(n, _)) if n == line
+            ));
+        }
+    }
+}
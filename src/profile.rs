@@ -0,0 +1,300 @@
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+
+use crate::crtc::Rotation;
+use crate::output::Output;
+
+// A profile is a saved layout description, similar to what autorandr keeps
+// on disk. It records which displays it was made for (by connector name and
+// EDID fingerprint) and how each of them should be configured. Matching a
+// profile against the currently connected outputs tells us which saved
+// layout applies to the current situation (e.g. docked/undocked).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Profile {
+    pub name: String,
+    /// Use this profile if no other profile matches the connected outputs
+    pub fallback: bool,
+    pub outputs: Vec<ProfileOutput>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct ProfileOutput {
+    /// Name of the connector, e.g. `DP-1`
+    pub connector: String,
+    /// See [`Output::fingerprint`]. `None` accepts any display on `connector`.
+    pub fingerprint: Option<String>,
+    /// How to configure this output. `None` means it should be disabled.
+    pub layout: Option<OutputLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct OutputLayout {
+    pub width: u32,
+    pub height: u32,
+    pub rate: Option<f64>,
+    pub x: i32,
+    pub y: i32,
+    pub rotation: Rotation,
+    pub primary: bool,
+}
+
+// How well a profile matches the connected outputs. Variants are ordered
+// from worst to best, so the best candidate is the maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// The profile does not fit, but is marked as fallback
+    Fallback,
+    /// All outputs of the profile are connected, but so are others
+    Superset,
+    /// The connected outputs are exactly those of the profile
+    Exact,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// An output of the profile is not connected
+    MissingOutput(String),
+    /// The display on a connector is not the one the profile was made for
+    FingerprintMismatch {
+        connector: String,
+        expected: String,
+        found: Option<String>,
+    },
+    /// The profile matches, but another profile matches better
+    Outranked { by: String, kind: MatchKind },
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate<'a> {
+    pub profile: &'a Profile,
+    pub kind: MatchKind,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rejected<'a> {
+    pub profile: &'a Profile,
+    pub reasons: Vec<Rejection>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProfileMatch<'a> {
+    /// The profile that fits the connected outputs best, if any
+    pub best: Option<Candidate<'a>>,
+    /// Every other profile, with the reasons it was not chosen
+    pub rejected: Vec<Rejected<'a>>,
+}
+
+impl Profile {
+    /// Checks this profile against a set of outputs. Disconnected outputs
+    /// are ignored.
+    ///
+    /// # Errors
+    /// The reasons why the profile does not match, if it is not a fallback.
+    ///
+    pub fn matches(&self, outputs: &[Output]) -> Result<MatchKind, Vec<Rejection>> {
+        let connected: Vec<&Output> = outputs.iter().filter(|o| o.connected).collect();
+
+        let mut reasons = Vec::new();
+        for expected in &self.outputs {
+            let Some(output) = connected.iter().find(|o| o.name == expected.connector) else {
+                reasons.push(Rejection::MissingOutput(expected.connector.clone()));
+                continue;
+            };
+
+            if let Some(fingerprint) = &expected.fingerprint {
+                let found = output.fingerprint();
+                if found.as_ref() != Some(fingerprint) {
+                    reasons.push(Rejection::FingerprintMismatch {
+                        connector: expected.connector.clone(),
+                        expected: fingerprint.clone(),
+                        found,
+                    });
+                }
+            }
+        }
+
+        if !reasons.is_empty() {
+            return if self.fallback {
+                Ok(MatchKind::Fallback)
+            } else {
+                Err(reasons)
+            };
+        }
+
+        let is_exact = connected
+            .iter()
+            .all(|o| self.outputs.iter().any(|p| p.connector == o.name));
+
+        Ok(if is_exact {
+            MatchKind::Exact
+        } else {
+            MatchKind::Superset
+        })
+    }
+}
+
+/// Picks the profile that best describes the connected outputs.
+///
+/// An exact match beats a superset match, which beats a fallback. Among
+/// superset matches, the profile that covers the most outputs wins. Any
+/// remaining ties go to the profile listed first.
+///
+/// # Examples
+/// ```ignore
+/// let outputs = xhandle.all_outputs()?;
+/// let result = match_profiles(&profiles, &outputs);
+/// for rejected in result.rejected {
+///     println!("{}: {:?}", rejected.profile.name, rejected.reasons);
+/// }
+/// ```
+///
+#[must_use]
+pub fn match_profiles<'a>(profiles: &'a [Profile], outputs: &[Output]) -> ProfileMatch<'a> {
+    let mut candidates = Vec::new();
+    let mut rejected = Vec::new();
+
+    for profile in profiles {
+        match profile.matches(outputs) {
+            Ok(kind) => candidates.push(Candidate { profile, kind }),
+            Err(reasons) => rejected.push(Rejected { profile, reasons }),
+        }
+    }
+
+    // `max_by_key` returns the last maximum, so iterate in reverse to
+    // prefer profiles listed first
+    let best = candidates
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, c)| (c.kind, c.profile.outputs.len()))
+        .map(|(i, _)| i);
+
+    let best = best.map(|i| candidates.remove(i));
+
+    if let Some(best) = &best {
+        for loser in candidates {
+            rejected.push(Rejected {
+                profile: loser.profile,
+                reasons: vec![Rejection::Outranked {
+                    by: best.profile.name.clone(),
+                    kind: loser.kind,
+                }],
+            });
+        }
+    }
+
+    ProfileMatch { best, rejected }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn output(name: &str, connected: bool, edid: Option<&[u8]>) -> Output {
+        let mut output = fixtures::output(0, name);
+        output.connected = connected;
+
+        if let Some(edid) = edid {
+            output.properties.insert(
+                "EDID".to_string(),
+                crate::Property {
+                    name: "EDID".to_string(),
+                    value: crate::Value::Edid(edid.to_vec()),
+                    values: None,
+                    is_immutable: true,
+                    is_pending: false,
+                },
+            );
+        }
+
+        output
+    }
+
+    fn profile(name: &str, fallback: bool, outputs: &[(&str, Option<&Output>)]) -> Profile {
+        Profile {
+            name: name.to_string(),
+            fallback,
+            outputs: outputs
+                .iter()
+                .map(|(connector, display)| ProfileOutput {
+                    connector: (*connector).to_string(),
+                    fingerprint: display.and_then(Output::fingerprint),
+                    layout: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn prefers_exact_over_superset() {
+        let laptop = output("eDP-1", true, Some(&[1, 2, 3]));
+        let dock = output("DP-1", true, Some(&[4, 5, 6]));
+        let outputs = [laptop, dock, output("HDMI-1", false, None)];
+
+        let profiles = [
+            profile("mobile", false, &[("eDP-1", Some(&outputs[0]))]),
+            profile(
+                "docked",
+                false,
+                &[("eDP-1", Some(&outputs[0])), ("DP-1", Some(&outputs[1]))],
+            ),
+        ];
+
+        let result = match_profiles(&profiles, &outputs);
+        let best = result.best.unwrap();
+        assert_eq!(best.profile.name, "docked");
+        assert_eq!(best.kind, MatchKind::Exact);
+        assert_eq!(
+            result.rejected[0].reasons,
+            vec![Rejection::Outranked {
+                by: "docked".to_string(),
+                kind: MatchKind::Superset
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_other_display() {
+        let outputs = [output("DP-1", true, Some(&[7, 7, 7]))];
+        let other = output("DP-1", true, Some(&[4, 5, 6]));
+        let profiles = [
+            profile("office", false, &[("DP-1", Some(&other))]),
+            profile("any", true, &[]),
+        ];
+
+        let result = match_profiles(&profiles, &outputs);
+        let best = result.best.unwrap();
+        assert_eq!(best.profile.name, "any");
+        assert_eq!(best.kind, MatchKind::Superset);
+
+        let profiles = [
+            profile("office", false, &[("DP-1", Some(&other))]),
+            profile("fallback", true, &[("HDMI-1", None)]),
+        ];
+        let result = match_profiles(&profiles, &outputs);
+        let best = result.best.unwrap();
+        assert_eq!(best.profile.name, "fallback");
+        assert_eq!(best.kind, MatchKind::Fallback);
+        assert!(matches!(
+            result.rejected[0].reasons[0],
+            Rejection::FingerprintMismatch { .. }
+        ));
+    }
+
+    #[test]
+    fn reports_missing_outputs() {
+        let outputs = [output("eDP-1", true, None), output("DP-1", false, None)];
+        let profiles = [profile("docked", false, &[("eDP-1", None), ("DP-1", None)])];
+
+        let result = match_profiles(&profiles, &outputs);
+        assert!(result.best.is_none());
+        assert_eq!(
+            result.rejected[0].reasons,
+            vec![Rejection::MissingOutput("DP-1".to_string())]
+        );
+    }
+}
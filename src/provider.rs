@@ -0,0 +1,345 @@
+#[cfg(feature = "xlib")]
+use std::os::raw::c_int;
+#[cfg(feature = "xlib")]
+use std::{ptr, slice};
+
+use indexmap::IndexMap;
+#[cfg(feature = "serialize")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "xlib")]
+use x11::{xlib, xrandr};
+
+use crate::crtc::Crtc;
+use crate::output::property::Property;
+#[cfg(feature = "xlib")]
+use crate::output::property::PropertyOwner;
+use crate::output::Output;
+#[cfg(feature = "xlib")]
+use crate::screen_resources::ScreenResourcesHandle;
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+use crate::XId;
+#[cfg(feature = "xlib")]
+use crate::XrandrError;
+
+// What a provider (usually a GPU) can do for other providers. With PRIME, a
+// discrete GPU renders (source) while an integrated one drives the displays
+// (sink), or the other way around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Capabilities {
+    /// Can provide the image for outputs of other providers
+    pub source_output: bool,
+    /// Can show images that another provider renders on its outputs
+    pub sink_output: bool,
+    /// Can render for another provider
+    pub source_offload: bool,
+    /// Can have another provider render for it
+    pub sink_offload: bool,
+}
+
+// The `RR_Capability_*` bits
+const SOURCE_OUTPUT: u32 = 1;
+const SINK_OUTPUT: u32 = 2;
+const SOURCE_OFFLOAD: u32 = 4;
+const SINK_OFFLOAD: u32 = 8;
+
+impl From<u32> for Capabilities {
+    fn from(bits: u32) -> Self {
+        let has = |capability: u32| bits & capability != 0;
+        Self {
+            source_output: has(SOURCE_OUTPUT),
+            sink_output: has(SINK_OUTPUT),
+            source_offload: has(SOURCE_OFFLOAD),
+            sink_offload: has(SINK_OFFLOAD),
+        }
+    }
+}
+
+// A provider is a device that has crtcs and outputs and/or renders, i.e.
+// usually a GPU. Hybrid graphics laptops have two of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Provider {
+    pub xid: XId,
+    pub name: String,
+    pub capabilities: Capabilities,
+    pub crtcs: Vec<XId>,
+    pub outputs: Vec<XId>,
+    /// Providers this one currently works with, and in what role
+    pub associated: Vec<(XId, Capabilities)>,
+    /// E.g. `PRIME Synchronization` on modesetting
+    pub properties: IndexMap<String, Property>,
+}
+
+// A wrapper that drops the pointer if it goes out of scope.
+// Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
+struct ProviderResourcesHandle {
+    ptr: ptr::NonNull<xrandr::XRRProviderResources>,
+}
+
+#[cfg(feature = "xlib")]
+impl ProviderResourcesHandle {
+    fn new(handle: &mut XHandle) -> Result<Self, XrandrError> {
+        let raw_ptr =
+            unsafe { xrandr::XRRGetProviderResources(handle.sys.as_ptr(), handle.root()) };
+        let ptr = ptr::NonNull::new(raw_ptr).ok_or(XrandrError::GetProviders)?;
+
+        Ok(Self { ptr })
+    }
+
+    fn xids(&self) -> Vec<XId> {
+        let resources = unsafe { self.ptr.as_ref() };
+        if resources.nproviders <= 0 {
+            return Vec::new();
+        }
+        unsafe { slice::from_raw_parts(resources.providers, resources.nproviders as usize) }
+            .to_vec()
+    }
+}
+
+#[cfg(feature = "xlib")]
+impl Drop for ProviderResourcesHandle {
+    fn drop(&mut self) {
+        unsafe { xrandr::XRRFreeProviderResources(self.ptr.as_ptr()) };
+    }
+}
+
+// A wrapper that drops the pointer if it goes out of scope.
+// Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
+struct ProviderHandle {
+    ptr: ptr::NonNull<xrandr::XRRProviderInfo>,
+}
+
+#[cfg(feature = "xlib")]
+impl ProviderHandle {
+    fn new(handle: &mut XHandle, xid: XId) -> Result<Self, XrandrError> {
+        let res = ScreenResourcesHandle::new(handle)?;
+
+        let raw_ptr = unsafe { xrandr::XRRGetProviderInfo(handle.sys.as_ptr(), res.ptr(), xid) };
+        let ptr = ptr::NonNull::new(raw_ptr).ok_or(XrandrError::GetProviderInfo(xid))?;
+
+        Ok(Self { ptr })
+    }
+}
+
+#[cfg(feature = "xlib")]
+impl Drop for ProviderHandle {
+    fn drop(&mut self) {
+        unsafe { xrandr::XRRFreeProviderInfo(self.ptr.as_ptr()) };
+    }
+}
+
+// Copies a list of `len` items, which may be a null pointer if empty
+#[cfg(feature = "xlib")]
+unsafe fn list<T: Copy>(items: *const T, len: c_int) -> Vec<T> {
+    if items.is_null() || len <= 0 {
+        return Vec::new();
+    }
+    slice::from_raw_parts(items, len as usize).to_vec()
+}
+
+#[cfg(feature = "xlib")]
+impl Provider {
+    /// Gets every provider of the screen
+    ///
+    /// # Errors
+    /// * `XrandrError::GetProviders` - The x backend has no provider support
+    /// * `XrandrError::GetProviderInfo(xid)` - Getting a provider failed
+    /// * `XrandrError::GetProviderProp(xid)` - Getting a provider's
+    ///   properties failed
+    ///
+    pub(crate) fn all(handle: &mut XHandle) -> Result<Vec<Self>, XrandrError> {
+        let xids = ProviderResourcesHandle::new(handle)?.xids();
+        xids.into_iter()
+            .map(|xid| Self::from_xid(handle, xid))
+            .collect()
+    }
+
+    fn from_xid(handle: &mut XHandle, xid: XId) -> Result<Self, XrandrError> {
+        let info = ProviderHandle::new(handle, xid)?;
+
+        let xrandr::XRRProviderInfo {
+            capabilities,
+            ncrtcs,
+            crtcs,
+            noutputs,
+            outputs,
+            name,
+            nassociatedproviders,
+            associated_providers,
+            associated_capability,
+            nameLen,
+        } = unsafe { info.ptr.as_ref() };
+
+        let name = unsafe { list(name.cast::<u8>(), *nameLen) };
+        let providers = unsafe { list(*associated_providers, *nassociatedproviders) };
+        let roles = unsafe { list(*associated_capability, *nassociatedproviders) };
+        let properties = Property::all(handle, PropertyOwner::Provider(xid))?;
+
+        Ok(Self {
+            xid,
+            name: String::from_utf8_lossy(&name).to_string(),
+            capabilities: Capabilities::from(*capabilities),
+            crtcs: unsafe { list(*crtcs, *ncrtcs) },
+            outputs: unsafe { list(*outputs, *noutputs) },
+            associated: providers
+                .into_iter()
+                .zip(roles)
+                .map(|(xid, role)| (xid, Capabilities::from(role)))
+                .collect(),
+            properties,
+        })
+    }
+
+    /// Makes `source` render the image for this provider's outputs, or stops
+    /// that if `source` is `None`. Like `xrandr --setprovideroutputsource`.
+    ///
+    /// # Errors
+    /// * `XrandrError::ProviderCapability` - A provider cannot act in its role
+    ///
+    pub(crate) fn set_output_source(
+        &self,
+        handle: &mut XHandle,
+        source: Option<&Provider>,
+    ) -> Result<(), XrandrError> {
+        if let Some(source) = source {
+            self.require(self.capabilities.sink_output, "output sink")?;
+            source.require(source.capabilities.source_output, "output source")?;
+        }
+
+        unsafe {
+            xrandr::XRRSetProviderOutputSource(
+                handle.sys.as_ptr(),
+                self.xid,
+                source.map_or(0, |s| s.xid),
+            );
+            xlib::XSync(handle.sys.as_ptr(), xlib::False);
+        }
+        Ok(())
+    }
+
+    /// Makes this provider render for `sink`, or stops that if `sink` is
+    /// `None`. Like `xrandr --setprovideroffloadsink`.
+    ///
+    /// # Errors
+    /// * `XrandrError::ProviderCapability` - A provider cannot act in its role
+    ///
+    pub(crate) fn set_offload_sink(
+        &self,
+        handle: &mut XHandle,
+        sink: Option<&Provider>,
+    ) -> Result<(), XrandrError> {
+        if let Some(sink) = sink {
+            self.require(self.capabilities.source_offload, "offload source")?;
+            sink.require(sink.capabilities.sink_offload, "offload sink")?;
+        }
+
+        unsafe {
+            xrandr::XRRSetProviderOffloadSink(
+                handle.sys.as_ptr(),
+                self.xid,
+                sink.map_or(0, |s| s.xid),
+            );
+            xlib::XSync(handle.sys.as_ptr(), xlib::False);
+        }
+        Ok(())
+    }
+
+    fn require(&self, capable: bool, role: &'static str) -> Result<(), XrandrError> {
+        if capable {
+            Ok(())
+        } else {
+            Err(XrandrError::ProviderCapability(self.name.clone(), role))
+        }
+    }
+}
+
+impl Output {
+    /// The provider this output belongs to
+    #[must_use]
+    pub fn provider<'a>(&self, providers: &'a [Provider]) -> Option<&'a Provider> {
+        providers.iter().find(|p| p.outputs.contains(&self.xid))
+    }
+}
+
+impl Crtc {
+    /// The provider this crtc belongs to
+    #[must_use]
+    pub fn provider<'a>(&self, providers: &'a [Provider]) -> Option<&'a Provider> {
+        providers.iter().find(|p| p.crtcs.contains(&self.xid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    fn provider(
+        xid: XId,
+        name: &str,
+        capabilities: u32,
+        crtcs: &[XId],
+        outputs: &[XId],
+    ) -> Provider {
+        Provider {
+            xid,
+            name: name.to_string(),
+            capabilities: Capabilities::from(capabilities),
+            crtcs: crtcs.to_vec(),
+            outputs: outputs.to_vec(),
+            associated: Vec::new(),
+            properties: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn decodes_capabilities() {
+        let capabilities = Capabilities::from(0x0f);
+        assert!(capabilities.source_output && capabilities.sink_output);
+        assert!(capabilities.source_offload && capabilities.sink_offload);
+
+        let sink = Capabilities::from(SINK_OUTPUT);
+        assert_eq!(
+            sink,
+            Capabilities {
+                sink_output: true,
+                ..Capabilities::default()
+            }
+        );
+    }
+
+    #[test]
+    fn links_outputs_and_crtcs() {
+        let providers = [
+            provider(1, "modesetting", 0x0f, &[10, 11], &[20, 21]),
+            provider(2, "NVIDIA-G0", 0x05, &[12], &[22]),
+        ];
+
+        let output = fixtures::output(22, "HDMI-1-0");
+        assert_eq!(output.provider(&providers).unwrap().name, "NVIDIA-G0");
+
+        let crtc = fixtures::crtc(11, 0, 0, 1920, 1080);
+        assert_eq!(crtc.provider(&providers).unwrap().xid, 1);
+
+        assert!(fixtures::output(99, "DP-9").provider(&providers).is_none());
+    }
+
+    #[cfg(feature = "xlib")]
+    #[test]
+    fn requires_capabilities() {
+        let integrated = provider(1, "modesetting", 0x0f, &[], &[]);
+        let discrete = provider(2, "NVIDIA-0", SINK_OUTPUT, &[], &[]);
+
+        assert!(integrated
+            .require(integrated.capabilities.sink_output, "output sink")
+            .is_ok());
+        assert!(matches!(
+            discrete.require(discrete.capabilities.source_output, "output source"),
+            Err(XrandrError::ProviderCapability(name, "output source")) if name == "NVIDIA-0"
+        ));
+    }
+}
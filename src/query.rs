@@ -0,0 +1,707 @@
+use std::collections::HashSet;
+use std::fmt::{self, Write};
+
+use crate::crtc::{Crtc, Reflection, Rotation};
+use crate::gamma::GammaRamp;
+use crate::mode::Mode;
+use crate::output::property::{Property, Range, Ranges, Supported, Value, Values};
+use crate::output::Output;
+use crate::snapshot::Snapshot;
+use crate::transform::Transform;
+use crate::XId;
+
+// Names of the mode flags (`RR_HSyncPositive` etc.), in the order xrandr
+// prints them
+pub(crate) const MODE_FLAGS: [(i32, &str); 9] = [
+    (0x0000_0001, "+HSync"),
+    (0x0000_0002, "-HSync"),
+    (0x0000_0004, "+VSync"),
+    (0x0000_0008, "-VSync"),
+    (0x0000_0010, "Interlace"),
+    (0x0000_0020, "DoubleScan"),
+    (0x0000_0040, "CSync"),
+    (0x0000_0080, "+CSync"),
+    (0x0000_0100, "-CSync"),
+];
+
+// Names of the subpixel orders, by their value
+pub(crate) const SUBPIXEL_ORDERS: [&str; 6] = [
+    "unknown",
+    "horizontal rgb",
+    "horizontal bgr",
+    "vertical rgb",
+    "vertical bgr",
+    "no subpixels",
+];
+
+impl Snapshot {
+    /// The state of the screen, exactly like `xrandr --query` prints it
+    ///
+    /// # Examples
+    /// ```ignore
+    /// print!("{}", xhandle.snapshot()?.query());
+    /// ```
+    ///
+    #[must_use]
+    pub fn query(&self) -> String {
+        self.format(false)
+    }
+
+    /// The state of the screen, exactly like `xrandr --verbose` prints it
+    #[must_use]
+    pub fn verbose(&self) -> String {
+        self.format(true)
+    }
+
+    fn format(&self, verbose: bool) -> String {
+        let mut out = String::new();
+        self.write(&mut out, verbose)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write(&self, f: &mut impl Write, verbose: bool) -> fmt::Result {
+        writeln!(
+            f,
+            "Screen {}: minimum {} x {}, current {} x {}, maximum {} x {}",
+            self.screen,
+            self.range.min_width,
+            self.range.min_height,
+            self.size.width,
+            self.size.height,
+            self.range.max_width,
+            self.range.max_height,
+        )?;
+
+        let mut shown = HashSet::new();
+        for output in &self.outputs {
+            self.write_output(f, output, verbose)?;
+            if verbose {
+                self.write_verbose_modes(f, output)?;
+            } else {
+                self.write_modes(f, output)?;
+            }
+            shown.extend(output.modes.iter().copied());
+        }
+
+        // Modes that no output has, e.g. new ones that were not added yet
+        for mode in self.modes.iter().filter(|m| !shown.contains(&m.xid)) {
+            write_verbose_mode(f, mode, false, false)?;
+        }
+        Ok(())
+    }
+
+    // The rotations and reflections every crtc of `output` supports
+    fn rotations(&self, output: &Output) -> u16 {
+        output
+            .crtcs
+            .iter()
+            .filter_map(|xid| self.crtc(*xid))
+            .map(rotation_bits)
+            .reduce(|a, b| a & b)
+            .unwrap_or(Rotation::Normal as u16)
+    }
+
+    fn crtc_index(&self, xid: XId) -> Option<usize> {
+        self.crtcs.iter().position(|c| c.xid == xid)
+    }
+
+    fn write_output(&self, f: &mut impl Write, output: &Output, verbose: bool) -> fmt::Result {
+        let crtc = output.crtc.and_then(|xid| self.crtc(xid));
+        let mode = crtc.and_then(|c| self.mode(c.mode));
+        let state = if output.connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+
+        write!(f, "{} {}", output.name, state)?;
+        if output.is_primary {
+            write!(f, " primary")?;
+        }
+
+        if let (Some(crtc), Some(mode)) = (crtc, mode) {
+            let (width, height) = crtc.screen_size();
+            write!(f, " {}x{}+{}+{}", width, height, crtc.x, crtc.y)?;
+            if verbose {
+                write!(f, " (0x{:x})", mode.xid)?;
+            }
+            if rotation_bits_of(crtc.rotation, crtc.reflection) != Rotation::Normal as u16
+                || verbose
+            {
+                write!(f, " {}", rotation_name(crtc.rotation))?;
+                if crtc.reflection != Reflection::Normal {
+                    write!(f, " {}", reflection_name(crtc.reflection))?;
+                }
+            }
+        }
+
+        let rotations = self.rotations(output);
+        if rotations != Rotation::Normal as u16 || verbose {
+            let mut names: Vec<&str> = [
+                Rotation::Normal,
+                Rotation::Left,
+                Rotation::Inverted,
+                Rotation::Right,
+            ]
+            .into_iter()
+            .filter(|r| rotations & *r as u16 != 0)
+            .map(rotation_name)
+            .collect();
+            if rotations & Reflection::X as u16 != 0 {
+                names.push("x axis");
+            }
+            if rotations & Reflection::Y as u16 != 0 {
+                names.push("y axis");
+            }
+            write!(f, " ({})", names.join(" "))?;
+        }
+
+        if mode.is_some() {
+            write!(f, " {}mm x {}mm", output.mm_width, output.mm_height)?;
+        }
+
+        if let Some(pan) = crtc.and_then(|c| c.panning.as_ref()) {
+            write!(
+                f,
+                " panning {}x{}+{}+{}",
+                pan.width, pan.height, pan.left, pan.top
+            )?;
+            let tracks_x = pan.track_width != 0
+                && (pan.track_left != pan.left
+                    || pan.track_width != pan.width
+                    || pan.border_left != 0
+                    || pan.border_right != 0);
+            let tracks_y = pan.track_height != 0
+                && (pan.track_top != pan.top
+                    || pan.track_height != pan.height
+                    || pan.border_top != 0
+                    || pan.border_bottom != 0);
+            if tracks_x || tracks_y {
+                write!(
+                    f,
+                    " tracking {}x{}+{}+{} border {}/{}/{}/{}",
+                    pan.track_width,
+                    pan.track_height,
+                    pan.track_left,
+                    pan.track_top,
+                    pan.border_left,
+                    pan.border_top,
+                    pan.border_right,
+                    pan.border_bottom
+                )?;
+            }
+        }
+        writeln!(f)?;
+
+        if verbose {
+            self.write_details(f, output, crtc)?;
+        }
+        Ok(())
+    }
+
+    fn write_details(
+        &self,
+        f: &mut impl Write,
+        output: &Output,
+        crtc: Option<&Crtc>,
+    ) -> fmt::Result {
+        // xrandr prints these as C ints
+        writeln!(f, "\tIdentifier: 0x{:x}", output.xid)?;
+        writeln!(f, "\tTimestamp:  {}", output.timestamp as i32)?;
+        let subpixel = SUBPIXEL_ORDERS.get(usize::from(output.subpixel_order));
+        writeln!(f, "\tSubpixel:   {}", subpixel.unwrap_or(&"unknown"))?;
+
+        let gamma = crtc.and_then(|c| self.gamma.get(&c.xid));
+        if let Some((gamma, brightness)) = gamma.and_then(estimate_gamma) {
+            if gamma.iter().all(|g| *g != 0.0) {
+                let [red, green, blue] = gamma.map(|g| c_g2(1.0 / g));
+                writeln!(f, "\tGamma:      {}:{}:{}", red, green, blue)?;
+                writeln!(f, "\tBrightness: {}", c_g2(brightness))?;
+            }
+        }
+
+        write!(f, "\tClones:    ")?;
+        for clone in &output.clones {
+            if let Some(clone) = self.outputs.iter().find(|o| o.xid == *clone) {
+                write!(f, " {}", clone.name)?;
+            }
+        }
+        writeln!(f)?;
+
+        if let Some(index) = crtc.and_then(|c| self.crtc_index(c.xid)) {
+            writeln!(f, "\tCRTC:       {}", index)?;
+        }
+        write!(f, "\tCRTCs:     ")?;
+        for index in output.crtcs.iter().filter_map(|xid| self.crtc_index(*xid)) {
+            write!(f, " {}", index)?;
+        }
+        writeln!(f)?;
+
+        if let Some(pan) = crtc.and_then(|c| c.panning.as_ref()) {
+            writeln!(
+                f,
+                "\tPanning:    {}x{}+{}+{}",
+                pan.width, pan.height, pan.left, pan.top
+            )?;
+            writeln!(
+                f,
+                "\tTracking:   {}x{}+{}+{}",
+                pan.track_width, pan.track_height, pan.track_left, pan.track_top
+            )?;
+            writeln!(
+                f,
+                "\tBorder:     {}/{}/{}/{}",
+                pan.border_left, pan.border_top, pan.border_right, pan.border_bottom
+            )?;
+        }
+
+        // Outputs without a crtc show the identity, without a filter
+        let (matrix, filter) = match crtc {
+            Some(crtc) => (crtc.transform.to_matrix(), crtc.transform.filter.as_str()),
+            None => (Transform::identity().to_matrix(), ""),
+        };
+        write!(f, "\tTransform: ")?;
+        for (i, row) in matrix.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\n\t           ")?;
+            }
+            for value in row {
+                write!(f, " {:.6}", value)?;
+            }
+        }
+        writeln!(f, "\n\t           filter: {}", filter)?;
+
+        output
+            .properties
+            .values()
+            .try_for_each(|property| write_property(f, property))
+    }
+
+    // Modes with the same name share a line, which lists their rates
+    fn write_modes(&self, f: &mut impl Write, output: &Output) -> fmt::Result {
+        let current = output.crtc.and_then(|xid| self.crtc(xid)).map(|c| c.mode);
+        let modes: Vec<(usize, &Mode)> = output
+            .modes
+            .iter()
+            .filter_map(|xid| self.mode(*xid))
+            .enumerate()
+            .collect();
+
+        let mut shown = vec![false; modes.len()];
+        for (j, mode) in &modes {
+            if shown[*j] {
+                continue;
+            }
+            write!(f, "   {:<12}", mode.name)?;
+            for (k, other) in &modes[*j..] {
+                if shown[*k] || other.name != mode.name {
+                    continue;
+                }
+                shown[*k] = true;
+                write!(f, " {:6.2}", other.rate)?;
+                write!(f, "{}", if current == Some(other.xid) { '*' } else { ' ' })?;
+                let preferred = *k < output.preferred_modes.len();
+                write!(f, "{}", if preferred { '+' } else { ' ' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+
+    fn write_verbose_modes(&self, f: &mut impl Write, output: &Output) -> fmt::Result {
+        let current = output.crtc.and_then(|xid| self.crtc(xid)).map(|c| c.mode);
+        for (j, xid) in output.modes.iter().enumerate() {
+            if let Some(mode) = self.mode(*xid) {
+                let preferred = j < output.preferred_modes.len();
+                write_verbose_mode(f, mode, current == Some(*xid), preferred)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_verbose_mode(
+    f: &mut impl Write,
+    mode: &Mode,
+    current: bool,
+    preferred: bool,
+) -> fmt::Result {
+    write!(
+        f,
+        "  {} (0x{:x}) {:6.3}MHz",
+        mode.name,
+        mode.xid,
+        mode.dot_clock as f64 / 1_000_000.0
+    )?;
+    for (flag, name) in MODE_FLAGS {
+        if mode.flags & flag as u64 != 0 {
+            write!(f, " {}", name)?;
+        }
+    }
+    if current {
+        write!(f, " *current")?;
+    }
+    if preferred {
+        write!(f, " +preferred")?;
+    }
+    writeln!(f)?;
+
+    let hsync = if mode.htotal == 0 {
+        0.0
+    } else {
+        mode.dot_clock as f64 / f64::from(mode.htotal)
+    };
+    writeln!(
+        f,
+        "        h: width  {:4} start {:4} end {:4} total {:4} skew {:4} clock {:6.2}KHz",
+        mode.width,
+        mode.hsync_tart,
+        mode.hsync_end,
+        mode.htotal,
+        mode.hskew,
+        hsync / 1000.0
+    )?;
+    writeln!(
+        f,
+        "        v: height {:4} start {:4} end {:4} total {:4}           clock {:6.2}Hz",
+        mode.height, mode.vsync_start, mode.vsync_end, mode.vtotal, mode.rate
+    )
+}
+
+fn write_property(f: &mut impl Write, property: &Property) -> fmt::Result {
+    write!(f, "\t{}: ", property.name)?;
+
+    match &property.value {
+        // Only the EDID is dumped in hex
+        Value::Edid(bytes) => {
+            for (k, byte) in bytes.iter().enumerate() {
+                if k % 16 == 0 {
+                    write!(f, "\n\t\t")?;
+                }
+                write!(f, "{:02x}", byte)?;
+            }
+            writeln!(f)?;
+        }
+        Value::Guid(bytes) => {
+            write!(f, "{{")?;
+            for (k, byte) in bytes.iter().enumerate() {
+                write!(f, "{:02X}", byte)?;
+                if matches!(k, 3 | 5 | 7 | 9) {
+                    write!(f, "-")?;
+                }
+            }
+            writeln!(f, "}}")?;
+        }
+        value => {
+            let items: Vec<String> = match value {
+                Value::Atom(name) => vec![name.clone()],
+                Value::Integer8(values) => strings(values),
+                Value::Integer16(values) => strings(values),
+                Value::Integer32(values) => strings(values),
+                Value::Cardinal8(values) => strings(values),
+                Value::Cardinal16(values) => strings(values),
+                Value::Cardinal32(values) => strings(values),
+                _ => vec!["?".to_string()],
+            };
+            for (k, item) in items.iter().enumerate() {
+                if k != 0 && k % 16 == 0 {
+                    write!(f, "\n\t\t")?;
+                }
+                write!(f, "{} ", item)?;
+            }
+            writeln!(f)?;
+        }
+    }
+
+    match &property.values {
+        Some(Values::Range(ranges)) => {
+            let ranges = match ranges {
+                Ranges::Atom(r) => pairs(r),
+                Ranges::Integer8(r) => pairs(r),
+                Ranges::Integer16(r) => pairs(r),
+                Ranges::Integer32(r) => pairs(r),
+                Ranges::Cardinal8(r) => pairs(r),
+                Ranges::Cardinal16(r) => pairs(r),
+                Ranges::Cardinal32(r) => pairs(r),
+            };
+            if !ranges.is_empty() {
+                let plural = if ranges.len() == 1 { "" } else { "s" };
+                writeln!(f, "\t\trange{}: {}", plural, ranges.join(", "))?;
+            }
+        }
+        Some(Values::Supported(supported)) => {
+            let supported = match supported {
+                Supported::Atom(v) => v.clone(),
+                Supported::Integer8(v) => strings(v),
+                Supported::Integer16(v) => strings(v),
+                Supported::Integer32(v) => strings(v),
+                Supported::Cardinal8(v) => strings(v),
+                Supported::Cardinal16(v) => strings(v),
+                Supported::Cardinal32(v) => strings(v),
+            };
+            if !supported.is_empty() {
+                writeln!(f, "\t\tsupported: {}", supported.join(", "))?;
+            }
+        }
+        Some(Values::Unrecognized { .. }) | None => {}
+    }
+    Ok(())
+}
+
+fn strings<T: ToString>(values: &[T]) -> Vec<String> {
+    values.iter().map(ToString::to_string).collect()
+}
+
+fn pairs<T: fmt::Display>(ranges: &[Range<T>]) -> Vec<String> {
+    ranges
+        .iter()
+        .map(|r| format!("({}, {})", r.lower, r.upper))
+        .collect()
+}
+
+fn rotation_bits_of(rotation: Rotation, reflection: Reflection) -> u16 {
+    rotation as u16 | reflection as u16
+}
+
+// The bitmask of rotations and reflections `crtc` supports
+fn rotation_bits(crtc: &Crtc) -> u16 {
+    let rotations = crtc.rotations.iter().map(|r| *r as u16);
+    let reflections = crtc.reflections.iter().map(|r| *r as u16);
+    rotations.chain(reflections).fold(0, |bits, bit| bits | bit)
+}
+
+fn rotation_name(rotation: Rotation) -> &'static str {
+    match rotation {
+        Rotation::Normal => "normal",
+        Rotation::Left => "left",
+        Rotation::Inverted => "inverted",
+        Rotation::Right => "right",
+    }
+}
+
+fn reflection_name(reflection: Reflection) -> &'static str {
+    match reflection {
+        Reflection::Normal => "none",
+        Reflection::X => "X axis",
+        Reflection::Y => "Y axis",
+        Reflection::XY => "X and Y axis",
+    }
+}
+
+// Approximates a gamma ramp by a gamma per channel and a brightness, the way
+// xrandr does: the brightness from the highest point of the curve that is
+// not clamped, and the gamma from the middle of it.
+fn estimate_gamma(ramp: &GammaRamp) -> Option<([f64; 3], f64)> {
+    let size = ramp.size();
+    if size < 2 {
+        return None;
+    }
+
+    let channels = [&ramp.red, &ramp.green, &ramp.blue];
+    let last_unclamped = channels.map(|c| (1..size).rev().find(|i| c[*i] < u16::MAX).unwrap_or(0));
+    let value = |channel: usize, i: usize| f64::from(channels[channel][i]) / 65535.0;
+
+    let mut best = 0;
+    for channel in 1..3 {
+        if last_unclamped[channel] > last_unclamped[best] {
+            best = channel;
+        }
+    }
+    let last = last_unclamped[best].max(1);
+    let middle = last / 2;
+    let (i1, v1) = ((middle + 1) as f64 / size as f64, value(best, middle));
+    let (i2, v2) = ((last + 1) as f64 / size as f64, value(best, last));
+
+    // The screen is black
+    if v2 < 0.0001 {
+        return Some(([1.0; 3], 0.0));
+    }
+
+    let brightness = if last + 1 == size {
+        v2
+    } else {
+        ((v2.ln() * i1.ln() - v1.ln() * i2.ln()) / (i1 / i2).ln()).exp()
+    };
+    let gamma = [0, 1, 2].map(|channel| {
+        let half = last_unclamped[channel] / 2;
+        (value(channel, half) / brightness).ln() / ((half + 1) as f64 / size as f64).ln()
+    });
+    Some((gamma, brightness))
+}
+
+// Formats like C's `%#.2g`: two significant digits, keeping trailing zeros
+fn c_g2(value: f64) -> String {
+    if value == 0.0 {
+        return "0.0".to_string();
+    }
+    if !value.is_finite() {
+        return value.to_string();
+    }
+
+    let scientific = format!("{:.1e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').expect("{:e} has an exponent");
+    let exponent: i32 = exponent.parse().expect("{:e} has an integer exponent");
+
+    if !(-4..2).contains(&exponent) {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        return format!("{}e{}{:02}", mantissa, sign, exponent.abs());
+    }
+    let fixed = format!("{:.*}", (1 - exponent) as usize, value);
+    if fixed.contains('.') {
+        fixed
+    } else {
+        fixed + "."
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{fixtures, ScreenSize, ScreenSizeRange};
+
+    fn snapshot() -> Snapshot {
+        let mut modes = vec![
+            fixtures::mode(0x48, 1920, 1080, 60.02),
+            fixtures::mode(0x49, 1920, 1080, 48.0),
+            fixtures::mode(0x4a, 1280, 720, 59.94),
+        ];
+        modes[0].dot_clock = 141_000_000;
+        // -HSync -VSync
+        modes[0].flags = 0x0000_000a;
+        modes[0].htotal = 2104;
+
+        let mut crtc = fixtures::crtc(0x40, 0, 0, 1920, 1080);
+        crtc.mode = 0x48;
+        crtc.outputs = vec![0x42];
+        crtc.reflections = vec![Reflection::Normal, Reflection::X, Reflection::Y];
+
+        let mut edp = fixtures::output(0x42, "eDP-1");
+        edp.is_primary = true;
+        edp.crtc = Some(0x40);
+        edp.crtcs = vec![0x40];
+        edp.mm_width = 344;
+        edp.mm_height = 194;
+        edp.modes = vec![0x48, 0x49, 0x4a];
+        edp.preferred_modes = vec![0x48];
+        edp.properties.insert(
+            "non-desktop".to_string(),
+            Property {
+                name: "non-desktop".to_string(),
+                value: Value::Integer32(vec![0]),
+                values: Some(Values::Range(Ranges::Integer32(vec![Range {
+                    lower: 0,
+                    upper: 1,
+                }]))),
+                is_immutable: true,
+                is_pending: false,
+            },
+        );
+
+        let mut hdmi = fixtures::output(0x43, "HDMI-1");
+        hdmi.connected = false;
+
+        Snapshot {
+            screen: 0,
+            size: ScreenSize {
+                width: 1920,
+                height: 1080,
+                width_mm: 508,
+                height_mm: 285,
+            },
+            range: ScreenSizeRange {
+                min_width: 320,
+                min_height: 200,
+                max_width: 16384,
+                max_height: 16384,
+            },
+            modes,
+            crtcs: vec![crtc],
+            outputs: vec![edp, hdmi],
+            gamma: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn formats_query() {
+        assert_eq!(
+            snapshot().query(),
+            "Screen 0: minimum 320 x 200, current 1920 x 1080, maximum 16384 x 16384\n\
+             eDP-1 connected primary 1920x1080+0+0 \
+             (normal left inverted right x axis y axis) 344mm x 194mm\n   \
+             1920x1080     60.02*+  48.00  \n   \
+             1280x720      59.94  \n\
+             HDMI-1 disconnected\n"
+        );
+    }
+
+    #[test]
+    fn formats_verbose() {
+        let mut snapshot = snapshot();
+        snapshot.gamma.insert(0x40, GammaRamp::linear(256));
+        let verbose = snapshot.verbose();
+
+        assert!(verbose.contains(
+            "eDP-1 connected primary 1920x1080+0+0 (0x48) normal \
+             (normal left inverted right x axis y axis) 344mm x 194mm\n\
+             \tIdentifier: 0x42\n\
+             \tTimestamp:  0\n\
+             \tSubpixel:   unknown\n\
+             \tGamma:      0.99:0.99:0.99\n\
+             \tBrightness: 1.0\n\
+             \tClones:    \n\
+             \tCRTC:       0\n\
+             \tCRTCs:      0\n\
+             \tTransform:  1.000000 0.000000 0.000000\n\
+             \t            0.000000 1.000000 0.000000\n\
+             \t            0.000000 0.000000 1.000000\n\
+             \t           filter: nearest\n\
+             \tnon-desktop: 0 \n\
+             \t\trange: (0, 1)\n\
+             \x20 1920x1080 (0x48) 141.000MHz -HSync -VSync *current +preferred\n\
+             \x20       h: width  1920 start    0 end    0 total 2104 skew    0 clock  67.02KHz\n\
+             \x20       v: height 1080 start    0 end    0 total    0           clock  60.02Hz\n"
+        ));
+        assert!(verbose.contains("HDMI-1 disconnected (normal)\n\tIdentifier: 0x43\n"));
+    }
+
+    fn property(name: &str, value: Value) -> Property {
+        Property {
+            name: name.to_string(),
+            value,
+            values: None,
+            is_immutable: false,
+            is_pending: false,
+        }
+    }
+
+    #[test]
+    fn formats_guids() {
+        let guid = Value::Guid([
+            0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab,
+            0xcd, 0xef,
+        ]);
+        let mut out = String::new();
+        write_property(&mut out, &property("GUID", guid)).unwrap();
+        assert_eq!(out, "\tGUID: {12345678-9ABC-DEF0-0123-456789ABCDEF}\n");
+    }
+
+    #[test]
+    fn formats_integer8() {
+        let mut out = String::new();
+        let value = Value::Integer8(vec![-1, 0, 127]);
+        write_property(&mut out, &property("Broadcast", value)).unwrap();
+        assert_eq!(out, "\tBroadcast: -1 0 127 \n");
+    }
+
+    #[test]
+    fn formats_like_c() {
+        assert_eq!(c_g2(1.0), "1.0");
+        assert_eq!(c_g2(0.8), "0.80");
+        assert_eq!(c_g2(1.0 / 0.8), "1.2");
+        assert_eq!(c_g2(12.0), "12.");
+        assert_eq!(c_g2(123.0), "1.2e+02");
+    }
+}
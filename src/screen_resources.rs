@@ -1,9 +1,14 @@
+#[cfg(feature = "xlib")]
 use std::{ptr, slice};
+#[cfg(feature = "xlib")]
 use x11::xrandr;
 
+#[cfg(feature = "xlib")]
 use crate::crtc::Crtc;
+#[cfg(feature = "xlib")]
 use crate::output::Output;
 use crate::Mode;
+#[cfg(feature = "xlib")]
 use crate::XHandle;
 use crate::XrandrError;
 
@@ -12,10 +17,12 @@ use crate::XTime;
 
 // A wrapper that drops the pointer if it goes out of scope.
 // Avoid having to deal with the various early returns
+#[cfg(feature = "xlib")]
 pub(crate) struct ScreenResourcesHandle {
     ptr: ptr::NonNull<xrandr::XRRScreenResources>,
 }
 
+#[cfg(feature = "xlib")]
 impl ScreenResourcesHandle {
     pub(crate) fn new(handle: &mut XHandle) -> Result<Self, XrandrError> {
         let raw_ptr = unsafe { xrandr::XRRGetScreenResources(handle.sys.as_ptr(), handle.root()) };
@@ -29,6 +36,7 @@ impl ScreenResourcesHandle {
     }
 }
 
+#[cfg(feature = "xlib")]
 impl Drop for ScreenResourcesHandle {
     fn drop(&mut self) {
         unsafe { xrandr::XRRFreeScreenResources(self.ptr.as_ptr()) };
@@ -40,7 +48,7 @@ pub struct ScreenResources {
     pub timestamp: XTime,
     pub config_timestamp: XTime,
     pub ncrtc: i32,
-    crtcs: Vec<XId>,
+    pub(crate) crtcs: Vec<XId>,
     pub outputs: Vec<XId>,
     pub nmode: i32,
     pub modes: Vec<Mode>,
@@ -60,6 +68,7 @@ impl ScreenResources {
     /// let crtc_87 = res.crtc(&mut xhandle, 87);
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn new(handle: &mut XHandle) -> Result<ScreenResources, XrandrError> {
         // TODO: does this need to be freed?
         let res = ScreenResourcesHandle::new(handle)?;
@@ -99,7 +108,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetOutputInfo(xid)`
-    ///    -- Getting info failed for output xid
+    ///   -- Getting info failed for output xid
     ///
     /// # Examples
     /// ```
@@ -107,6 +116,7 @@ impl ScreenResources {
     /// let outputs = res.outputs(&mut xhandle);
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn outputs(&self, handle: &mut XHandle) -> Result<Vec<Output>, XrandrError> {
         self.outputs
             .iter()
@@ -118,7 +128,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetOutputInfo(xid)`
-    ///    -- Getting info failed for output with XID `xid`
+    ///   -- Getting info failed for output with XID `xid`
     ///
     /// # Examples
     /// ```
@@ -126,6 +136,7 @@ impl ScreenResources {
     /// let output_89 = res.output(&mut xhandle, 89);
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn output(&self, handle: &mut XHandle, xid: XId) -> Result<Output, XrandrError> {
         self.outputs(handle)?
             .into_iter()
@@ -137,7 +148,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetCrtcInfo(xid)`
-    ///    -- Getting info failed for crtc with XID `xid`
+    ///   -- Getting info failed for crtc with XID `xid`
     ///
     /// # Examples
     /// ```
@@ -145,6 +156,7 @@ impl ScreenResources {
     /// let crtcs = res.crtcs(&mut xhandle);
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn crtcs(&self, handle: &mut XHandle) -> Result<Vec<Crtc>, XrandrError> {
         self.crtcs
             .iter()
@@ -156,8 +168,9 @@ impl ScreenResources {
     /// See also: `self.crtcs()`
     /// # Errors
     /// * `XrandrError::GetCrtcInfo(xid)`
-    ///    -- Getting info failed for crtc with XID `xid`
+    ///   -- Getting info failed for crtc with XID `xid`
     ///
+    #[cfg(feature = "xlib")]
     pub fn enabled_crtcs(&self, handle: &mut XHandle) -> Result<Vec<Crtc>, XrandrError> {
         Ok(self
             .crtcs(handle)?
@@ -170,7 +183,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetCrtcInfo(xid)`
-    ///    -- Getting info failed for crtc with XID `xid`
+    ///   -- Getting info failed for crtc with XID `xid`
     ///
     /// # Examples
     /// ```
@@ -178,6 +191,7 @@ impl ScreenResources {
     /// let current_crtc = res.crtc(&mut xhandle, output.crtc);
     /// ```
     ///
+    #[cfg(feature = "xlib")]
     pub fn crtc(&self, handle: &mut XHandle, xid: XId) -> Result<Crtc, XrandrError> {
         self.crtcs(handle)?
             .into_iter()
@@ -189,7 +203,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetCrtcInfo(xid)`
-    ///    -- Getting info failed for crtc with XID `xid`
+    ///   -- Getting info failed for crtc with XID `xid`
     ///
     /// # Examples
     /// ```
@@ -206,7 +220,7 @@ impl ScreenResources {
     ///
     /// # Errors
     /// * `XrandrError::GetModeInfo(xid)`
-    ///    -- Getting info failed for mode with XID `xid`
+    ///   -- Getting info failed for mode with XID `xid`
     ///
     /// # Examples
     /// ```
@@ -1,16 +1,29 @@
 use crate::crtc::Crtc;
+#[cfg(feature = "xlib")]
 use crate::XHandle;
-use x11::xlib;
+use crate::XrandrError;
+#[cfg(feature = "xlib")]
+use x11::{xlib, xrandr};
 
 // The amount of milimeters in an inch, needed for dpi calculation
 const INCH_MM: f32 = 25.4;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ScreenSize {
-    pub(crate) width: i32,
-    pub(crate) width_mm: i32,
-    pub(crate) height: i32,
-    pub(crate) height_mm: i32,
+    pub width: i32,
+    pub width_mm: i32,
+    pub height: i32,
+    pub height_mm: i32,
+}
+
+// The smallest and largest screen the x backend can handle, usually limited
+// by the maximum framebuffer size of the graphics driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenSizeRange {
+    pub min_width: i32,
+    pub min_height: i32,
+    pub max_width: i32,
+    pub max_height: i32,
 }
 
 // Apparently this does not exist (in non-nightly)?
@@ -25,6 +38,40 @@ fn lossy_f32_to_i32(from: f32) -> Result<i32, ()> {
     }
 }
 
+#[cfg(feature = "xlib")]
+impl ScreenSizeRange {
+    /// Queries the screen sizes supported by the x backend
+    ///
+    /// # Errors
+    /// * `XrandrError::GetScreenSizeRange` - The query failed
+    ///
+    pub(crate) fn new(handle: &mut XHandle) -> Result<Self, XrandrError> {
+        let (mut min_width, mut min_height, mut max_width, mut max_height) = (0, 0, 0, 0);
+
+        let status = unsafe {
+            xrandr::XRRGetScreenSizeRange(
+                handle.sys.as_ptr(),
+                handle.root(),
+                &mut min_width,
+                &mut min_height,
+                &mut max_width,
+                &mut max_height,
+            )
+        };
+
+        if status == 0 {
+            return Err(XrandrError::GetScreenSizeRange);
+        }
+
+        Ok(Self {
+            min_width,
+            min_height,
+            max_width,
+            max_height,
+        })
+    }
+}
+
 impl ScreenSize {
     /// True iff the given crtc fits on a screen of this size
     #[must_use]
@@ -33,30 +80,153 @@ impl ScreenSize {
         max_x <= self.width && max_y <= self.height
     }
 
-    /// Calculates the screen size that (snugly) fits a set of crtcs
-    pub(crate) fn fitting_crtcs(handle: &mut XHandle, crtcs: &[Crtc]) -> Self {
-        // see also: following unwraps
-        assert!(!crtcs.is_empty(), "Empty input vector");
+    /// The current size of the (first) screen
+    #[cfg(feature = "xlib")]
+    pub(crate) fn current(handle: &mut XHandle) -> Self {
+        unsafe {
+            Self {
+                width: xlib::XDisplayWidth(handle.sys.as_ptr(), 0),
+                width_mm: xlib::XDisplayWidthMM(handle.sys.as_ptr(), 0),
+                height: xlib::XDisplayHeight(handle.sys.as_ptr(), 0),
+                height_mm: xlib::XDisplayHeightMM(handle.sys.as_ptr(), 0),
+            }
+        }
+    }
 
-        let width = crtcs.iter().map(|p| p.max_coordinates().0).max().unwrap();
-        let height = crtcs.iter().map(|p| p.max_coordinates().1).max().unwrap();
+    /// Calculates the screen size that (snugly) fits a set of crtcs, with
+    /// the dpi of the `current` size. Without any crtcs, that is the minimum
+    /// size of `range`.
+    ///
+    /// # Errors
+    /// * `XrandrError::PhysicalScreenSize` - The physical size cannot be
+    ///   calculated, e.g. because the current size is 0x0
+    ///
+    pub(crate) fn fitting_crtcs(
+        current: &ScreenSize,
+        range: &ScreenSizeRange,
+        crtcs: &[Crtc],
+    ) -> Result<Self, XrandrError> {
+        let width = crtcs.iter().map(|p| p.max_coordinates().0).max();
+        let height = crtcs.iter().map(|p| p.max_coordinates().1).max();
+        let width = width.unwrap_or(range.min_width);
+        let height = height.unwrap_or(range.min_height);
 
         // Get the old sizes to calculate the dpi
-        let c_h = unsafe { xlib::XDisplayHeight(handle.sys.as_ptr(), 0) };
-        let c_h_mm = unsafe { xlib::XDisplayHeightMM(handle.sys.as_ptr(), 0) };
+        let c_h = current.height;
+        let c_h_mm = current.height_mm;
 
         // Calculate the new physical size with the dpi and px count
         let dpi: f32 = (INCH_MM * c_h as f32) / c_h_mm as f32;
 
         // let x = (INCH_MM * width as f32) / dpi
-        let width_mm = lossy_f32_to_i32((INCH_MM * width as f32) / dpi).unwrap();
-        let height_mm = lossy_f32_to_i32((INCH_MM * height as f32) / dpi).unwrap();
+        let millimeters = |pixels: i32| {
+            lossy_f32_to_i32((INCH_MM * pixels as f32) / dpi)
+                .map_err(|()| XrandrError::PhysicalScreenSize(width, height))
+        };
 
-        ScreenSize {
+        Ok(ScreenSize {
             width,
-            width_mm,
+            width_mm: millimeters(width)?,
             height,
-            height_mm,
+            height_mm: millimeters(height)?,
+        })
+    }
+
+    /// Grows this size to the minimum of `range` where needed, keeping the
+    /// dpi the same
+    ///
+    /// # Errors
+    /// * `XrandrError::ScreenTooLarge` - The size exceeds the maximum of
+    ///   `range`. Nothing is changed in that case.
+    ///
+    pub(crate) fn fit_to_range(&mut self, range: &ScreenSizeRange) -> Result<(), XrandrError> {
+        if self.width > range.max_width || self.height > range.max_height {
+            return Err(XrandrError::ScreenTooLarge {
+                width: self.width,
+                height: self.height,
+                max_width: range.max_width,
+                max_height: range.max_height,
+            });
+        }
+
+        if self.width < range.min_width {
+            self.width_mm = scale(self.width_mm, range.min_width, self.width);
+            self.width = range.min_width;
         }
+        if self.height < range.min_height {
+            self.height_mm = scale(self.height_mm, range.min_height, self.height);
+            self.height = range.min_height;
+        }
+
+        Ok(())
+    }
+}
+
+// `value * numerator / denominator`, without overflowing in between
+fn scale(value: i32, numerator: i32, denominator: i32) -> i32 {
+    if denominator == 0 {
+        return value;
+    }
+    let scaled = i64::from(value) * i64::from(numerator) / i64::from(denominator);
+    i32::try_from(scaled).unwrap_or(i32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RANGE: ScreenSizeRange = ScreenSizeRange {
+        min_width: 320,
+        min_height: 200,
+        max_width: 8192,
+        max_height: 8192,
+    };
+
+    #[test]
+    fn fits_size_to_range() {
+        let mut size = ScreenSize {
+            width: 160,
+            width_mm: 40,
+            height: 1080,
+            height_mm: 300,
+        };
+        size.fit_to_range(&RANGE).unwrap();
+        assert_eq!((size.width, size.width_mm), (320, 80));
+        assert_eq!((size.height, size.height_mm), (1080, 300));
+
+        let mut size = ScreenSize {
+            width: 8960,
+            width_mm: 2000,
+            height: 1080,
+            height_mm: 300,
+        };
+        assert!(matches!(
+            size.fit_to_range(&RANGE),
+            Err(XrandrError::ScreenTooLarge { width: 8960, .. })
+        ));
+    }
+
+    #[test]
+    fn fits_size_to_crtcs() {
+        let current = ScreenSize {
+            width: 1920,
+            width_mm: 480,
+            height: 1080,
+            height_mm: 270,
+        };
+        let size = ScreenSize::fitting_crtcs(&current, &RANGE, &[]).unwrap();
+        assert_eq!((size.width, size.width_mm), (320, 80));
+        assert_eq!((size.height, size.height_mm), (200, 50));
+
+        let unknown = ScreenSize {
+            width: 0,
+            width_mm: 0,
+            height: 0,
+            height_mm: 0,
+        };
+        assert!(matches!(
+            ScreenSize::fitting_crtcs(&unknown, &RANGE, &[]),
+            Err(XrandrError::PhysicalScreenSize(320, 200))
+        ));
     }
 }
@@ -0,0 +1,93 @@
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+
+use x11::xlib;
+
+use crate::XHandle;
+use crate::XrandrError;
+
+// A handle that can be cloned and used from several threads. The clones
+// share one display connection, which a lock hands to one thread at a time.
+//
+// A thread that waits for events should not hold the lock while it waits,
+// since that blocks every other clone. Give it its own `XHandle` instead.
+#[derive(Debug, Clone)]
+pub struct SharedHandle {
+    handle: Arc<Mutex<XHandle>>,
+}
+
+/// Makes Xlib safe to use from several threads, via `XInitThreads`. This has
+/// to happen before the process makes any other Xlib call, so call it first
+/// thing in `main` if displays are opened in more than one place.
+/// `SharedHandle::open` calls it as well. Calling it again does nothing.
+///
+/// # Errors
+/// * `XrandrError::InitThreads` - Xlib has no thread support
+///
+pub fn init_threads() -> Result<(), XrandrError> {
+    static INITIALIZED: OnceLock<bool> = OnceLock::new();
+
+    let initialized = *INITIALIZED.get_or_init(|| unsafe { xlib::XInitThreads() } != 0);
+    if initialized {
+        Ok(())
+    } else {
+        Err(XrandrError::InitThreads)
+    }
+}
+
+impl SharedHandle {
+    /// Initializes Xlib threading and opens a handle to share
+    ///
+    /// # Errors
+    /// * `XrandrError::InitThreads` - Xlib has no thread support
+    /// * `XrandrError::Open` - Getting the handle failed.
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let shared = SharedHandle::open()?;
+    /// let worker = shared.clone();
+    /// std::thread::spawn(move || worker.lock().all_outputs());
+    /// ```
+    ///
+    pub fn open() -> Result<Self, XrandrError> {
+        init_threads()?;
+        Ok(Self::from(XHandle::open()?))
+    }
+
+    /// Waits until no other clone uses the handle, then borrows it. The
+    /// handle is released when the guard is dropped.
+    ///
+    /// A thread that panicked while holding the handle leaves it usable,
+    /// since every call leaves the display in a consistent state.
+    pub fn lock(&self) -> MutexGuard<'_, XHandle> {
+        self.handle.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<XHandle> for SharedHandle {
+    /// Shares an open handle. The lock keeps threads from using it at the
+    /// same time, so this does not need `init_threads`.
+    fn from(handle: XHandle) -> Self {
+        Self {
+            handle: Arc::new(Mutex::new(handle)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_send_and_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send::<XHandle>();
+        assert_send_sync::<SharedHandle>();
+    }
+
+    #[test]
+    fn initializes_threads_once() {
+        assert!(init_threads().is_ok());
+        assert!(init_threads().is_ok());
+    }
+}
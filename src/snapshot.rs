@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::crtc::Crtc;
+use crate::gamma::GammaRamp;
+use crate::mode::Mode;
+use crate::output::Output;
+use crate::{RandrBackend, ScreenSize, ScreenSizeRange, XId, XrandrError};
+
+// The whole state of a screen, read in one go, as `xrandr --verbose` shows
+// it. It can be printed (see `query`), and inspected without a display.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The number of the screen, usually 0
+    pub screen: i32,
+    pub size: ScreenSize,
+    pub range: ScreenSizeRange,
+    pub modes: Vec<Mode>,
+    /// Every crtc, in the order of the screen resources
+    pub crtcs: Vec<Crtc>,
+    /// Every output, in the order of the screen resources
+    pub outputs: Vec<Output>,
+    /// The gamma ramps of the enabled crtcs, by crtc xid
+    pub gamma: HashMap<XId, GammaRamp>,
+}
+
+impl Snapshot {
+    /// Reads the state of the screen of `backend`. Backends have no gamma
+    /// ramps, so `gamma` is left empty.
+    ///
+    /// # Errors
+    /// * `XrandrError::_` - various calls to the backend may fail
+    ///
+    /// # Examples
+    /// ```ignore
+    /// let snapshot = Snapshot::read(&mut fake)?;
+    /// print!("{}", snapshot.query());
+    /// ```
+    ///
+    pub fn read(backend: &mut impl RandrBackend) -> Result<Self, XrandrError> {
+        let res = backend.screen_resources()?;
+        let crtcs = res
+            .crtcs
+            .iter()
+            .map(|xid| backend.crtc_info(*xid))
+            .collect::<Result<_, _>>()?;
+        let outputs = res
+            .outputs
+            .iter()
+            .map(|xid| backend.output_info(*xid))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            screen: 0,
+            size: backend.screen_size()?,
+            range: backend.screen_size_range()?,
+            modes: res.modes,
+            crtcs,
+            outputs,
+            gamma: HashMap::new(),
+        })
+    }
+
+    /// The mode with xid `xid`, if there is one
+    #[must_use]
+    pub fn mode(&self, xid: XId) -> Option<&Mode> {
+        self.modes.iter().find(|m| m.xid == xid)
+    }
+
+    /// The crtc with xid `xid`, if there is one
+    #[must_use]
+    pub fn crtc(&self, xid: XId) -> Option<&Crtc> {
+        self.crtcs.iter().find(|c| c.xid == xid)
+    }
+
+    /// The output called `name`, if there is one
+    #[must_use]
+    pub fn output(&self, name: &str) -> Option<&Output> {
+        self.outputs.iter().find(|o| o.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeBackend;
+
+    #[test]
+    fn reads_backends() {
+        let mut fake = FakeBackend::new(ScreenSizeRange {
+            min_width: 320,
+            min_height: 200,
+            max_width: 8192,
+            max_height: 8192,
+        });
+        let mode = fake.add_mode(1920, 1080, 60.0);
+        let crtc = fake.add_crtc();
+        fake.add_output("DP-1", &[mode]);
+
+        let snapshot = Snapshot::read(&mut fake).unwrap();
+        assert_eq!(snapshot.range.max_width, 8192);
+        assert_eq!(snapshot.mode(mode).unwrap().width, 1920);
+        assert!(snapshot.crtc(crtc).is_some());
+        assert_eq!(snapshot.output("DP-1").unwrap().modes, vec![mode]);
+        assert!(snapshot.output("DP-2").is_none());
+    }
+}
@@ -0,0 +1,306 @@
+use std::str::FromStr;
+
+#[cfg(feature = "xlib")]
+use crate::crtc::{Crtc, Rotation};
+use crate::monitor::MonitorGeometry;
+#[cfg(feature = "xlib")]
+use crate::output::Output;
+use crate::XrandrError;
+
+// How to divide an output into several monitors, e.g. so a tiling window
+// manager treats the halves of an ultrawide display as separate screens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Split {
+    /// Side by side, with the given relative widths, e.g. `[60, 40]`
+    Horizontal(Vec<u32>),
+    /// On top of each other, with the given relative heights
+    Vertical(Vec<u32>),
+    /// Explicit areas as (x, y, width, height), relative to the top left
+    /// corner of the output
+    Rects(Vec<(i32, i32, i32, i32)>),
+}
+
+// X screens are at most this many pixels wide and high, so an output cannot
+// be split into more monitors than that
+const MAX_PARTS: u32 = i16::MAX as u32;
+
+impl Split {
+    /// `n` equally wide monitors side by side. `n` is capped at the largest
+    /// possible screen width, which `geometries` rejects anyway.
+    #[must_use]
+    pub fn columns(n: u32) -> Self {
+        Split::Horizontal(vec![1; n.min(MAX_PARTS) as usize])
+    }
+
+    /// `n` equally high monitors on top of each other. `n` is capped at the
+    /// largest possible screen height, which `geometries` rejects anyway.
+    #[must_use]
+    pub fn rows(n: u32) -> Self {
+        Split::Vertical(vec![1; n.min(MAX_PARTS) as usize])
+    }
+
+    /// The geometries of the monitors that split an output at `output`.
+    /// Physical sizes are scaled along with the pixels.
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidSplit` - The split has no parts, a part is
+    ///   empty, or a rectangle is not within the output
+    ///
+    pub fn geometries(
+        &self,
+        output: &MonitorGeometry,
+    ) -> Result<Vec<MonitorGeometry>, XrandrError> {
+        let rects = match self {
+            Split::Horizontal(weights) => divide(weights, output.width_px)?
+                .into_iter()
+                .map(|(x, width)| (x, 0, width, output.height_px))
+                .collect(),
+            Split::Vertical(weights) => divide(weights, output.height_px)?
+                .into_iter()
+                .map(|(y, height)| (0, y, output.width_px, height))
+                .collect(),
+            Split::Rects(rects) => rects.clone(),
+        };
+
+        if rects.is_empty() {
+            return Err(XrandrError::InvalidSplit("no monitors".to_string()));
+        }
+
+        rects
+            .into_iter()
+            .map(|(x, y, width, height)| {
+                let fits = width > 0
+                    && height > 0
+                    && x >= 0
+                    && y >= 0
+                    && i64::from(x) + i64::from(width) <= i64::from(output.width_px)
+                    && i64::from(y) + i64::from(height) <= i64::from(output.height_px);
+                if !fits {
+                    return Err(XrandrError::InvalidSplit(format!(
+                        "{}x{}+{}+{} is not within the output",
+                        width, height, x, y
+                    )));
+                }
+
+                Ok(MonitorGeometry {
+                    x: output.x + x,
+                    y: output.y + y,
+                    width_px: width,
+                    height_px: height,
+                    width_mm: scale(output.width_mm, width, output.width_px),
+                    height_mm: scale(output.height_mm, height, output.height_px),
+                })
+            })
+            .collect()
+    }
+}
+
+impl FromStr for Split {
+    type Err = XrandrError;
+
+    /// Parses `2 columns`, `3 rows`, `60/40 horizontal`, `50/50 vertical`,
+    /// or rectangles like xrandr's `--setmonitor`, e.g.
+    /// `1720x1440+0+0,1720x1440+1720+0`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || XrandrError::InvalidSplit(s.to_string());
+        let words: Vec<&str> = s.split_whitespace().collect();
+
+        let weights = |spec: &str| -> Result<Vec<u32>, XrandrError> {
+            spec.split('/')
+                .map(|w| w.parse().map_err(|_| invalid()))
+                .collect()
+        };
+        let rect = |spec: &str| -> Option<(i32, i32, i32, i32)> {
+            let (size, position) = spec.split_once('+')?;
+            let (width, height) = size.split_once('x')?;
+            let (x, y) = position.split_once('+')?;
+            Some((
+                x.parse().ok()?,
+                y.parse().ok()?,
+                width.parse().ok()?,
+                height.parse().ok()?,
+            ))
+        };
+
+        match words.as_slice() {
+            [n, "columns" | "column"] => Ok(Split::columns(n.parse().map_err(|_| invalid())?)),
+            [n, "rows" | "row"] => Ok(Split::rows(n.parse().map_err(|_| invalid())?)),
+            [spec, "horizontal"] => Ok(Split::Horizontal(weights(spec)?)),
+            [spec, "vertical"] => Ok(Split::Vertical(weights(spec)?)),
+            [spec] => spec
+                .split(',')
+                .map(|r| rect(r).ok_or_else(invalid))
+                .collect::<Result<_, _>>()
+                .map(Split::Rects),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The area `output` shows on `crtc`, which a split divides. The physical
+/// size of an output is that of the unrotated panel, so it is turned along
+/// with the pixels.
+#[cfg(feature = "xlib")]
+pub(crate) fn output_geometry(output: &Output, crtc: &Crtc) -> MonitorGeometry {
+    let (width, height) = crtc.screen_size();
+    let (width_mm, height_mm) = match crtc.rotation {
+        Rotation::Left | Rotation::Right => (output.mm_height, output.mm_width),
+        Rotation::Normal | Rotation::Inverted => (output.mm_width, output.mm_height),
+    };
+
+    MonitorGeometry {
+        x: crtc.x,
+        y: crtc.y,
+        width_px: i32::try_from(width).unwrap_or(i32::MAX),
+        height_px: i32::try_from(height).unwrap_or(i32::MAX),
+        width_mm: i32::try_from(width_mm).unwrap_or(i32::MAX),
+        height_mm: i32::try_from(height_mm).unwrap_or(i32::MAX),
+    }
+}
+
+/// The name of the `i`th (from 0) monitor of a split output
+#[cfg(feature = "xlib")]
+pub(crate) fn monitor_name(output: &str, i: usize) -> String {
+    format!("{}~{}", output, i + 1)
+}
+
+/// True iff `monitor` is one of the monitors a split of `output` created
+#[cfg(feature = "xlib")]
+pub(crate) fn is_part_of(monitor: &str, output: &str) -> bool {
+    monitor
+        .strip_prefix(output)
+        .and_then(|rest| rest.strip_prefix('~'))
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+}
+
+// Divides `total` pixels by `weights`, as (offset, size) pairs. The sizes add
+// up to `total` exactly.
+fn divide(weights: &[u32], total: i32) -> Result<Vec<(i32, i32)>, XrandrError> {
+    let sum: u64 = weights.iter().map(|w| u64::from(*w)).sum();
+    if weights.contains(&0) {
+        return Err(XrandrError::InvalidSplit("empty monitor".to_string()));
+    }
+
+    let mut cumulative = 0;
+    let mut start = 0;
+    Ok(weights
+        .iter()
+        .map(|w| {
+            cumulative += u64::from(*w);
+            let end = (cumulative as f64 * f64::from(total) / sum as f64).round() as i32;
+            let part = (start, end - start);
+            start = end;
+            part
+        })
+        .collect())
+}
+
+// `value` scaled by `part` / `whole`
+fn scale(value: i32, part: i32, whole: i32) -> i32 {
+    if whole == 0 {
+        return 0;
+    }
+    (f64::from(value) * f64::from(part) / f64::from(whole)).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "xlib")]
+    use crate::fixtures;
+
+    const ULTRAWIDE: MonitorGeometry = MonitorGeometry {
+        x: 1920,
+        y: 0,
+        width_px: 3440,
+        height_px: 1440,
+        width_mm: 800,
+        height_mm: 335,
+    };
+
+    #[test]
+    fn parses_split_specs() {
+        assert_eq!("2 columns".parse::<Split>().unwrap(), Split::columns(2));
+        assert_eq!("3 rows".parse::<Split>().unwrap(), Split::rows(3));
+        assert_eq!(
+            "60/40 horizontal".parse::<Split>().unwrap(),
+            Split::Horizontal(vec![60, 40])
+        );
+        assert_eq!(
+            "1720x1440+0+0,1720x1440+1720+0".parse::<Split>().unwrap(),
+            Split::Rects(vec![(0, 0, 1720, 1440), (1720, 0, 1720, 1440)])
+        );
+        assert!("2 diagonal".parse::<Split>().is_err());
+        assert!("60/x horizontal".parse::<Split>().is_err());
+    }
+
+    #[test]
+    fn splits_pixels_and_millimeters() {
+        let parts = Split::Horizontal(vec![60, 40])
+            .geometries(&ULTRAWIDE)
+            .unwrap();
+        assert_eq!(
+            parts
+                .iter()
+                .map(|g| (g.x, g.width_px, g.width_mm))
+                .collect::<Vec<_>>(),
+            vec![(1920, 2064, 480), (3984, 1376, 320)]
+        );
+        assert!(parts
+            .iter()
+            .all(|g| g.height_px == 1440 && g.height_mm == 335));
+
+        let rows = Split::rows(3).geometries(&ULTRAWIDE).unwrap();
+        assert_eq!(
+            rows.iter().map(|g| (g.y, g.height_px)).collect::<Vec<_>>(),
+            vec![(0, 480), (480, 480), (960, 480)]
+        );
+
+        assert!(Split::Rects(vec![(3000, 0, 500, 1440)])
+            .geometries(&ULTRAWIDE)
+            .is_err());
+        assert!(Split::columns(0).geometries(&ULTRAWIDE).is_err());
+        assert!(Split::columns(u32::MAX).geometries(&ULTRAWIDE).is_err());
+        assert_eq!(
+            "4000000000 rows".parse::<Split>().unwrap(),
+            Split::rows(MAX_PARTS)
+        );
+    }
+
+    #[cfg(feature = "xlib")]
+    #[test]
+    fn recognizes_split_monitors() {
+        assert_eq!(monitor_name("DP-1", 0), "DP-1~1");
+        assert!(is_part_of("DP-1~2", "DP-1"));
+        assert!(!is_part_of("DP-1~", "DP-1"));
+        assert!(!is_part_of("DP-10~1", "DP-1"));
+        assert!(!is_part_of("DP-1", "DP-1"));
+    }
+
+    #[cfg(feature = "xlib")]
+    #[test]
+    fn turns_rotated_outputs() {
+        let mut output = fixtures::output(0x42, "DP-1");
+        (output.mm_width, output.mm_height) = (344, 194);
+        let mut crtc = fixtures::crtc(0x40, 1920, 0, 1920, 1080);
+
+        let whole = output_geometry(&output, &crtc);
+        assert_eq!((whole.width_px, whole.height_px), (1920, 1080));
+        assert_eq!((whole.width_mm, whole.height_mm), (344, 194));
+
+        // The crtc size is that on screen already
+        crtc.rotation = Rotation::Left;
+        (crtc.width, crtc.height) = (1080, 1920);
+        let whole = output_geometry(&output, &crtc);
+        assert_eq!(
+            (whole.x, whole.width_px, whole.height_px),
+            (1920, 1080, 1920)
+        );
+        assert_eq!((whole.width_mm, whole.height_mm), (194, 344));
+
+        let halves = Split::rows(2).geometries(&whole).unwrap();
+        assert!(halves
+            .iter()
+            .all(|g| (g.width_mm, g.height_mm) == (194, 172)));
+    }
+}
@@ -0,0 +1,253 @@
+#[cfg(feature = "xlib")]
+use std::ffi::{CStr, CString};
+use std::os::raw::c_int;
+#[cfg(feature = "xlib")]
+use std::{ptr, slice};
+
+#[cfg(feature = "xlib")]
+use x11::xrender::XTransform;
+#[cfg(feature = "xlib")]
+use x11::{xlib, xrandr};
+
+#[cfg(feature = "xlib")]
+use crate::XHandle;
+#[cfg(feature = "xlib")]
+use crate::XId;
+#[cfg(feature = "xlib")]
+use crate::XrandrError;
+
+// A 16.16 fixed point number, Xrender's `XFixed`
+type XFixed = c_int;
+
+// A projective transformation of the image a crtc shows, applied after the
+// rotation. Used for scaling (xrandr's `--scale`/`--scale-from`), keystone
+// correction and reflection. The matrix maps crtc pixels to screen pixels and
+// is stored in 16.16 fixed point, like the x backend does.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Transform {
+    pub matrix: [[XFixed; 3]; 3],
+    /// Name of the filter used when sampling, e.g. `nearest` or `bilinear`
+    pub filter: String,
+    /// Parameters for `filter`, in 16.16 fixed point
+    pub params: Vec<XFixed>,
+}
+
+const IDENTITY: [[f64; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    /// The transformation that changes nothing
+    #[must_use]
+    pub fn identity() -> Self {
+        Self {
+            matrix: IDENTITY.map(|row| row.map(to_fixed)),
+            filter: "nearest".to_string(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Scales the image by `x` horizontally and `y` vertically, like
+    /// xrandr's `--scale xXy`. A scale of 2 shows twice as many pixels.
+    #[must_use]
+    pub fn scale(x: f64, y: f64) -> Self {
+        Self::from_matrix([[x, 0.0, 0.0], [0.0, y, 0.0], [0.0, 0.0, 1.0]])
+    }
+
+    /// Scales a `width` x `height` image so that it covers `target_width` x
+    /// `target_height` pixels of the screen, like xrandr's `--scale-from`
+    #[must_use]
+    pub fn scale_from(width: u32, height: u32, target_width: u32, target_height: u32) -> Self {
+        Self::scale(
+            f64::from(target_width) / f64::from(width),
+            f64::from(target_height) / f64::from(height),
+        )
+    }
+
+    /// An arbitrary transformation, like xrandr's `--transform`. Images that
+    /// are transformed get bilinear filtering.
+    #[must_use]
+    pub fn from_matrix(matrix: [[f64; 3]; 3]) -> Self {
+        let matrix = matrix.map(|row| row.map(to_fixed));
+
+        let mut transform = Self {
+            matrix,
+            filter: "nearest".to_string(),
+            params: Vec::new(),
+        };
+        if !transform.is_identity() {
+            transform.filter = "bilinear".to_string();
+        }
+        transform
+    }
+
+    /// The matrix as floating point numbers
+    #[must_use]
+    pub fn to_matrix(&self) -> [[f64; 3]; 3] {
+        self.matrix.map(|row| row.map(from_fixed))
+    }
+
+    #[must_use]
+    pub fn is_identity(&self) -> bool {
+        self.to_matrix() == IDENTITY
+    }
+
+    /// The size of the bounding box of a `width` x `height` image, after
+    /// applying this transformation
+    #[must_use]
+    pub fn transformed_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.is_identity() {
+            return (width, height);
+        }
+
+        let m = self.to_matrix();
+        let (w, h) = (f64::from(width), f64::from(height));
+        let corners = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)].map(|(x, y)| {
+            let z = m[2][0] * x + m[2][1] * y + m[2][2];
+            (
+                (m[0][0] * x + m[0][1] * y + m[0][2]) / z,
+                (m[1][0] * x + m[1][1] * y + m[1][2]) / z,
+            )
+        });
+
+        let extent = |values: [f64; 4]| {
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            (max - min).round() as u32
+        };
+
+        (
+            extent(corners.map(|(x, _)| x)),
+            extent(corners.map(|(_, y)| y)),
+        )
+    }
+
+    /// Gets the current transformation of a crtc. Falls back to the identity
+    /// if the x backend does not support transformations.
+    #[cfg(feature = "xlib")]
+    pub(crate) fn get(handle: &mut XHandle, crtc: XId) -> Self {
+        let mut attributes = ptr::null_mut();
+        let status =
+            unsafe { xrandr::XRRGetCrtcTransform(handle.sys.as_ptr(), crtc, &mut attributes) };
+
+        let attributes = match ptr::NonNull::new(attributes) {
+            Some(attributes) if status != 0 => attributes,
+            _ => return Self::identity(),
+        };
+
+        let xrandr::XRRCrtcTransformAttributes {
+            currentTransform,
+            currentFilter,
+            currentNparams,
+            currentParams,
+            ..
+        } = unsafe { attributes.as_ref() };
+
+        let filter = if currentFilter.is_null() {
+            String::new()
+        } else {
+            unsafe { CStr::from_ptr(*currentFilter) }
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        let params = if currentParams.is_null() {
+            Vec::new()
+        } else {
+            unsafe { slice::from_raw_parts(*currentParams, *currentNparams as usize) }.to_vec()
+        };
+
+        let transform = Self {
+            matrix: currentTransform.matrix,
+            filter,
+            params,
+        };
+
+        unsafe { xlib::XFree(attributes.as_ptr().cast()) };
+
+        transform
+    }
+
+    /// Sets this as the pending transformation of a crtc. It takes effect
+    /// with the next call to `XRRSetCrtcConfig`.
+    ///
+    /// # Errors
+    /// * `XrandrError::InvalidFilter(name)` - The filter contains a nul byte
+    ///
+    #[cfg(feature = "xlib")]
+    pub(crate) fn set(&self, handle: &mut XHandle, crtc: XId) -> Result<(), XrandrError> {
+        let mut transform = XTransform {
+            matrix: self.matrix,
+        };
+        let filter = CString::new(self.filter.as_str())
+            .map_err(|_| XrandrError::InvalidFilter(self.filter.clone()))?;
+        let mut params = self.params.clone();
+        let params_ptr = if params.is_empty() {
+            ptr::null_mut()
+        } else {
+            params.as_mut_ptr()
+        };
+
+        unsafe {
+            xrandr::XRRSetCrtcTransform(
+                handle.sys.as_ptr(),
+                crtc,
+                &mut transform,
+                filter.as_ptr(),
+                params_ptr,
+                i32::try_from(params.len()).unwrap(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn to_fixed(value: f64) -> XFixed {
+    (value * 65536.0).round() as XFixed
+}
+
+fn from_fixed(value: XFixed) -> f64 {
+    f64::from(value) / 65536.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_is_nearest() {
+        let identity = Transform::identity();
+        assert!(identity.is_identity());
+        assert_eq!(identity.filter, "nearest");
+        assert_eq!(Transform::scale(1.0, 1.0), identity);
+        assert_eq!(identity.transformed_size(1920, 1080), (1920, 1080));
+    }
+
+    #[test]
+    fn scales_size() {
+        let scale = Transform::scale(1.5, 2.0);
+        assert_eq!(scale.filter, "bilinear");
+        assert_eq!(scale.to_matrix()[0][0], 1.5);
+        assert_eq!(scale.transformed_size(1920, 1080), (2880, 2160));
+
+        let scale_from = Transform::scale_from(1920, 1080, 3840, 2160);
+        assert_eq!(scale_from, Transform::scale(2.0, 2.0));
+    }
+
+    #[test]
+    fn bounds_projective_transforms() {
+        // Reflect along the x axis, moving the image back into view
+        let reflect =
+            Transform::from_matrix([[-1.0, 0.0, 1920.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert_eq!(reflect.transformed_size(1920, 1080), (1920, 1080));
+
+        // Keystone: the bottom edge is wider than the top edge
+        let keystone =
+            Transform::from_matrix([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, -1.0 / 4096.0, 1.0]]);
+        assert_eq!(keystone.transformed_size(2048, 2048), (4096, 4096));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::crtc::{Crtc, Reflection, Rotation};
+use crate::screensize::ScreenSizeRange;
+use crate::XId;
+
+// Something that is wrong with a proposed set of crtcs. Applying a layout
+// with any of these either fails in the x backend, or leaves the user with a
+// screen they cannot use properly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// Two crtcs overlap without mirroring each other
+    Overlap(XId, XId),
+    /// These crtcs do not touch the rest of the layout, so the pointer
+    /// cannot move onto them
+    Unreachable(Vec<XId>),
+    /// The layout needs a larger screen than the x backend supports
+    ScreenTooLarge {
+        width: i64,
+        height: i64,
+        max_width: i32,
+        max_height: i32,
+    },
+    /// The layout needs a smaller screen than the x backend supports
+    ScreenTooSmall {
+        width: i64,
+        height: i64,
+        min_width: i32,
+        min_height: i32,
+    },
+    /// The crtc is set to a rotation it does not support
+    UnsupportedRotation(XId, Rotation),
+    /// The crtc is set to a reflection it does not support
+    UnsupportedReflection(XId, Reflection),
+}
+
+/// Checks a proposed set of crtcs for problems, without changing anything.
+/// Disabled crtcs are ignored. Positions do not have to be normalized.
+///
+/// # Arguments
+/// * `crtcs` - The crtcs as they would be applied
+/// * `range` - The screen sizes the x backend supports
+///
+/// # Examples
+/// ```ignore
+/// for diagnostic in validate(&crtcs, &range) {
+///     eprintln!("{:?}", diagnostic);
+/// }
+/// ```
+///
+#[must_use]
+pub fn validate(crtcs: &[Crtc], range: &ScreenSizeRange) -> Vec<Diagnostic> {
+    let enabled: Vec<&Crtc> = crtcs.iter().filter(|c| c.mode != 0).collect();
+    let mut diagnostics = Vec::new();
+
+    for crtc in &enabled {
+        if !crtc.rotations.contains(&crtc.rotation) {
+            diagnostics.push(Diagnostic::UnsupportedRotation(crtc.xid, crtc.rotation));
+        }
+        if !crtc.reflections.contains(&crtc.reflection) {
+            diagnostics.push(Diagnostic::UnsupportedReflection(crtc.xid, crtc.reflection));
+        }
+    }
+
+    for (a, b) in overlapping(&enabled) {
+        diagnostics.push(Diagnostic::Overlap(enabled[a].xid, enabled[b].xid));
+    }
+
+    // The largest island is the main screen, the others are unreachable
+    let mut islands = islands(&enabled);
+    let main = islands
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, island)| island.len())
+        .map(|(i, _)| i);
+    if let Some(main) = main {
+        islands.remove(main);
+    }
+    for island in islands {
+        let xids = island.iter().map(|i| enabled[*i].xid).collect();
+        diagnostics.push(Diagnostic::Unreachable(xids));
+    }
+
+    if let Some((width, height)) = extent(&enabled) {
+        if width > i64::from(range.max_width) || height > i64::from(range.max_height) {
+            diagnostics.push(Diagnostic::ScreenTooLarge {
+                width,
+                height,
+                max_width: range.max_width,
+                max_height: range.max_height,
+            });
+        }
+        if width < i64::from(range.min_width) || height < i64::from(range.min_height) {
+            diagnostics.push(Diagnostic::ScreenTooSmall {
+                width,
+                height,
+                min_width: range.min_width,
+                min_height: range.min_height,
+            });
+        }
+    }
+
+    diagnostics
+}
+
+/// Pairs of indices into `crtcs` that overlap. Crtcs that share their top
+/// left corner are mirrored (see `Relation::SameAs`) and may overlap.
+pub(crate) fn overlapping(crtcs: &[&Crtc]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    for (i, a) in crtcs.iter().enumerate() {
+        for (j, b) in crtcs.iter().enumerate().skip(i + 1) {
+            if a.xid != b.xid && (a.x, a.y) != (b.x, b.y) && overlap(a, b) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+/// Groups indices into `crtcs` by which crtcs the pointer can move between,
+/// i.e. crtcs that overlap or share (a part of) an edge. Groups are ordered
+/// by their first member.
+pub(crate) fn islands(crtcs: &[&Crtc]) -> Vec<Vec<usize>> {
+    let mut reached = vec![false; crtcs.len()];
+    let mut islands = Vec::new();
+
+    for start in 0..crtcs.len() {
+        if reached[start] {
+            continue;
+        }
+
+        let mut island = vec![start];
+        let mut todo = vec![start];
+        reached[start] = true;
+
+        while let Some(i) = todo.pop() {
+            for (j, other) in crtcs.iter().enumerate() {
+                if !reached[j] && (overlap(crtcs[i], other) || touch(crtcs[i], other)) {
+                    reached[j] = true;
+                    island.push(j);
+                    todo.push(j);
+                }
+            }
+        }
+
+        island.sort_unstable();
+        islands.push(island);
+    }
+
+    islands
+}
+
+// The area a crtc covers, as (left, top, right, bottom)
+fn bounds(c: &Crtc) -> (i64, i64, i64, i64) {
+    let (x, y) = (i64::from(c.x), i64::from(c.y));
+    let (width, height) = c.screen_size();
+    (x, y, x + i64::from(width), y + i64::from(height))
+}
+
+// Width and height of the screen needed to fit all crtcs
+fn extent(crtcs: &[&Crtc]) -> Option<(i64, i64)> {
+    let left = crtcs.iter().map(|c| bounds(c).0).min()?;
+    let top = crtcs.iter().map(|c| bounds(c).1).min()?;
+    let right = crtcs.iter().map(|c| bounds(c).2).max()?;
+    let bottom = crtcs.iter().map(|c| bounds(c).3).max()?;
+    Some((right - left, bottom - top))
+}
+
+fn overlap(a: &Crtc, b: &Crtc) -> bool {
+    let (a_l, a_t, a_r, a_b) = bounds(a);
+    let (b_l, b_t, b_r, b_b) = bounds(b);
+    a_l < b_r && b_l < a_r && a_t < b_b && b_t < a_b
+}
+
+// True iff a and b share (a part of) an edge
+fn touch(a: &Crtc, b: &Crtc) -> bool {
+    let (a_l, a_t, a_r, a_b) = bounds(a);
+    let (b_l, b_t, b_r, b_b) = bounds(b);
+
+    let shares_vertical = (a_r == b_l || b_r == a_l) && a_t < b_b && b_t < a_b;
+    let shares_horizontal = (a_b == b_t || b_b == a_t) && a_l < b_r && b_l < a_r;
+
+    shares_vertical || shares_horizontal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::crtc;
+
+    const RANGE: ScreenSizeRange = ScreenSizeRange {
+        min_width: 320,
+        min_height: 200,
+        max_width: 8192,
+        max_height: 8192,
+    };
+
+    #[test]
+    fn accepts_valid_layout() {
+        let crtcs = [
+            crtc(1, -1920, 0, 1920, 1080),
+            crtc(2, 0, 0, 2560, 1440),
+            crtc(3, 0, 0, 1920, 1080),
+        ];
+        assert_eq!(validate(&crtcs, &RANGE), vec![]);
+    }
+
+    #[test]
+    fn reports_overlaps_and_islands() {
+        let crtcs = [
+            crtc(1, 0, 0, 1920, 1080),
+            crtc(2, 1000, 500, 1920, 1080),
+            crtc(3, 5000, 0, 1920, 1080),
+        ];
+        assert_eq!(
+            validate(&crtcs, &RANGE),
+            vec![Diagnostic::Overlap(1, 2), Diagnostic::Unreachable(vec![3])]
+        );
+    }
+
+    #[test]
+    fn reports_size_and_rotation() {
+        let mut rotated = crtc(2, 7000, 0, 1440, 2560);
+        rotated.rotation = Rotation::Left;
+        rotated.rotations = vec![Rotation::Normal];
+        rotated.reflection = Reflection::X;
+
+        let crtcs = [crtc(1, 0, 0, 7000, 1080), rotated];
+        assert_eq!(
+            validate(&crtcs, &RANGE),
+            vec![
+                Diagnostic::UnsupportedRotation(2, Rotation::Left),
+                Diagnostic::UnsupportedReflection(2, Reflection::X),
+                Diagnostic::ScreenTooLarge {
+                    width: 8440,
+                    height: 2560,
+                    max_width: 8192,
+                    max_height: 8192,
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,739 @@
+use std::os::raw::c_ulong;
+
+use indexmap::IndexMap;
+use x11rb::connection::Connection as _;
+use x11rb::protocol::randr::{self, ConnectionExt as _};
+use x11rb::protocol::render;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Window};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use crate::crtc::{supported_reflections, supported_rotations, Crtc, Reflection, Rotation};
+use crate::mode::refresh_rate;
+use crate::output::property::{Range, Ranges, Supported, Value, Values};
+use crate::transform::Transform;
+use crate::{
+    Mode, Monitor, Output, Panning, Property, RandrBackend, ScreenResources, ScreenSize,
+    ScreenSizeRange, XId, XrandrError,
+};
+
+// The RandR version we need, for monitors
+const RANDR_VERSION: (u32, u32) = (1, 5);
+
+// A backend that speaks the X11 protocol itself through x11rb, instead of
+// going through Xlib. Protocol errors are returned instead of being fatal,
+// and the handle can be sent to other threads. Without the default `xlib`
+// feature, the crate does not link against libX11 and libXrandr at all.
+#[derive(Debug)]
+pub struct X11rbBackend {
+    conn: RustConnection,
+    root: Window,
+    // The screen size as we last saw it. The setup only has the initial
+    // one, later changes (also by other clients) arrive as events.
+    size: ScreenSize,
+}
+
+// Converts any x11rb error into ours
+fn protocol(error: impl std::fmt::Display) -> XrandrError {
+    XrandrError::Protocol(error.to_string())
+}
+
+// Xids are 32 bits on the wire
+fn wire(xid: XId) -> u32 {
+    xid as u32
+}
+
+impl X11rbBackend {
+    /// Connects to the display in `$DISPLAY`
+    ///
+    /// # Errors
+    /// * `XrandrError::Open` - Connecting failed
+    /// * `XrandrError::Protocol` - The x server does not support RandR 1.5
+    ///
+    pub fn open() -> Result<Self, XrandrError> {
+        let (conn, screen) = x11rb::connect(None).map_err(|_| XrandrError::Open)?;
+
+        let (major, minor) = RANDR_VERSION;
+        let version = conn
+            .randr_query_version(major, minor)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+        if (version.major_version, version.minor_version) < RANDR_VERSION {
+            return Err(XrandrError::Protocol(format!(
+                "RandR {}.{} is too old",
+                version.major_version, version.minor_version
+            )));
+        }
+
+        let setup = &conn.setup().roots[screen];
+        let root = setup.root;
+        let size = ScreenSize {
+            width: i32::from(setup.width_in_pixels),
+            width_mm: i32::from(setup.width_in_millimeters),
+            height: i32::from(setup.height_in_pixels),
+            height_mm: i32::from(setup.height_in_millimeters),
+        };
+
+        // Keeps the physical size up to date, see `screen_size`
+        conn.randr_select_input(root, randr::NotifyMask::SCREEN_CHANGE)
+            .map_err(protocol)?
+            .check()
+            .map_err(protocol)?;
+
+        Ok(Self { conn, root, size })
+    }
+
+    /// List every monitor
+    ///
+    /// # Errors
+    /// * `XrandrError::Protocol` - A request failed
+    ///
+    pub fn monitors(&mut self) -> Result<Vec<Monitor>, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_monitors(self.root, true)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        reply
+            .monitors
+            .into_iter()
+            .map(|m| {
+                Ok(Monitor {
+                    name: self.atom_name(m.name)?,
+                    is_primary: m.primary,
+                    is_automatic: m.automatic,
+                    x: i32::from(m.x),
+                    y: i32::from(m.y),
+                    width_px: i32::from(m.width),
+                    height_px: i32::from(m.height),
+                    width_mm: i32::try_from(m.width_in_millimeters).unwrap_or(i32::MAX),
+                    height_mm: i32::try_from(m.height_in_millimeters).unwrap_or(i32::MAX),
+                    outputs: m
+                        .outputs
+                        .iter()
+                        .map(|o| self.output_info(XId::from(*o)))
+                        .collect::<Result<_, _>>()?,
+                })
+            })
+            .collect()
+    }
+
+    fn atom_name(&self, atom: u32) -> Result<String, XrandrError> {
+        let reply = self
+            .conn
+            .get_atom_name(atom)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+        Ok(String::from_utf8_lossy(&reply.name).into_owned())
+    }
+
+    fn config_timestamp(&self) -> Result<u32, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_screen_resources(self.root)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+        Ok(reply.config_timestamp)
+    }
+
+    fn transform(&self, crtc: XId) -> Result<Transform, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_crtc_transform(wire(crtc))
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+        if !reply.has_transforms {
+            return Ok(Transform::identity());
+        }
+
+        let t = reply.current_transform;
+        Ok(Transform {
+            matrix: [
+                [t.matrix11, t.matrix12, t.matrix13],
+                [t.matrix21, t.matrix22, t.matrix23],
+                [t.matrix31, t.matrix32, t.matrix33],
+            ],
+            filter: String::from_utf8_lossy(&reply.current_filter_name).into_owned(),
+            params: reply.current_params,
+        })
+    }
+
+    fn panning(&self, crtc: XId) -> Result<Option<Panning>, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_panning(wire(crtc))
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        let panning = Panning {
+            left: i32::from(reply.left),
+            top: i32::from(reply.top),
+            width: u32::from(reply.width),
+            height: u32::from(reply.height),
+            track_left: i32::from(reply.track_left),
+            track_top: i32::from(reply.track_top),
+            track_width: u32::from(reply.track_width),
+            track_height: u32::from(reply.track_height),
+            border_left: i32::from(reply.border_left),
+            border_top: i32::from(reply.border_top),
+            border_right: i32::from(reply.border_right),
+            border_bottom: i32::from(reply.border_bottom),
+        };
+        Ok(panning.is_enabled().then_some(panning))
+    }
+
+    fn set_transform(&self, crtc: &Crtc) -> Result<(), XrandrError> {
+        let [[m11, m12, m13], [m21, m22, m23], [m31, m32, m33]] = crtc.transform.matrix;
+        let transform = render::Transform {
+            matrix11: m11,
+            matrix12: m12,
+            matrix13: m13,
+            matrix21: m21,
+            matrix22: m22,
+            matrix23: m23,
+            matrix31: m31,
+            matrix32: m32,
+            matrix33: m33,
+        };
+
+        self.conn
+            .randr_set_crtc_transform(
+                wire(crtc.xid),
+                transform,
+                crtc.transform.filter.as_bytes(),
+                &crtc.transform.params,
+            )
+            .map_err(protocol)?
+            .check()
+            .map_err(protocol)
+    }
+
+    fn set_panning(&self, crtc: XId, panning: &Panning) -> Result<(), XrandrError> {
+        let u = |v: u32| u16::try_from(v).map_err(|_| XrandrError::SetPanning(crtc));
+        let i = |v: i32| u16::try_from(v).map_err(|_| XrandrError::SetPanning(crtc));
+        let border = |v: i32| i16::try_from(v).map_err(|_| XrandrError::SetPanning(crtc));
+
+        let reply = self
+            .conn
+            .randr_set_panning(
+                wire(crtc),
+                x11rb::CURRENT_TIME,
+                i(panning.left)?,
+                i(panning.top)?,
+                u(panning.width)?,
+                u(panning.height)?,
+                i(panning.track_left)?,
+                i(panning.track_top)?,
+                u(panning.track_width)?,
+                u(panning.track_height)?,
+                border(panning.border_left)?,
+                border(panning.border_top)?,
+                border(panning.border_right)?,
+                border(panning.border_bottom)?,
+            )
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        if reply.status != randr::SetConfig::SUCCESS {
+            return Err(XrandrError::SetPanning(crtc));
+        }
+        Ok(())
+    }
+}
+
+impl RandrBackend for X11rbBackend {
+    fn screen_resources(&mut self) -> Result<ScreenResources, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_screen_resources(self.root)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        let mut names = reply.names.as_slice();
+        let modes: Vec<Mode> = reply
+            .modes
+            .iter()
+            .map(|m| {
+                let (name, rest) = names.split_at(usize::from(m.name_len).min(names.len()));
+                names = rest;
+                mode_from(m, name)
+            })
+            .collect();
+
+        Ok(ScreenResources {
+            timestamp: XId::from(reply.timestamp),
+            config_timestamp: XId::from(reply.config_timestamp),
+            ncrtc: i32::try_from(reply.crtcs.len()).unwrap_or(i32::MAX),
+            crtcs: reply.crtcs.iter().map(|c| XId::from(*c)).collect(),
+            outputs: reply.outputs.iter().map(|o| XId::from(*o)).collect(),
+            nmode: i32::try_from(modes.len()).unwrap_or(i32::MAX),
+            modes,
+        })
+    }
+
+    fn crtc_info(&mut self, xid: XId) -> Result<Crtc, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_crtc_info(wire(xid), x11rb::CURRENT_TIME)
+            .map_err(protocol)?
+            .reply()
+            .map_err(|_| XrandrError::GetCrtc(xid))?;
+
+        let bits = u16::from(reply.rotation);
+        let reflection = Reflection::from(bits);
+        let rotation = Rotation::try_from(bits)?;
+
+        // Like `Crtc::from_xid`, keep track of the size of the rotated mode
+        let transform = self.transform(xid)?;
+        let (width, height) = if transform.is_identity() || reply.mode == 0 {
+            (u32::from(reply.width), u32::from(reply.height))
+        } else {
+            let mode = self.screen_resources()?.mode(XId::from(reply.mode))?;
+            match rotation {
+                Rotation::Normal | Rotation::Inverted => (mode.width, mode.height),
+                Rotation::Left | Rotation::Right => (mode.height, mode.width),
+            }
+        };
+
+        Ok(Crtc {
+            xid,
+            timestamp: XId::from(reply.timestamp),
+            x: i32::from(reply.x),
+            y: i32::from(reply.y),
+            width,
+            height,
+            mode: XId::from(reply.mode),
+            rotation,
+            reflection,
+            outputs: reply.outputs.iter().map(|o| XId::from(*o)).collect(),
+            rotations: supported_rotations(u16::from(reply.rotations)),
+            reflections: supported_reflections(u16::from(reply.rotations)),
+            possible: reply.possible.iter().map(|o| XId::from(*o)).collect(),
+            transform,
+            panning: self.panning(xid)?,
+        })
+    }
+
+    fn output_info(&mut self, xid: XId) -> Result<Output, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_output_info(wire(xid), x11rb::CURRENT_TIME)
+            .map_err(protocol)?
+            .reply()
+            .map_err(|_| XrandrError::GetOutputInfo(xid))?;
+
+        let primary = self
+            .conn
+            .randr_get_output_primary(self.root)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        let modes: Vec<XId> = reply.modes.iter().map(|m| XId::from(*m)).collect();
+        let crtc = (reply.crtc != 0).then(|| XId::from(reply.crtc));
+        let current_mode = match crtc {
+            Some(crtc) => {
+                let mode = self.crtc_info(crtc)?.mode;
+                modes.contains(&mode).then_some(mode)
+            }
+            None => None,
+        };
+
+        Ok(Output {
+            xid,
+            properties: self.output_properties(xid)?,
+            timestamp: XId::from(reply.timestamp),
+            is_primary: XId::from(primary.output) == xid,
+            crtc,
+            name: String::from_utf8_lossy(&reply.name).into_owned(),
+            mm_width: u64::from(reply.mm_width),
+            mm_height: u64::from(reply.mm_height),
+            connected: reply.connection == randr::Connection::CONNECTED,
+            subpixel_order: u16::try_from(u32::from(reply.subpixel_order)).unwrap_or(0),
+            crtcs: reply.crtcs.iter().map(|c| XId::from(*c)).collect(),
+            clones: reply.clones.iter().map(|o| XId::from(*o)).collect(),
+            preferred_modes: modes[..usize::from(reply.num_preferred).min(modes.len())].to_vec(),
+            modes,
+            current_mode,
+        })
+    }
+
+    fn output_properties(&mut self, xid: XId) -> Result<IndexMap<String, Property>, XrandrError> {
+        let atoms = self
+            .conn
+            .randr_list_output_properties(wire(xid))
+            .map_err(protocol)?
+            .reply()
+            .map_err(|_| XrandrError::GetOutputProp(xid))?
+            .atoms;
+
+        let mut properties = IndexMap::new();
+        for atom in atoms {
+            let name = self.atom_name(atom)?;
+            let value = self
+                .conn
+                .randr_get_output_property(wire(xid), atom, AtomEnum::ANY, 0, 100, false, false)
+                .map_err(protocol)?
+                .reply()
+                .map_err(|_| XrandrError::GetOutputProp(xid))?;
+            let info = self
+                .conn
+                .randr_query_output_property(wire(xid), atom)
+                .map_err(protocol)?
+                .reply()
+                .map_err(|_| XrandrError::GetOutputProp(xid))?;
+
+            let mut atom_name = |atom| self.atom_name(atom);
+            let property = Property {
+                value: decode_value(
+                    &name,
+                    value.type_,
+                    value.format,
+                    &value.data,
+                    &mut atom_name,
+                )?,
+                values: decode_values(
+                    value.type_,
+                    value.format,
+                    info.range,
+                    &info.valid_values,
+                    &mut atom_name,
+                )?,
+                is_immutable: info.immutable,
+                is_pending: info.pending,
+                name,
+            };
+            properties.insert(property.name.clone(), property);
+        }
+        Ok(properties)
+    }
+
+    fn set_crtc_config(&mut self, crtc: &Crtc) -> Result<(), XrandrError> {
+        // The transform is only pending until the crtc is configured
+        if crtc.mode != 0 {
+            self.set_transform(crtc)?;
+        }
+
+        // An empty panning area turns panning off
+        if crtc.panning.is_none() && crtc.mode != 0 && self.panning(crtc.xid)?.is_some() {
+            self.set_panning(crtc.xid, &Panning::default())?;
+        }
+
+        let outputs: Vec<u32> = crtc.outputs.iter().map(|o| wire(*o)).collect();
+        let invalid = || XrandrError::InvalidCrtcConfig(crtc.xid);
+        let reply = self
+            .conn
+            .randr_set_crtc_config(
+                wire(crtc.xid),
+                x11rb::CURRENT_TIME,
+                self.config_timestamp()?,
+                i16::try_from(crtc.x).map_err(|_| invalid())?,
+                i16::try_from(crtc.y).map_err(|_| invalid())?,
+                wire(crtc.mode),
+                randr::Rotation::from(crtc.rotation as u16 | crtc.reflection as u16),
+                &outputs,
+            )
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        if reply.status != randr::SetConfig::SUCCESS {
+            return Err(invalid());
+        }
+
+        // Like the transform, panning needs the crtc to be configured, but
+        // here it has to be configured first
+        if let Some(panning) = &crtc.panning {
+            if crtc.mode != 0 {
+                self.set_panning(crtc.xid, panning)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn screen_size(&mut self) -> Result<ScreenSize, XrandrError> {
+        // A round trip, after which every earlier screen change has arrived
+        let geometry = self
+            .conn
+            .get_geometry(self.root)
+            .map_err(protocol)?
+            .reply()
+            .map_err(protocol)?;
+
+        // Only the events have the physical size. Like Xlib, they are
+        // swapped back for rotated screens.
+        while let Some(event) = self.conn.poll_for_event().map_err(protocol)? {
+            if let Event::RandrScreenChangeNotify(change) = event {
+                let rotated = Rotation::Left as u16 | Rotation::Right as u16;
+                let (mut width_mm, mut height_mm) = (change.mwidth, change.mheight);
+                if u16::from(change.rotation) & rotated != 0 {
+                    (width_mm, height_mm) = (height_mm, width_mm);
+                }
+                self.size.width_mm = i32::from(width_mm);
+                self.size.height_mm = i32::from(height_mm);
+            }
+        }
+
+        self.size.width = i32::from(geometry.width);
+        self.size.height = i32::from(geometry.height);
+        Ok(self.size)
+    }
+
+    fn screen_size_range(&mut self) -> Result<ScreenSizeRange, XrandrError> {
+        let reply = self
+            .conn
+            .randr_get_screen_size_range(self.root)
+            .map_err(protocol)?
+            .reply()
+            .map_err(|_| XrandrError::GetScreenSizeRange)?;
+
+        Ok(ScreenSizeRange {
+            min_width: i32::from(reply.min_width),
+            min_height: i32::from(reply.min_height),
+            max_width: i32::from(reply.max_width),
+            max_height: i32::from(reply.max_height),
+        })
+    }
+
+    fn set_screen_size(&mut self, size: &ScreenSize) -> Result<(), XrandrError> {
+        let too_large = || XrandrError::ScreenTooLarge {
+            width: size.width,
+            height: size.height,
+            max_width: i32::from(u16::MAX),
+            max_height: i32::from(u16::MAX),
+        };
+
+        self.conn
+            .randr_set_screen_size(
+                self.root,
+                u16::try_from(size.width).map_err(|_| too_large())?,
+                u16::try_from(size.height).map_err(|_| too_large())?,
+                u32::try_from(size.width_mm).unwrap_or(0),
+                u32::try_from(size.height_mm).unwrap_or(0),
+            )
+            .map_err(protocol)?
+            .check()
+            .map_err(protocol)?;
+
+        self.size = *size;
+        Ok(())
+    }
+
+    fn set_primary(&mut self, output: XId) -> Result<(), XrandrError> {
+        self.conn
+            .randr_set_output_primary(self.root, wire(output))
+            .map_err(protocol)?
+            .check()
+            .map_err(protocol)
+    }
+}
+
+fn mode_from(info: &randr::ModeInfo, name: &[u8]) -> Mode {
+    let flags = u64::from(u32::from(info.mode_flags));
+    let (htotal, vtotal) = (u32::from(info.htotal), u32::from(info.vtotal));
+
+    Mode {
+        xid: XId::from(info.id),
+        width: u32::from(info.width),
+        height: u32::from(info.height),
+        dot_clock: u64::from(info.dot_clock),
+        hsync_tart: u32::from(info.hsync_start),
+        hsync_end: u32::from(info.hsync_end),
+        htotal,
+        hskew: u32::from(info.hskew),
+        vsync_start: u32::from(info.vsync_start),
+        vsync_end: u32::from(info.vsync_end),
+        vtotal,
+        name: String::from_utf8_lossy(name).into_owned(),
+        flags,
+        rate: refresh_rate(u64::from(info.dot_clock), htotal, vtotal, flags),
+    }
+}
+
+// Splits `data` into items of `N` bytes, in the byte order of the
+// connection (x11rb always uses the native one)
+fn items<const N: usize, T>(data: &[u8], from: impl Fn([u8; N]) -> T) -> Vec<T> {
+    data.chunks_exact(N)
+        .map(|chunk| from(chunk.try_into().expect("chunks have N bytes")))
+        .collect()
+}
+
+// Decodes the value of the property called `name`, like `Property::get`
+fn decode_value(
+    name: &str,
+    value_type: u32,
+    format: u8,
+    data: &[u8],
+    atom_name: &mut impl FnMut(u32) -> Result<String, XrandrError>,
+) -> Result<Value, XrandrError> {
+    if name == "EDID" {
+        return Ok(Value::Edid(data.to_vec()));
+    }
+    if name == "GUID" {
+        if let Ok(guid) = data.try_into() {
+            return Ok(Value::Guid(guid));
+        }
+    }
+
+    let (atom, integer, cardinal) = (
+        u32::from(AtomEnum::ATOM),
+        u32::from(AtomEnum::INTEGER),
+        u32::from(AtomEnum::CARDINAL),
+    );
+
+    Ok(match (value_type, format) {
+        (t, 32) if t == atom => {
+            let atoms = items(data, u32::from_ne_bytes);
+            Value::Atom(match atoms.first() {
+                Some(atom) => atom_name(*atom)?,
+                None => String::new(),
+            })
+        }
+        (t, 8) if t == integer => Value::Integer8(items(data, i8::from_ne_bytes)),
+        (t, 16) if t == integer => Value::Integer16(items(data, i16::from_ne_bytes)),
+        (t, 32) if t == integer => Value::Integer32(items(data, i32::from_ne_bytes)),
+        (t, 8) if t == cardinal => Value::Cardinal8(data.to_vec()),
+        (t, 16) if t == cardinal => Value::Cardinal16(items(data, u16::from_ne_bytes)),
+        (t, 32) if t == cardinal => Value::Cardinal32(items(data, u32::from_ne_bytes)),
+        _ => Value::Unrecognized {
+            value_type: c_ulong::from(value_type),
+            format: i32::from(format),
+        },
+    })
+}
+
+// Decodes the valid values of a property, which the protocol always sends as
+// 32 bit items, like `Property::get`
+fn decode_values(
+    value_type: u32,
+    format: u8,
+    range: bool,
+    values: &[i32],
+    atom_name: &mut impl FnMut(u32) -> Result<String, XrandrError>,
+) -> Result<Option<Values>, XrandrError> {
+    fn ranges<T>(values: &[i32], f: impl Fn(i32) -> T) -> Vec<Range<T>> {
+        values
+            .chunks_exact(2)
+            .map(|pair| Range {
+                lower: f(pair[0]),
+                upper: f(pair[1]),
+            })
+            .collect()
+    }
+
+    if values.is_empty() {
+        return Ok(None);
+    }
+
+    let (atom, integer, cardinal) = (
+        u32::from(AtomEnum::ATOM),
+        u32::from(AtomEnum::INTEGER),
+        u32::from(AtomEnum::CARDINAL),
+    );
+
+    // Narrowing casts keep the bits that the format has, like the Xlib path
+    let values = match (value_type, format, range) {
+        (t, _, true) if t == atom => Values::Range(Ranges::Atom(
+            values
+                .chunks_exact(2)
+                .map(|pair| {
+                    Ok(Range {
+                        lower: atom_name(pair[0] as u32)?,
+                        upper: atom_name(pair[1] as u32)?,
+                    })
+                })
+                .collect::<Result<_, XrandrError>>()?,
+        )),
+        (t, _, false) if t == atom => Values::Supported(Supported::Atom(
+            values
+                .iter()
+                .map(|v| atom_name(*v as u32))
+                .collect::<Result<_, _>>()?,
+        )),
+        (t, 8, true) if t == integer => Ranges::Integer8(ranges(values, |v| v as i8)).into(),
+        (t, 16, true) if t == integer => Ranges::Integer16(ranges(values, |v| v as i16)).into(),
+        (t, 32, true) if t == integer => Ranges::Integer32(ranges(values, |v| v)).into(),
+        (t, 8, true) if t == cardinal => Ranges::Cardinal8(ranges(values, |v| v as u8)).into(),
+        (t, 16, true) if t == cardinal => Ranges::Cardinal16(ranges(values, |v| v as u16)).into(),
+        (t, 32, true) if t == cardinal => Ranges::Cardinal32(ranges(values, |v| v as u32)).into(),
+        (t, 8, false) if t == integer => {
+            Supported::Integer8(values.iter().map(|v| *v as i8).collect()).into()
+        }
+        (t, 16, false) if t == integer => {
+            Supported::Integer16(values.iter().map(|v| *v as i16).collect()).into()
+        }
+        (t, 32, false) if t == integer => Supported::Integer32(values.to_vec()).into(),
+        (t, 8, false) if t == cardinal => {
+            Supported::Cardinal8(values.iter().map(|v| *v as u8).collect()).into()
+        }
+        (t, 16, false) if t == cardinal => {
+            Supported::Cardinal16(values.iter().map(|v| *v as u16).collect()).into()
+        }
+        (t, 32, false) if t == cardinal => {
+            Supported::Cardinal32(values.iter().map(|v| *v as u32).collect()).into()
+        }
+        _ => Values::Unrecognized {
+            value_type: c_ulong::from(value_type),
+            format: i32::from(format),
+        },
+    };
+    Ok(Some(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn atom_name(atom: u32) -> Result<String, XrandrError> {
+        Ok(format!("atom{}", atom))
+    }
+
+    #[test]
+    fn is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<X11rbBackend>();
+    }
+
+    #[test]
+    fn decodes_properties() {
+        let integer = u32::from(AtomEnum::INTEGER);
+        let data: Vec<u8> = [30i32, -1].iter().flat_map(|v| v.to_ne_bytes()).collect();
+
+        let value = decode_value("Backlight", integer, 32, &data, &mut atom_name).unwrap();
+        assert!(matches!(value, Value::Integer32(v) if v == vec![30, -1]));
+
+        let values = decode_values(integer, 32, true, &[0, 100], &mut atom_name).unwrap();
+        assert!(matches!(
+            values,
+            Some(Values::Range(Ranges::Integer32(r))) if r[0].lower == 0 && r[0].upper == 100
+        ));
+
+        let atom = u32::from(AtomEnum::ATOM);
+        let value = decode_value(
+            "scaling mode",
+            atom,
+            32,
+            &7u32.to_ne_bytes(),
+            &mut atom_name,
+        );
+        assert!(matches!(value, Ok(Value::Atom(name)) if name == "atom7"));
+
+        let values = decode_values(atom, 32, false, &[7, 8], &mut atom_name).unwrap();
+        assert!(matches!(
+            values,
+            Some(Values::Supported(Supported::Atom(names))) if names == ["atom7", "atom8"]
+        ));
+
+        let edid = decode_value("EDID", integer, 8, &[0, 255], &mut atom_name).unwrap();
+        assert!(matches!(edid, Value::Edid(bytes) if bytes == vec![0, 255]));
+    }
+}